@@ -0,0 +1,6 @@
+CREATE TABLE todos (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    description TEXT NOT NULL,
+    completed INTEGER NOT NULL DEFAULT 0,
+    editing INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,7 @@
+-- the add_employees directory: a department is there as long as someone works in it
+CREATE TABLE employees (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    department TEXT NOT NULL,
+    name TEXT NOT NULL
+);
+CREATE INDEX employees_department ON employees (department, name);
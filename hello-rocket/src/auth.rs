@@ -0,0 +1,226 @@
+use crate::db::Users;
+use crate::error::ApiError;
+use argon2::password_hash::rand_core::OsRng;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use rand::RngCore;
+use rocket::http::{Cookie, CookieJar, Status};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::status;
+use rocket::serde::json::{self, Json};
+use rocket::tokio::task::spawn_blocking;
+use rocket::State;
+use rocket_okapi::openapi;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// private cookies are encrypted with the secret_key, the client can neither read nor forge them
+const SESSION_COOKIE: &str = "user_id";
+
+// whoever sent the request, through a session cookie or an `Authorization: Bearer` token.
+// routes taking a User answer 401 to anyone else.
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct User {
+    pub id: u64,
+    pub username: String,
+}
+
+// an API token as listed, the secret itself is only shown when the token is created
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Token {
+    pub id: u64,
+    pub name: String,
+    // unix time in seconds
+    pub created_at: u64,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct NewToken {
+    #[serde(flatten)]
+    pub token: Token,
+    pub secret: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct Credentials {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TokenRequest {
+    pub name: String,
+}
+
+fn validate(credentials: &Credentials) -> Result<(), ApiError> {
+    let username = &credentials.username;
+    let valid_username = (3..=32).contains(&username.len())
+        && username.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_username {
+        return Err(ApiError::invalid("invalid_username", "usernames are 3 to 32 letters, digits, _ or -"));
+    }
+    if credentials.password.chars().count() < 8 {
+        return Err(ApiError::invalid("invalid_password", "passwords are at least 8 characters"));
+    }
+    Ok(())
+}
+
+// argon2 is slow on purpose, it runs on a blocking thread
+async fn hash_password(password: String) -> Result<String, ApiError> {
+    let hashed = spawn_blocking(move || {
+        let salt = SaltString::generate(&mut OsRng);
+        Argon2::default().hash_password(password.as_bytes(), &salt).map(|hash| hash.to_string())
+    })
+    .await;
+    match hashed {
+        Ok(Ok(hash)) => Ok(hash),
+        _ => Err(ApiError::from_status(Status::InternalServerError)),
+    }
+}
+
+// stands in for the hash of a user that does not exist, so a login for an unknown username
+// takes as long as a wrong password and does not tell which usernames are taken
+const NO_USER_HASH: &str =
+    concat!("$argon2id$v=19$m=19456,t=2,p=1$", "bm8gc3VjaCB1c2VyLi4uLg$VqED7xrFCVU7RZ0WubKS8DBqOVLvG4YWa6L6iboNXkE");
+
+async fn verify_password(password: String, hash: String) -> bool {
+    spawn_blocking(move || match PasswordHash::new(&hash) {
+        Ok(hash) => Argon2::default().verify_password(password.as_bytes(), &hash).is_ok(),
+        Err(_) => false,
+    })
+    .await
+    .unwrap_or(false)
+}
+
+// tokens are 256 random bits, a plain hash is enough to keep them out of the database
+pub(crate) fn hash_token(secret: &str) -> String {
+    hex::encode(Sha256::digest(secret.as_bytes()))
+}
+
+fn new_secret() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn start_session(cookies: &CookieJar<'_>, user: &User) {
+    cookies.add_private(Cookie::new(SESSION_COOKIE, user.id.to_string()));
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = ();
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, ()> {
+        let users = match request.guard::<&State<Users>>().await {
+            Outcome::Success(users) => users,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        let user = match request.headers().get_one("Authorization") {
+            Some(authorization) => match authorization.strip_prefix("Bearer ") {
+                Some(secret) => users.token_user(&hash_token(secret.trim())).await,
+                None => Ok(None),
+            },
+            None => match request.cookies().get_private(SESSION_COOKIE).and_then(|cookie| cookie.value().parse().ok()) {
+                Some(id) => users.user(id).await,
+                None => Ok(None),
+            },
+        };
+        match user {
+            Ok(Some(user)) => Outcome::Success(user),
+            Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+            Err(e) => Outcome::Error((ApiError::from(e).status, ())),
+        }
+    }
+}
+
+// signs up and logs in right away
+#[openapi(tag = "Users")]
+#[post("/users", format = "json", data = "<credentials>")]
+pub async fn register(
+    credentials: Result<Json<Credentials>, json::Error<'_>>,
+    users: &State<Users>,
+    cookies: &CookieJar<'_>,
+) -> Result<status::Created<Json<User>>, ApiError> {
+    let credentials = credentials?;
+    validate(&credentials)?;
+    let hash = hash_password(credentials.password.clone()).await?;
+    let user = users
+        .create_user(&credentials.username, &hash)
+        .await?
+        .ok_or_else(|| ApiError::new(Status::Conflict, "username_taken", "the username is taken"))?;
+    start_session(cookies, &user);
+    Ok(status::Created::new("/api/session").body(Json(user)))
+}
+
+#[openapi(tag = "Users")]
+#[post("/session", format = "json", data = "<credentials>")]
+pub async fn login(
+    credentials: Result<Json<Credentials>, json::Error<'_>>,
+    users: &State<Users>,
+    cookies: &CookieJar<'_>,
+) -> Result<Json<User>, ApiError> {
+    let credentials = credentials?;
+    // the same answer whether the username or the password is wrong
+    let wrong = || ApiError::unauthorized("wrong username or password");
+    let (user, hash) = match users.login(&credentials.username).await? {
+        Some((user, hash)) => (Some(user), hash),
+        None => (None, NO_USER_HASH.to_string()),
+    };
+    let verified = verify_password(credentials.password.clone(), hash).await;
+    match user {
+        Some(user) if verified => {
+            start_session(cookies, &user);
+            Ok(Json(user))
+        }
+        _ => Err(wrong()),
+    }
+}
+
+#[openapi(tag = "Users")]
+#[get("/session")]
+pub fn me(user: User) -> Json<User> {
+    Json(user)
+}
+
+#[openapi(tag = "Users")]
+#[delete("/session")]
+pub fn logout(cookies: &CookieJar<'_>) -> status::NoContent {
+    cookies.remove_private(SESSION_COOKIE);
+    status::NoContent
+}
+
+#[openapi(tag = "Users")]
+#[get("/tokens")]
+pub async fn tokens(user: User, users: &State<Users>) -> Result<Json<Vec<Token>>, ApiError> {
+    Ok(Json(users.tokens(user.id).await?))
+}
+
+// the secret is in the response and nowhere else, the database keeps its hash
+#[openapi(tag = "Users")]
+#[post("/tokens", format = "json", data = "<request>")]
+pub async fn create_token(
+    user: User,
+    request: Result<Json<TokenRequest>, json::Error<'_>>,
+    users: &State<Users>,
+) -> Result<status::Created<Json<NewToken>>, ApiError> {
+    let request = request?;
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(ApiError::invalid("empty_name", "the token name is empty"));
+    }
+    let secret = new_secret();
+    let token = users.create_token(user.id, name, &hash_token(&secret)).await?;
+    Ok(status::Created::new(format!("/api/tokens/{}", token.id)).body(Json(NewToken { token, secret })))
+}
+
+#[openapi(tag = "Users")]
+#[delete("/tokens/<id>")]
+pub async fn delete_token(id: u64, user: User, users: &State<Users>) -> Result<status::NoContent, ApiError> {
+    if users.delete_token(user.id, id).await? {
+        Ok(status::NoContent)
+    } else {
+        Err(ApiError::not_found(format!("no token {}", id)))
+    }
+}
@@ -0,0 +1,153 @@
+use super::{DbError, DepartmentRepository, TodoRepository, UserRepository};
+use crate::auth::{Token, User};
+use crate::todos::{Entry, Todo};
+use rocket::async_trait;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// keeps everything in process memory, for tests and throwaway servers
+#[derive(Debug, Default)]
+pub struct MemoryRepository {
+    todos: Mutex<TodoList>,
+    users: Mutex<UserList>,
+    // names in the order they were added
+    departments: Mutex<BTreeMap<String, Vec<String>>>,
+}
+
+#[derive(Debug, Default)]
+struct TodoList {
+    next_id: u64,
+    // with the id of their user
+    todos: Vec<(u64, Todo)>,
+}
+
+impl TodoList {
+    fn of(&self, user: u64) -> Vec<Todo> {
+        self.todos.iter().filter(|(owner, _)| *owner == user).map(|(_, todo)| todo.clone()).collect()
+    }
+}
+
+#[derive(Debug, Default)]
+struct UserList {
+    // with their password hash
+    users: Vec<(User, String)>,
+    next_token_id: u64,
+    // with their user and hash
+    tokens: Vec<(u64, Token, String)>,
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[async_trait]
+impl TodoRepository for MemoryRepository {
+    async fn list(&self, user: u64) -> Result<Vec<Todo>, DbError> {
+        Ok(self.todos.lock().unwrap().of(user))
+    }
+
+    async fn get(&self, user: u64, id: u64) -> Result<Option<Todo>, DbError> {
+        Ok(self.todos.lock().unwrap().of(user).into_iter().find(|todo| todo.id == id))
+    }
+
+    async fn create(&self, user: u64, entry: Entry) -> Result<Todo, DbError> {
+        let mut list = self.todos.lock().unwrap();
+        list.next_id += 1;
+        let todo = Todo { id: list.next_id, entry };
+        list.todos.push((user, todo.clone()));
+        Ok(todo)
+    }
+
+    async fn update(&self, user: u64, id: u64, entry: Entry) -> Result<Option<Todo>, DbError> {
+        let mut list = self.todos.lock().unwrap();
+        Ok(list.todos.iter_mut().find(|(owner, todo)| *owner == user && todo.id == id).map(|(_, todo)| {
+            todo.entry = entry;
+            todo.clone()
+        }))
+    }
+
+    async fn delete(&self, user: u64, id: u64) -> Result<bool, DbError> {
+        let mut list = self.todos.lock().unwrap();
+        let before = list.todos.len();
+        list.todos.retain(|(owner, todo)| !(*owner == user && todo.id == id));
+        Ok(list.todos.len() < before)
+    }
+
+    async fn clear_completed(&self, user: u64) -> Result<Vec<Todo>, DbError> {
+        let mut list = self.todos.lock().unwrap();
+        list.todos.retain(|(owner, todo)| !(*owner == user && todo.entry.completed));
+        Ok(list.of(user))
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn create_user(&self, username: &str, password_hash: &str) -> Result<Option<User>, DbError> {
+        let mut list = self.users.lock().unwrap();
+        if list.users.iter().any(|(user, _)| user.username == username) {
+            return Ok(None);
+        }
+        let user = User { id: list.users.len() as u64 + 1, username: username.to_string() };
+        list.users.push((user.clone(), password_hash.to_string()));
+        Ok(Some(user))
+    }
+
+    async fn user(&self, id: u64) -> Result<Option<User>, DbError> {
+        Ok(self.users.lock().unwrap().users.iter().find(|(user, _)| user.id == id).map(|(user, _)| user.clone()))
+    }
+
+    async fn login(&self, username: &str) -> Result<Option<(User, String)>, DbError> {
+        Ok(self.users.lock().unwrap().users.iter().find(|(user, _)| user.username == username).cloned())
+    }
+
+    async fn create_token(&self, user: u64, name: &str, token_hash: &str) -> Result<Token, DbError> {
+        let mut list = self.users.lock().unwrap();
+        list.next_token_id += 1;
+        let token = Token { id: list.next_token_id, name: name.to_string(), created_at: now() };
+        list.tokens.push((user, token.clone(), token_hash.to_string()));
+        Ok(token)
+    }
+
+    async fn tokens(&self, user: u64) -> Result<Vec<Token>, DbError> {
+        let list = self.users.lock().unwrap();
+        Ok(list.tokens.iter().filter(|(owner, _, _)| *owner == user).map(|(_, token, _)| token.clone()).collect())
+    }
+
+    async fn delete_token(&self, user: u64, id: u64) -> Result<bool, DbError> {
+        let mut list = self.users.lock().unwrap();
+        let before = list.tokens.len();
+        list.tokens.retain(|(owner, token, _)| !(*owner == user && token.id == id));
+        Ok(list.tokens.len() < before)
+    }
+
+    async fn token_user(&self, token_hash: &str) -> Result<Option<User>, DbError> {
+        let owner = self.users.lock().unwrap().tokens.iter().find(|(_, _, hash)| hash == token_hash).map(|(owner, _, _)| *owner);
+        match owner {
+            Some(owner) => self.user(owner).await,
+            None => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl DepartmentRepository for MemoryRepository {
+    async fn add_employee(&self, department: &str, name: &str) -> Result<(), DbError> {
+        self.departments.lock().unwrap().entry(department.to_string()).or_default().push(name.to_string());
+        Ok(())
+    }
+
+    async fn departments(&self) -> Result<BTreeMap<String, Vec<String>>, DbError> {
+        let mut departments = self.departments.lock().unwrap().clone();
+        departments.values_mut().for_each(|names| names.sort());
+        Ok(departments)
+    }
+
+    async fn employees(&self, department: &str) -> Result<Option<Vec<String>>, DbError> {
+        let mut names = self.departments.lock().unwrap().get(department).cloned();
+        if let Some(names) = &mut names {
+            names.sort();
+        }
+        Ok(names)
+    }
+}
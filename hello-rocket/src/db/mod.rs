@@ -0,0 +1,88 @@
+use crate::auth::{Token, User};
+use crate::todos::{Entry, Todo};
+use rocket::async_trait;
+use rocket::tokio::task::JoinError;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+pub mod memory;
+pub mod sqlite;
+
+pub use memory::MemoryRepository;
+pub use sqlite::{Pool, SqliteRepository};
+
+// a storage failure, the details are for the log and not for the client
+#[derive(Debug)]
+pub struct DbError(pub String);
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "database error: {}", self.0)
+    }
+}
+
+impl Error for DbError {}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(e: rusqlite::Error) -> Self {
+        DbError(e.to_string())
+    }
+}
+
+impl From<r2d2::Error> for DbError {
+    fn from(e: r2d2::Error) -> Self {
+        DbError(e.to_string())
+    }
+}
+
+impl From<JoinError> for DbError {
+    fn from(e: JoinError) -> Self {
+        DbError(e.to_string())
+    }
+}
+
+// where the todos are kept, each belongs to the user who created it. lists come back in the order
+// the todos were created.
+#[async_trait]
+pub trait TodoRepository: Send + Sync {
+    async fn list(&self, user: u64) -> Result<Vec<Todo>, DbError>;
+    async fn get(&self, user: u64, id: u64) -> Result<Option<Todo>, DbError>;
+    async fn create(&self, user: u64, entry: Entry) -> Result<Todo, DbError>;
+    // None if the user has no todo `id`
+    async fn update(&self, user: u64, id: u64, entry: Entry) -> Result<Option<Todo>, DbError>;
+    // whether the user had a todo `id`
+    async fn delete(&self, user: u64, id: u64) -> Result<bool, DbError>;
+    // deletes the user's completed todos and returns the rest
+    async fn clear_completed(&self, user: u64) -> Result<Vec<Todo>, DbError>;
+}
+
+// accounts and their API tokens. passwords and tokens arrive here hashed.
+#[async_trait]
+pub trait UserRepository: Send + Sync {
+    // None if the username is taken
+    async fn create_user(&self, username: &str, password_hash: &str) -> Result<Option<User>, DbError>;
+    async fn user(&self, id: u64) -> Result<Option<User>, DbError>;
+    // the user and their password hash
+    async fn login(&self, username: &str) -> Result<Option<(User, String)>, DbError>;
+    async fn create_token(&self, user: u64, name: &str, token_hash: &str) -> Result<Token, DbError>;
+    async fn tokens(&self, user: u64) -> Result<Vec<Token>, DbError>;
+    // whether the user had a token `id`
+    async fn delete_token(&self, user: u64, id: u64) -> Result<bool, DbError>;
+    async fn token_user(&self, token_hash: &str) -> Result<Option<User>, DbError>;
+}
+
+// the company directory of collections/add_employees, a list of names per department. listings
+// are sorted by department and then by name.
+#[async_trait]
+pub trait DepartmentRepository: Send + Sync {
+    async fn add_employee(&self, department: &str, name: &str) -> Result<(), DbError>;
+    async fn departments(&self) -> Result<BTreeMap<String, Vec<String>>, DbError>;
+    // None if nobody works in the department
+    async fn employees(&self, department: &str) -> Result<Option<Vec<String>>, DbError>;
+}
+
+// the repositories the routes use, managed as Rocket state
+pub type Todos = Box<dyn TodoRepository>;
+pub type Users = Box<dyn UserRepository>;
+pub type Departments = Box<dyn DepartmentRepository>;
@@ -0,0 +1,326 @@
+use super::{DbError, DepartmentRepository, TodoRepository, UserRepository};
+use crate::auth::{Token, User};
+use crate::todos::{Entry, Todo};
+use r2d2_sqlite::SqliteConnectionManager;
+use rocket::async_trait;
+use rocket::tokio::task::spawn_blocking;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub type Pool = r2d2::Pool<SqliteConnectionManager>;
+
+// schema changes in the order they were made. PRAGMA user_version counts the ones a database
+// has, so each runs once.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../../migrations/001_create_todos.sql"),
+    include_str!("../../migrations/002_create_users.sql"),
+    include_str!("../../migrations/003_create_employees.sql"),
+];
+
+// opens the database file at `path`, creating it if needed, and brings its schema up to date
+pub fn connect(path: &str) -> Result<Pool, DbError> {
+    // SQLite leaves foreign keys off unless every connection asks for them
+    let manager = SqliteConnectionManager::file(path).with_init(|conn| conn.execute_batch("PRAGMA foreign_keys = ON;"));
+    let pool = r2d2::Pool::new(manager)?;
+    let mut conn = pool.get()?;
+    migrate(&mut conn)?;
+    Ok(pool)
+}
+
+pub fn migrate(conn: &mut Connection) -> Result<(), DbError> {
+    let applied: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (idx, migration) in MIGRATIONS.iter().enumerate().skip(applied) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", idx + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+// every query runs on a blocking thread, SQLite calls would stall the async workers
+pub struct SqliteRepository {
+    pool: Pool,
+}
+
+impl SqliteRepository {
+    pub fn new(pool: Pool) -> Self {
+        SqliteRepository { pool }
+    }
+
+    async fn run<T, F>(&self, query: F) -> Result<T, DbError>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T, DbError> + Send + 'static,
+    {
+        let pool = self.pool.clone();
+        spawn_blocking(move || query(&mut *pool.get()?)).await?
+    }
+}
+
+const COLUMNS: &str = "id, description, completed, editing";
+
+fn todo(row: &Row) -> rusqlite::Result<Todo> {
+    Ok(Todo {
+        id: row.get(0)?,
+        entry: Entry { description: row.get(1)?, completed: row.get(2)?, editing: row.get(3)? },
+    })
+}
+
+fn all(conn: &Connection, user: u64) -> Result<Vec<Todo>, DbError> {
+    let mut statement = conn.prepare(&format!("SELECT {} FROM todos WHERE user_id = ?1 ORDER BY id", COLUMNS))?;
+    let todos = statement.query_map([user], todo)?.collect::<Result<_, _>>()?;
+    Ok(todos)
+}
+
+fn user(row: &Row) -> rusqlite::Result<User> {
+    Ok(User { id: row.get(0)?, username: row.get(1)? })
+}
+
+fn token(row: &Row) -> rusqlite::Result<Token> {
+    Ok(Token { id: row.get(0)?, name: row.get(1)?, created_at: row.get(2)? })
+}
+
+// SQLite rowids are i64, an id beyond that names no row and must not reach a query
+fn in_range(id: u64) -> bool {
+    id <= i64::MAX as u64
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+#[async_trait]
+impl TodoRepository for SqliteRepository {
+    async fn list(&self, user: u64) -> Result<Vec<Todo>, DbError> {
+        self.run(move |conn| all(conn, user)).await
+    }
+
+    async fn get(&self, user: u64, id: u64) -> Result<Option<Todo>, DbError> {
+        if !in_range(id) {
+            return Ok(None);
+        }
+        self.run(move |conn| {
+            let sql = format!("SELECT {} FROM todos WHERE id = ?1 AND user_id = ?2", COLUMNS);
+            Ok(conn.query_row(&sql, [id, user], todo).optional()?)
+        })
+        .await
+    }
+
+    async fn create(&self, user: u64, entry: Entry) -> Result<Todo, DbError> {
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT INTO todos (description, completed, editing, user_id) VALUES (?1, ?2, ?3, ?4)",
+                params![entry.description, entry.completed, entry.editing, user],
+            )?;
+            Ok(Todo { id: conn.last_insert_rowid() as u64, entry })
+        })
+        .await
+    }
+
+    async fn update(&self, user: u64, id: u64, entry: Entry) -> Result<Option<Todo>, DbError> {
+        if !in_range(id) {
+            return Ok(None);
+        }
+        self.run(move |conn| {
+            let updated = conn.execute(
+                "UPDATE todos SET description = ?1, completed = ?2, editing = ?3 WHERE id = ?4 AND user_id = ?5",
+                params![entry.description, entry.completed, entry.editing, id, user],
+            )?;
+            Ok(if updated == 0 { None } else { Some(Todo { id, entry }) })
+        })
+        .await
+    }
+
+    async fn delete(&self, user: u64, id: u64) -> Result<bool, DbError> {
+        if !in_range(id) {
+            return Ok(false);
+        }
+        self.run(move |conn| Ok(conn.execute("DELETE FROM todos WHERE id = ?1 AND user_id = ?2", [id, user])? > 0)).await
+    }
+
+    async fn clear_completed(&self, user: u64) -> Result<Vec<Todo>, DbError> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            tx.execute("DELETE FROM todos WHERE completed AND user_id = ?1", [user])?;
+            let rest = all(&tx, user)?;
+            tx.commit()?;
+            Ok(rest)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl UserRepository for SqliteRepository {
+    async fn create_user(&self, username: &str, password_hash: &str) -> Result<Option<User>, DbError> {
+        let (username, password_hash) = (username.to_string(), password_hash.to_string());
+        self.run(move |conn| {
+            let inserted = conn.execute(
+                "INSERT INTO users (username, password_hash) VALUES (?1, ?2) ON CONFLICT (username) DO NOTHING",
+                params![username, password_hash],
+            )?;
+            Ok(if inserted == 0 { None } else { Some(User { id: conn.last_insert_rowid() as u64, username }) })
+        })
+        .await
+    }
+
+    async fn user(&self, id: u64) -> Result<Option<User>, DbError> {
+        if !in_range(id) {
+            return Ok(None);
+        }
+        self.run(move |conn| Ok(conn.query_row("SELECT id, username FROM users WHERE id = ?1", [id], user).optional()?))
+            .await
+    }
+
+    async fn login(&self, username: &str) -> Result<Option<(User, String)>, DbError> {
+        let username = username.to_string();
+        self.run(move |conn| {
+            let sql = "SELECT id, username, password_hash FROM users WHERE username = ?1";
+            Ok(conn.query_row(sql, [username], |row| Ok((user(row)?, row.get(2)?))).optional()?)
+        })
+        .await
+    }
+
+    async fn create_token(&self, user: u64, name: &str, token_hash: &str) -> Result<Token, DbError> {
+        let (name, token_hash) = (name.to_string(), token_hash.to_string());
+        self.run(move |conn| {
+            let created_at = now();
+            conn.execute(
+                "INSERT INTO tokens (user_id, name, token_hash, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![user, name, token_hash, created_at],
+            )?;
+            Ok(Token { id: conn.last_insert_rowid() as u64, name, created_at })
+        })
+        .await
+    }
+
+    async fn tokens(&self, user: u64) -> Result<Vec<Token>, DbError> {
+        self.run(move |conn| {
+            let mut statement = conn.prepare("SELECT id, name, created_at FROM tokens WHERE user_id = ?1 ORDER BY id")?;
+            let tokens = statement.query_map([user], token)?.collect::<Result<_, _>>()?;
+            Ok(tokens)
+        })
+        .await
+    }
+
+    async fn delete_token(&self, user: u64, id: u64) -> Result<bool, DbError> {
+        if !in_range(id) {
+            return Ok(false);
+        }
+        self.run(move |conn| Ok(conn.execute("DELETE FROM tokens WHERE id = ?1 AND user_id = ?2", [id, user])? > 0)).await
+    }
+
+    async fn token_user(&self, token_hash: &str) -> Result<Option<User>, DbError> {
+        let token_hash = token_hash.to_string();
+        self.run(move |conn| {
+            let sql = "SELECT users.id, users.username FROM tokens JOIN users ON users.id = tokens.user_id \
+                       WHERE tokens.token_hash = ?1";
+            Ok(conn.query_row(sql, [token_hash], user).optional()?)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl DepartmentRepository for SqliteRepository {
+    async fn add_employee(&self, department: &str, name: &str) -> Result<(), DbError> {
+        let (department, name) = (department.to_string(), name.to_string());
+        self.run(move |conn| {
+            conn.execute("INSERT INTO employees (department, name) VALUES (?1, ?2)", [department, name])?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn departments(&self) -> Result<BTreeMap<String, Vec<String>>, DbError> {
+        self.run(|conn| {
+            let mut statement = conn.prepare("SELECT department, name FROM employees ORDER BY department, name")?;
+            let mut departments = BTreeMap::<String, Vec<String>>::new();
+            for row in statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))? {
+                let (department, name) = row?;
+                departments.entry(department).or_default().push(name);
+            }
+            Ok(departments)
+        })
+        .await
+    }
+
+    async fn employees(&self, department: &str) -> Result<Option<Vec<String>>, DbError> {
+        let department = department.to_string();
+        self.run(move |conn| {
+            let mut statement = conn.prepare("SELECT name FROM employees WHERE department = ?1 ORDER BY name")?;
+            let names: Vec<String> = statement.query_map([department], |row| row.get(0))?.collect::<Result<_, _>>()?;
+            Ok(if names.is_empty() { None } else { Some(names) })
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::MemoryRepository;
+
+    fn entry(description: &str, completed: bool) -> Entry {
+        Entry { description: description.to_string(), completed, editing: false }
+    }
+
+    // both implementations keep todos and users the same way
+    async fn exercise<R: TodoRepository + UserRepository + DepartmentRepository>(repo: &R) {
+        let alice = repo.create_user("alice", "hash").await.unwrap().unwrap();
+        let bob = repo.create_user("bob", "hash").await.unwrap().unwrap();
+        assert_eq!(repo.create_user("alice", "other").await.unwrap(), None);
+        assert_eq!(repo.login("alice").await.unwrap(), Some((alice.clone(), "hash".to_string())));
+
+        let milk = repo.create(alice.id, entry("milk", false)).await.unwrap();
+        let eggs = repo.create(alice.id, entry("eggs", true)).await.unwrap();
+        let bread = repo.create(bob.id, entry("bread", true)).await.unwrap();
+        assert_eq!(repo.list(alice.id).await.unwrap(), vec![milk.clone(), eggs.clone()]);
+        assert_eq!(repo.get(alice.id, eggs.id).await.unwrap(), Some(eggs.clone()));
+        // nobody sees or touches the todos of someone else
+        assert_eq!(repo.get(bob.id, eggs.id).await.unwrap(), None);
+        assert_eq!(repo.update(bob.id, milk.id, entry("beer", false)).await.unwrap(), None);
+        assert!(!repo.delete(bob.id, milk.id).await.unwrap());
+
+        let oat_milk = repo.update(alice.id, milk.id, entry("oat milk", false)).await.unwrap().unwrap();
+        assert_eq!(repo.get(alice.id, milk.id).await.unwrap(), Some(oat_milk.clone()));
+        assert_eq!(repo.clear_completed(alice.id).await.unwrap(), vec![oat_milk.clone()]);
+        assert!(repo.delete(alice.id, oat_milk.id).await.unwrap());
+        assert!(!repo.delete(alice.id, oat_milk.id).await.unwrap());
+        assert_eq!(repo.list(alice.id).await.unwrap(), Vec::new());
+        assert_eq!(repo.list(bob.id).await.unwrap(), vec![bread]);
+
+        let token = repo.create_token(bob.id, "ci", "tokenhash").await.unwrap();
+        assert_eq!(repo.token_user("tokenhash").await.unwrap(), Some(bob.clone()));
+        assert_eq!(repo.tokens(bob.id).await.unwrap(), vec![token.clone()]);
+        assert!(!repo.delete_token(alice.id, token.id).await.unwrap());
+        assert!(repo.delete_token(bob.id, token.id).await.unwrap());
+        assert_eq!(repo.token_user("tokenhash").await.unwrap(), None);
+
+        for (name, department) in [("Sally", "Engineering"), ("Amir", "Sales"), ("Bea", "Engineering")] {
+            repo.add_employee(department, name).await.unwrap();
+        }
+        let engineering = vec!["Bea".to_string(), "Sally".to_string()];
+        assert_eq!(repo.employees("Engineering").await.unwrap(), Some(engineering.clone()));
+        assert_eq!(repo.employees("Marketing").await.unwrap(), None);
+        let departments = repo.departments().await.unwrap();
+        assert_eq!(departments.keys().collect::<Vec<_>>(), ["Engineering", "Sales"]);
+        assert_eq!(departments["Engineering"], engineering);
+    }
+
+    #[rocket::async_test]
+    async fn keeps_todos_in_sqlite_and_in_memory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("todos.sqlite");
+        let pool = connect(path.to_str().unwrap()).unwrap();
+        exercise(&SqliteRepository::new(pool)).await;
+        exercise(&MemoryRepository::default()).await;
+
+        // reopening does not run the migrations again
+        let pool = connect(path.to_str().unwrap()).unwrap();
+        let version: usize = pool.get().unwrap().query_row("PRAGMA user_version", [], |row| row.get(0)).unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+}
@@ -0,0 +1,70 @@
+use crate::auth::User;
+use crate::db::Departments;
+use crate::error::ApiError;
+use rocket::http::RawStr;
+use rocket::response::status;
+use rocket::serde::json::{self, Json};
+use rocket::State;
+use rocket_okapi::openapi;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// "Add Sally to Engineering" is POST /api/departments/Engineering/employees {"name": "Sally"}
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NewEmployee {
+    pub name: String,
+}
+
+// department and employee names are trimmed, and neither empty nor longer than 100 characters
+fn validate(name: &str, code: &'static str) -> Result<String, ApiError> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err(ApiError::invalid(code, "names are 1 to 100 characters"));
+    }
+    Ok(name.to_string())
+}
+
+fn not_found(department: &str) -> ApiError {
+    ApiError::not_found(format!("nobody works in {}", department))
+}
+
+// the directory is the company's, every user sees and adds to the same one. this is every
+// department with its people, as {"Engineering": ["Amir", "Sally"], ...}, all sorted.
+#[openapi(tag = "Departments")]
+#[get("/")]
+pub async fn list(
+    _user: User,
+    departments: &State<Departments>,
+) -> Result<Json<BTreeMap<String, Vec<String>>>, ApiError> {
+    Ok(Json(departments.departments().await?))
+}
+
+#[openapi(tag = "Departments")]
+#[get("/<department>/employees")]
+pub async fn employees(
+    department: &str,
+    _user: User,
+    departments: &State<Departments>,
+) -> Result<Json<Vec<String>>, ApiError> {
+    // named as add_employee names it, so " Sales" is Sales
+    let department = validate(department, "invalid_department")?;
+    departments.employees(&department).await?.map(Json).ok_or_else(|| not_found(&department))
+}
+
+// creates the department with its first employee, returns everyone in it
+#[openapi(tag = "Departments")]
+#[post("/<department>/employees", format = "json", data = "<employee>")]
+pub async fn add_employee(
+    department: &str,
+    employee: Result<Json<NewEmployee>, json::Error<'_>>,
+    _user: User,
+    departments: &State<Departments>,
+) -> Result<status::Created<Json<Vec<String>>>, ApiError> {
+    let department = validate(department, "invalid_department")?;
+    let name = validate(&employee?.name, "invalid_name")?;
+    departments.add_employee(&department, &name).await?;
+    let employees = departments.employees(&department).await?.ok_or_else(|| not_found(&department))?;
+    let location = format!("/api/departments/{}/employees", RawStr::new(&department).percent_encode());
+    Ok(status::Created::new(location).body(Json(employees)))
+}
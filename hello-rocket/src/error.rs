@@ -0,0 +1,91 @@
+use crate::db::DbError;
+use rocket::http::Status;
+use rocket::request::Request;
+use rocket::response::{self, Responder};
+use rocket::serde::json::{self, Json};
+use schemars::JsonSchema;
+use serde::Serialize;
+use std::io;
+
+// what every failed API call answers: the HTTP status, a code for programs and a message for
+// people, as `{"code": "not_found", "message": "no todo 3"}`
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct ApiError {
+    #[serde(skip)]
+    pub status: Status,
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ApiError {
+    pub fn new(status: Status, code: &'static str, message: impl Into<String>) -> Self {
+        ApiError { status, code, message: message.into() }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        ApiError::new(Status::NotFound, "not_found", message)
+    }
+
+    // a request that parsed but whose values do not make sense
+    pub fn invalid(code: &'static str, message: impl Into<String>) -> Self {
+        ApiError::new(Status::UnprocessableEntity, code, message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        ApiError::new(Status::Unauthorized, "unauthorized", message)
+    }
+
+    // for statuses nothing more specific is known about, mostly from the catchers
+    pub fn from_status(status: Status) -> Self {
+        let (code, message) = match status.code {
+            400 => ("bad_request", "the request is malformed"),
+            401 => ("unauthorized", "log in or send an API token"),
+            404 => ("not_found", "nothing here"),
+            413 => ("payload_too_large", "the request body is too large"),
+            422 => ("invalid_body", "the request body is not what this route takes"),
+            429 => ("rate_limited", "too many requests, see Retry-After for when to try again"),
+            500 => ("internal_error", "something went wrong on our side"),
+            _ => ("error", status.reason_lossy()),
+        };
+        ApiError::new(status, code, message)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = self.status;
+        (status, Json(self)).respond_to(request)
+    }
+}
+
+// the details go to the log, the client only learns that something broke
+impl From<DbError> for ApiError {
+    fn from(e: DbError) -> Self {
+        error!("{}", e);
+        ApiError::from_status(Status::InternalServerError)
+    }
+}
+
+// a JSON body that did not parse, taken by routes as Result<Json<T>, json::Error> so serde's
+// message reaches the client
+impl From<json::Error<'_>> for ApiError {
+    fn from(e: json::Error<'_>) -> Self {
+        match e {
+            json::Error::Io(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                ApiError::from_status(Status::PayloadTooLarge)
+            }
+            json::Error::Io(e) => ApiError::new(Status::BadRequest, "bad_request", e.to_string()),
+            json::Error::Parse(_, e) if e.is_data() => ApiError::invalid("invalid_body", e.to_string()),
+            json::Error::Parse(_, e) => ApiError::new(Status::BadRequest, "invalid_json", e.to_string()),
+        }
+    }
+}
+
+// every error Rocket answers itself (no route, a failed guard, a panic) comes out the same way
+#[catch(default)]
+pub fn default(status: Status, request: &Request<'_>) -> ApiError {
+    match status.code {
+        404 => ApiError::not_found(format!("no route for {} {}", request.method(), request.uri().path())),
+        _ => ApiError::from_status(status),
+    }
+}
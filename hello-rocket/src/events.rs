@@ -0,0 +1,94 @@
+use crate::auth::User;
+use crate::todos::Todo;
+use rocket::futures::stream::{BoxStream, StreamExt};
+use rocket::response::stream::{stream, Event, EventStream};
+use rocket::serde::json::json;
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::{self, error::RecvError};
+use rocket::{Shutdown, State};
+use rocket_okapi::openapi;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// changes waiting for slow clients before they miss some and are told to resync
+const BACKLOG: usize = 256;
+
+// what happened to a user's todo list, sent as the SSE event of the same name
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    // the todo as it now is
+    Created(Todo),
+    Updated(Todo),
+    // its id
+    Deleted(u64),
+    // the todos left after clearing the completed ones
+    Cleared(Vec<Todo>),
+}
+
+impl Change {
+    fn event(&self) -> Event {
+        match self {
+            Change::Created(todo) => Event::json(todo).event("created"),
+            Change::Updated(todo) => Event::json(todo).event("updated"),
+            Change::Deleted(id) => Event::json(&json!({ "id": id })).event("deleted"),
+            Change::Cleared(rest) => Event::json(rest).event("cleared"),
+        }
+    }
+}
+
+// one channel per user with a stream open, so a stream only ever wakes up for its own user's
+// changes and a busy user cannot make another one's client lag
+#[derive(Default)]
+pub struct Changes {
+    senders: Mutex<HashMap<u64, broadcast::Sender<Change>>>,
+}
+
+impl Changes {
+    pub fn publish(&self, user: u64, change: Change) {
+        let mut senders = self.senders.lock().unwrap();
+        if let Some(sender) = senders.get(&user) {
+            // an error only means the user's streams are all gone, the next one gets a new channel
+            if sender.send(change).is_err() {
+                senders.remove(&user);
+            }
+        }
+    }
+
+    fn subscribe(&self, user: u64) -> broadcast::Receiver<Change> {
+        let mut senders = self.senders.lock().unwrap();
+        senders.entry(user).or_insert_with(|| broadcast::channel(BACKLOG).0).subscribe()
+    }
+}
+
+// the changes to the user's list as server-sent events, from when the stream opens. everyone
+// logged in as the same user (several tabs, devices, API clients) sees the others' changes. a
+// client that fell behind gets a `resync` event and should fetch the list again. the stream is boxed
+// so that its type has a name, which the OpenAPI generator needs.
+#[openapi(tag = "Todos")]
+#[get("/events")]
+pub fn events(
+    user: User,
+    changes: &State<Changes>,
+    mut shutdown: Shutdown,
+) -> EventStream<BoxStream<'static, Event>> {
+    let mut receiver = changes.subscribe(user.id);
+    let stream = stream! {
+        loop {
+            // what was sent before the shutdown still goes out
+            let change = select! {
+                biased;
+                received = receiver.recv() => match received {
+                    Ok(received) => received,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => {
+                        yield Event::empty().event("resync");
+                        continue;
+                    }
+                },
+                _ = &mut shutdown => break,
+            };
+            yield change.event();
+        }
+    };
+    EventStream::from(stream.boxed())
+}
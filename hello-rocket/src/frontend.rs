@@ -0,0 +1,114 @@
+use rocket::fs::NamedFile;
+use rocket::http::{Header, Method, Status};
+use rocket::route::{Handler, Outcome, Route};
+use rocket::tokio::fs;
+use rocket::{Data, Request};
+use std::path::{Path, PathBuf};
+
+// below the API routes so they always win over the fallback, see Frontend::new
+const RANK: isize = 20;
+
+// a year, for files whose name changes with their content
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+
+// serves a built wasm frontend (index.html, the wasm-bindgen glue, the .wasm and whatever else is
+// in the directory) with the MIME type of each file's extension. a path that is not a file and has
+// no extension gets index.html, so reloading a client-side route still loads the app.
+#[derive(Clone)]
+pub struct Frontend {
+    root: PathBuf,
+    rank: isize,
+    // seconds the browser may keep files whose name does not change between builds
+    max_age: u32,
+}
+
+impl Frontend {
+    // `base` is where it gets mounted: /yew goes before /, which would otherwise take /yew/... too
+    pub fn new(base: &str, root: impl Into<PathBuf>, max_age: u32) -> Self {
+        let depth = base.split('/').filter(|segment| !segment.is_empty()).count();
+        Frontend { root: root.into(), rank: RANK - depth as isize, max_age }
+    }
+
+    // index.html is checked on every load so a new build shows up right away; fingerprinted
+    // files never change and the others are kept for max_age
+    fn cache_control(&self, path: &Path) -> String {
+        if path.ends_with("index.html") {
+            "no-cache".to_string()
+        } else if fingerprinted(path) {
+            IMMUTABLE.to_string()
+        } else if self.max_age == 0 {
+            "no-cache".to_string()
+        } else {
+            format!("public, max-age={}", self.max_age)
+        }
+    }
+}
+
+// whether the file name carries a content hash, as in trunk's `todo-app-1c9ef5e4a8b2d7f3_bg.wasm`
+fn fingerprinted(path: &Path) -> bool {
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("");
+    stem.split(['-', '_', '.'])
+        .any(|part| part.len() >= 16 && part.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[derive(Responder)]
+struct Asset {
+    file: NamedFile,
+    cache_control: Header<'static>,
+}
+
+// a mistyped API call gets a 404, not the app
+fn is_api(request: &Request<'_>) -> bool {
+    request.uri().path().segments().next() == Some("api")
+}
+
+async fn is_file(path: &Path) -> bool {
+    fs::metadata(path).await.map(|metadata| metadata.is_file()).unwrap_or(false)
+}
+
+#[rocket::async_trait]
+impl Handler for Frontend {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        // refuses `..` and dotfiles, nothing outside the directory is served
+        let path = match request.routed_segments(0..).to_path_buf(false) {
+            Ok(path) => path,
+            Err(_) => return Outcome::forward(data, Status::NotFound),
+        };
+        let path = if is_file(&self.root.join(&path)).await {
+            path
+        } else if path.extension().is_none() && !is_api(request) {
+            PathBuf::from("index.html")
+        } else {
+            return Outcome::forward(data, Status::NotFound);
+        };
+        match NamedFile::open(self.root.join(&path)).await {
+            Ok(file) => {
+                let cache_control = Header::new("Cache-Control", self.cache_control(&path));
+                Outcome::from(request, Asset { file, cache_control })
+            }
+            // no build in the directory yet
+            Err(_) => Outcome::forward(data, Status::NotFound),
+        }
+    }
+}
+
+impl From<Frontend> for Vec<Route> {
+    fn from(frontend: Frontend) -> Self {
+        vec![Route::ranked(frontend.rank, Method::Get, "/<path..>", frontend)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caches_by_file_name() {
+        let frontend = Frontend::new("/", "static", 3600);
+        assert_eq!(frontend.cache_control(Path::new("index.html")), "no-cache");
+        assert_eq!(frontend.cache_control(Path::new("wasm_bg.wasm")), "public, max-age=3600");
+        assert_eq!(frontend.cache_control(Path::new("todo-app-1c9ef5e4a8b2d7f3_bg.wasm")), IMMUTABLE);
+        assert_eq!(frontend.cache_control(Path::new("style-1c9ef5e4a8b2d7f3.css")), IMMUTABLE);
+        assert_eq!(Frontend::new("/", "static", 0).cache_control(Path::new("wasm.js")), "no-cache");
+    }
+}
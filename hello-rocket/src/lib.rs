@@ -0,0 +1,118 @@
+#[macro_use] extern crate rocket;
+
+pub mod auth;
+pub mod db;
+pub mod departments;
+pub mod error;
+pub mod events;
+pub mod frontend;
+pub mod openapi;
+pub mod rate_limit;
+pub mod request_log;
+pub mod stats;
+pub mod todos;
+
+use rocket::fairing::AdHoc;
+use rocket::tokio::task::spawn_blocking;
+use rocket::{Build, Rocket};
+use rocket_okapi::settings::OpenApiSettings;
+use rocket_okapi::{get_openapi_route, openapi_get_routes_spec};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+// only answers when no frontend is mounted at / or it has not been built
+#[get("/", rank = 30)]
+fn index() -> &'static str {
+    "hello, world!"
+}
+
+// opens the SQLite file named by the `database` config value (ROCKET_DATABASE or Rocket.toml),
+// migrates it and manages the pool and the repositories on top of it
+async fn database(rocket: Rocket<Build>) -> Result<Rocket<Build>, Rocket<Build>> {
+    let path = rocket.figment().extract_inner::<String>("database").unwrap_or_else(|_| "todos.sqlite".to_string());
+    let connected = {
+        let path = path.clone();
+        spawn_blocking(move || db::sqlite::connect(&path)).await.map_err(db::DbError::from).and_then(|pool| pool)
+    };
+    match connected {
+        Ok(pool) => {
+            let todos: db::Todos = Box::new(db::SqliteRepository::new(pool.clone()));
+            let users: db::Users = Box::new(db::SqliteRepository::new(pool.clone()));
+            let departments: db::Departments = Box::new(db::SqliteRepository::new(pool.clone()));
+            Ok(rocket.manage(pool).manage(todos).manage(users).manage(departments))
+        }
+        Err(e) => {
+            error!("cannot open {}: {}", path, e);
+            Err(rocket)
+        }
+    }
+}
+
+// mounts the `frontends` config table, a directory of built wasm app per mount point
+async fn frontends(rocket: Rocket<Build>) -> Rocket<Build> {
+    let figment = rocket.figment();
+    let frontends = figment.extract_inner::<BTreeMap<String, PathBuf>>("frontends").unwrap_or_default();
+    let max_age = figment.extract_inner::<u32>("frontend_max_age").unwrap_or(0);
+    frontends.into_iter().fold(rocket, |rocket, (base, root)| {
+        if !root.join("index.html").is_file() {
+            warn!("no index.html in {}, {} is not served until the frontend is built", root.display(), base);
+        }
+        let frontend = frontend::Frontend::new(&base, root, max_age);
+        rocket.mount(base, frontend)
+    })
+}
+
+// the routes, on top of whatever manages the repositories. the API is described at
+// /openapi.json and browsable at /docs.
+fn mount(rocket: Rocket<Build>) -> Rocket<Build> {
+    let settings = OpenApiSettings::default();
+    let (auth, auth_spec) = openapi_get_routes_spec![
+        settings: auth::register,
+        auth::login,
+        auth::me,
+        auth::logout,
+        auth::tokens,
+        auth::create_token,
+        auth::delete_token
+    ];
+    let (todos, todos_spec) = openapi_get_routes_spec![
+        settings: todos::list,
+        todos::get,
+        todos::create,
+        todos::update,
+        todos::delete,
+        todos::clear_completed,
+        events::events
+    ];
+    let (departments, departments_spec) = openapi_get_routes_spec![
+        settings: departments::list,
+        departments::employees,
+        departments::add_employee
+    ];
+    let (stats, stats_spec) = openapi_get_routes_spec![settings: stats::summarize_json, stats::summarize_csv];
+    let spec = openapi::merge(&[
+        ("/api", auth_spec),
+        ("/api/todos", todos_spec),
+        ("/api/departments", departments_spec),
+        ("/api/statistics", stats_spec),
+    ]);
+    rocket
+        .register("/", catchers![error::default])
+        .mount("/", routes![index])
+        .mount("/", vec![get_openapi_route(spec, &settings)])
+        .mount("/docs", openapi::swagger_ui())
+        .mount("/api", auth)
+        .mount("/api/todos", todos)
+        .mount("/api/departments", departments)
+        .mount("/api/statistics", stats)
+}
+
+pub fn rocket() -> Rocket<Build> {
+    let rocket = rocket::build()
+        .attach(request_log::RequestLog)
+        .attach(rate_limit::RateLimit::fairing())
+        .attach(AdHoc::try_on_ignite("database", database))
+        .attach(AdHoc::on_ignite("frontends", frontends))
+        .manage(events::Changes::default());
+    mount(rocket)
+}
@@ -0,0 +1,64 @@
+use crate::auth::User;
+use crate::error::ApiError;
+use rocket_okapi::gen::OpenApiGenerator;
+use rocket_okapi::okapi::merge::marge_spec_list;
+use rocket_okapi::okapi::openapi3::{
+    Object, OpenApi, Responses, SecurityRequirement, SecurityScheme, SecuritySchemeData,
+};
+use rocket_okapi::request::{OpenApiFromRequest, RequestHeaderInput};
+use rocket_okapi::response::OpenApiResponderInner;
+use rocket_okapi::swagger_ui::{make_swagger_ui, SwaggerUIConfig};
+use rocket_okapi::util::add_schema_response;
+use rocket_okapi::OpenApiError;
+
+// the spec is built from the routes marked #[openapi] and the JsonSchema of what they take and
+// return, these fill in what the macros cannot see
+
+const SECURITY_SCHEME: &str = "token";
+
+// routes taking a User need a token, or the session cookie a browser gets from logging in
+impl<'r> OpenApiFromRequest<'r> for User {
+    fn from_request_input(_: &mut OpenApiGenerator, _: String, _: bool) -> Result<RequestHeaderInput, OpenApiError> {
+        let scheme = SecurityScheme {
+            description: Some(
+                "an API token from POST /api/tokens, browsers can use the session cookie from POST /api/session instead"
+                    .to_string(),
+            ),
+            data: SecuritySchemeData::Http { scheme: "bearer".to_string(), bearer_format: None },
+            extensions: Object::default(),
+        };
+        let mut requirement = SecurityRequirement::new();
+        requirement.insert(SECURITY_SCHEME.to_string(), Vec::new());
+        Ok(RequestHeaderInput::Security(SECURITY_SCHEME.to_string(), scheme, requirement))
+    }
+
+    fn get_responses(gen: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
+        let mut responses = Responses::default();
+        add_schema_response(&mut responses, 401, "application/json", gen.json_schema::<ApiError>())?;
+        Ok(responses)
+    }
+}
+
+// the errors a route can answer besides its own, the default catcher sends the same body
+impl OpenApiResponderInner for ApiError {
+    fn responses(gen: &mut OpenApiGenerator) -> Result<Responses, OpenApiError> {
+        let mut responses = Responses::default();
+        for status in [400, 404, 409, 422, 500] {
+            add_schema_response(&mut responses, status, "application/json", gen.json_schema::<ApiError>())?;
+        }
+        Ok(responses)
+    }
+}
+
+// one spec for the routes mounted at each base. a route at "/" is at the base itself, as Rocket
+// serves it, and not at "<base>/".
+pub fn merge(specs: &[(&str, OpenApi)]) -> OpenApi {
+    let mut spec = marge_spec_list(specs).expect("route specs merge");
+    spec.paths = spec.paths.into_iter().map(|(path, item)| (path.trim_end_matches('/').to_string(), item)).collect();
+    spec
+}
+
+// Swagger UI for /openapi.json
+pub fn swagger_ui() -> Vec<rocket::Route> {
+    make_swagger_ui(&SwaggerUIConfig { url: "../openapi.json".to_string(), ..Default::default() }).into()
+}
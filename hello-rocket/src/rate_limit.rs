@@ -0,0 +1,229 @@
+use crate::auth::hash_token;
+use crate::db::Users;
+use crate::error::ApiError;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{Header, Method, Status};
+use rocket::route::{Handler, Outcome, Route};
+use rocket::{Build, Data, Request, Response, Rocket};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+// before every other route, so a limited request never reaches one
+const RANK: isize = -50;
+
+// buckets kept at most. a new client past that drops the full buckets and then the least recently
+// seen ones, down to KEPT_CLIENTS so that this does not happen again on the next request.
+const MAX_CLIENTS: usize = 10_000;
+const KEPT_CLIENTS: usize = MAX_CLIENTS - MAX_CLIENTS / 10;
+
+// how many API requests a client may make in `window` seconds, set with the `rate_limit` config
+// table. requests with a valid bearer token count against the token, the others against their IP.
+// 0 is no limit.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Quotas {
+    pub per_ip: u32,
+    pub per_token: u32,
+    pub window: u64,
+}
+
+impl Default for Quotas {
+    fn default() -> Self {
+        Quotas { per_ip: 300, per_token: 1200, window: 60 }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum Client {
+    // None when the IP is unknown, all of those share a bucket
+    Ip(Option<IpAddr>),
+    // the hash of the token
+    Token(String),
+}
+
+// a token bucket: holds up to the quota and refills it over the window
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+// what on_request decided, kept in the request's local cache for the route and on_response
+#[derive(Debug, Clone, Copy, Default)]
+struct Verdict {
+    // None for requests that are not counted
+    limit: Option<u32>,
+    remaining: u32,
+    // seconds until the next request is allowed, for a request that was refused
+    retry_after: Option<u64>,
+}
+
+pub struct RateLimit {
+    quotas: Mutex<Quotas>,
+    buckets: Mutex<HashMap<Client, Bucket>>,
+}
+
+impl RateLimit {
+    // the quotas are read from the config at ignition
+    pub fn fairing() -> Self {
+        RateLimit { quotas: Mutex::new(Quotas::default()), buckets: Mutex::new(HashMap::new()) }
+    }
+
+    // a token only has a bucket of its own once it is known to belong to a user, made up ones would
+    // otherwise each get a full quota
+    async fn client(request: &Request<'_>) -> Client {
+        let token = request.headers().get_one("Authorization").and_then(|value| value.strip_prefix("Bearer "));
+        if let (Some(secret), Some(users)) = (token, request.rocket().state::<Users>()) {
+            let hash = hash_token(secret.trim());
+            if let Ok(Some(_)) = users.token_user(&hash).await {
+                return Client::Token(hash);
+            }
+        }
+        Client::Ip(request.client_ip())
+    }
+
+    fn check(&self, client: Client, now: Instant) -> Verdict {
+        let quotas = *self.quotas.lock().unwrap();
+        let limit = match client {
+            Client::Ip(_) => quotas.per_ip,
+            Client::Token(_) => quotas.per_token,
+        };
+        if limit == 0 {
+            return Verdict::default();
+        }
+        let capacity = f64::from(limit);
+        // tokens per second
+        let rate = capacity / quotas.window.max(1) as f64;
+
+        let refilled = |bucket: &Bucket| bucket.tokens + now.duration_since(bucket.updated).as_secs_f64() * rate;
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= MAX_CLIENTS && !buckets.contains_key(&client) {
+            // a full bucket is the same as none
+            buckets.retain(|_, bucket| refilled(bucket) < capacity);
+            if buckets.len() > KEPT_CLIENTS {
+                let mut seen: Vec<_> = buckets.values().map(|bucket| bucket.updated).collect();
+                seen.sort_unstable();
+                let oldest_kept = seen[seen.len() - KEPT_CLIENTS];
+                buckets.retain(|_, bucket| bucket.updated >= oldest_kept);
+            }
+        }
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: capacity, updated: now });
+        bucket.tokens = refilled(bucket).min(capacity);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Verdict { limit: Some(limit), remaining: bucket.tokens as u32, retry_after: None }
+        } else {
+            let retry_after = ((1.0 - bucket.tokens) / rate).ceil() as u64;
+            Verdict { limit: Some(limit), remaining: 0, retry_after: Some(retry_after.max(1)) }
+        }
+    }
+}
+
+// answers the requests on_request refused and lets the others through
+#[derive(Clone)]
+struct Refuse;
+
+#[rocket::async_trait]
+impl Handler for Refuse {
+    async fn handle<'r>(&self, request: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        match request.local_cache(Verdict::default).retry_after {
+            Some(_) => Outcome::from(request, ApiError::from_status(Status::TooManyRequests)),
+            None => Outcome::forward(data, Status::NotFound),
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl Fairing for RateLimit {
+    fn info(&self) -> Info {
+        Info { name: "rate limit", kind: Kind::Ignite | Kind::Request | Kind::Response }
+    }
+
+    async fn on_ignite(&self, rocket: Rocket<Build>) -> rocket::fairing::Result {
+        match rocket.figment().extract_inner::<Quotas>("rate_limit") {
+            Ok(quotas) => *self.quotas.lock().unwrap() = quotas,
+            Err(e) if e.missing() => {}
+            Err(e) => {
+                error!("invalid rate_limit config: {}", e);
+                return Err(rocket);
+            }
+        }
+        let methods = [Method::Get, Method::Put, Method::Post, Method::Delete, Method::Patch, Method::Options];
+        let routes: Vec<_> = methods
+            .iter()
+            .map(|method| {
+                let mut route = Route::ranked(RANK, *method, "/<path..>", Refuse);
+                route.name = Some("rate_limit".into());
+                route
+            })
+            .collect();
+        Ok(rocket.mount("/api", routes))
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        // the frontends' files are not limited
+        if request.uri().path().segments().next() != Some("api") {
+            return;
+        }
+        let verdict = self.check(Self::client(request).await, Instant::now());
+        request.local_cache(|| verdict);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let verdict = request.local_cache(Verdict::default);
+        if let Some(limit) = verdict.limit {
+            response.set_header(Header::new("X-RateLimit-Limit", limit.to_string()));
+            response.set_header(Header::new("X-RateLimit-Remaining", verdict.remaining.to_string()));
+        }
+        if let Some(retry_after) = verdict.retry_after {
+            response.set_header(Header::new("Retry-After", retry_after.to_string()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn refills_over_the_window() {
+        let limit = RateLimit::fairing();
+        *limit.quotas.lock().unwrap() = Quotas { per_ip: 2, per_token: 0, window: 10 };
+        let ip = || Client::Ip(Some(IpAddr::from([10, 0, 0, 1])));
+        let start = Instant::now();
+
+        assert_eq!(limit.check(ip(), start).remaining, 1);
+        assert_eq!(limit.check(ip(), start).remaining, 0);
+        assert_eq!(limit.check(ip(), start).retry_after, Some(5));
+        // another client has its own bucket, tokens are not limited at all
+        assert_eq!(limit.check(Client::Ip(None), start).retry_after, None);
+        assert_eq!(limit.check(Client::Token("hash".to_string()), start).limit, None);
+        // one request every 5 seconds
+        assert_eq!(limit.check(ip(), start + Duration::from_secs(4)).retry_after, Some(1));
+        assert_eq!(limit.check(ip(), start + Duration::from_secs(5)).retry_after, None);
+        assert_eq!(limit.check(ip(), start + Duration::from_secs(60)).remaining, 1);
+    }
+
+    #[test]
+    fn keeps_a_bounded_number_of_clients() {
+        let limit = RateLimit::fairing();
+        *limit.quotas.lock().unwrap() = Quotas { per_ip: 2, per_token: 0, window: 3600 };
+        let ip = |n: usize| Client::Ip(Some(IpAddr::from([10, (n >> 16) as u8, (n >> 8) as u8, n as u8])));
+        let start = Instant::now();
+
+        // every bucket is partly used, none can be dropped for being full
+        for n in 0..MAX_CLIENTS * 3 {
+            limit.check(ip(n), start + Duration::from_millis(n as u64));
+            assert!(limit.buckets.lock().unwrap().len() <= MAX_CLIENTS);
+        }
+        // the most recently seen are kept, the oldest start over
+        let last = MAX_CLIENTS * 3 - 1;
+        assert_eq!(limit.check(ip(last), start + Duration::from_millis(last as u64)).remaining, 0);
+        assert_eq!(limit.check(ip(0), start + Duration::from_millis(last as u64)).remaining, 1);
+    }
+}
@@ -0,0 +1,79 @@
+use rand::RngCore;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::Header;
+use rocket::request::{FromRequest, Outcome};
+use rocket::{Data, Request, Response};
+use std::convert::Infallible;
+use std::time::Instant;
+
+pub const REQUEST_ID: &str = "X-Request-Id";
+
+// identifies a request in the access log and to the client. one that came with an X-Request-Id
+// (from a proxy or another service) keeps it, the others get a new one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestId(pub String);
+
+// what on_request noted for on_response
+#[derive(Debug, Clone)]
+struct Started {
+    id: RequestId,
+    at: Instant,
+}
+
+// ids from outside end up in logs, so only short printable ones are kept
+fn valid(id: &str) -> bool {
+    (1..=128).contains(&id.len()) && id.bytes().all(|b| b.is_ascii_graphic())
+}
+
+fn new_id() -> String {
+    let mut bytes = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    hex::encode(bytes)
+}
+
+fn started<'r>(request: &'r Request<'_>) -> &'r Started {
+    request.local_cache(|| {
+        let id = request.headers().get_one(REQUEST_ID).filter(|id| valid(id)).map_or_else(new_id, str::to_string);
+        Started { id: RequestId(id), at: Instant::now() }
+    })
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for RequestId {
+    type Error = Infallible;
+
+    async fn from_request(request: &'r Request<'_>) -> Outcome<Self, Infallible> {
+        Outcome::Success(started(request).id.clone())
+    }
+}
+
+// gives every request its id, sends it back in X-Request-Id and logs one line per request, at
+// the info level, with how long it took
+pub struct RequestLog;
+
+#[rocket::async_trait]
+impl Fairing for RequestLog {
+    fn info(&self) -> Info {
+        Info { name: "request log", kind: Kind::Request | Kind::Response }
+    }
+
+    async fn on_request(&self, request: &mut Request<'_>, _: &mut Data<'_>) {
+        started(request);
+    }
+
+    async fn on_response<'r>(&self, request: &'r Request<'_>, response: &mut Response<'r>) {
+        let started = started(request);
+        response.set_header(Header::new(REQUEST_ID, started.id.0.clone()));
+        let client = request.client_ip().map_or_else(|| "-".to_string(), |ip| ip.to_string());
+        let latency = started.at.elapsed().as_secs_f64() * 1000.0;
+        info!(
+            "{} {} \"{} {}\" {} {:.1}ms",
+            started.id.0,
+            client,
+            request.method(),
+            request.uri(),
+            response.status().code,
+            latency
+        );
+    }
+}
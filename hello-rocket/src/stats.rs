@@ -0,0 +1,137 @@
+use crate::error::ApiError;
+use rocket::data::{Data, Limits, ToByteUnit};
+use rocket::http::Status;
+use rocket::serde::json::{self, Json};
+use rocket_okapi::openapi;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+// at most this many numbers per request. the body size is capped too, by the `json` and `csv`
+// limits (ROCKET_LIMITS or Rocket.toml)
+pub const MAX_VALUES: usize = 100_000;
+const MAX_QUANTILES: usize = 100;
+const DEFAULT_QUANTILES: &[f64] = &[0.25, 0.5, 0.75];
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Quantile {
+    pub p: f64,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Summary {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    // every most frequent value, smallest first
+    pub mode: Vec<f64>,
+    pub min: f64,
+    pub max: f64,
+    pub quantiles: Vec<Quantile>,
+}
+
+fn summarize(data: &[f64], quantiles: Vec<f64>) -> Result<Summary, ApiError> {
+    if data.is_empty() {
+        return Err(ApiError::invalid("empty_data", "there are no numbers to summarize"));
+    }
+    if data.len() > MAX_VALUES {
+        let message = format!("at most {} numbers per request", MAX_VALUES);
+        return Err(ApiError::new(Status::PayloadTooLarge, "too_many_values", message));
+    }
+    if let Some(value) = data.iter().find(|value| !value.is_finite()) {
+        return Err(ApiError::invalid("invalid_number", format!("{} is not a finite number", value)));
+    }
+    let quantiles = if quantiles.is_empty() { DEFAULT_QUANTILES.to_vec() } else { quantiles };
+    if quantiles.len() > MAX_QUANTILES || quantiles.iter().any(|p| !(0.0..=1.0).contains(p)) {
+        let message = format!("up to {} quantiles, each from 0 to 1", MAX_QUANTILES);
+        return Err(ApiError::invalid("invalid_quantile", message));
+    }
+
+    // none of these is None for data that is not empty
+    let missing = || ApiError::from_status(Status::InternalServerError);
+    let quantiles = quantiles
+        .into_iter()
+        .map(|p| statistics::quantile(data, p).map(|value| Quantile { p, value }).ok_or_else(missing))
+        .collect::<Result<_, _>>()?;
+    Ok(Summary {
+        count: data.len(),
+        mean: statistics::mean(data).ok_or_else(missing)?,
+        median: statistics::median(data).ok_or_else(missing)?,
+        mode: statistics::modes(data),
+        min: data.iter().copied().fold(f64::INFINITY, f64::min),
+        max: data.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        quantiles,
+    })
+}
+
+// numbers separated by commas or line breaks. a first line without any number is a header and is
+// skipped, as are empty fields.
+fn parse_csv(text: &str) -> Result<Vec<f64>, ApiError> {
+    let mut data = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let fields: Vec<_> = line.split(',').map(str::trim).filter(|field| !field.is_empty()).collect();
+        if idx == 0 && !fields.is_empty() && fields.iter().all(|field| field.parse::<f64>().is_err()) {
+            continue;
+        }
+        for field in fields {
+            let value = field.parse().map_err(|_| {
+                ApiError::invalid("invalid_csv", format!("line {}: {:?} is not a number", idx + 1, field))
+            })?;
+            data.push(value);
+        }
+    }
+    Ok(data)
+}
+
+// count, mean, median, mode, min, max and quantiles of a JSON array of numbers. `q` picks the
+// quantiles, as in ?q=0.1&q=0.9, the default is the quartiles.
+#[openapi(tag = "Statistics")]
+#[post("/?<q>", format = "json", data = "<data>")]
+pub fn summarize_json(
+    q: Vec<f64>,
+    data: Result<Json<Vec<f64>>, json::Error<'_>>,
+) -> Result<Json<Summary>, ApiError> {
+    Ok(Json(summarize(&data?, q)?))
+}
+
+// the same for text/csv
+#[openapi(tag = "Statistics")]
+#[post("/?<q>", format = "text/csv", data = "<data>")]
+pub async fn summarize_csv(q: Vec<f64>, data: Data<'_>, limits: &Limits) -> Result<Json<Summary>, ApiError> {
+    let limit = limits.get("csv").unwrap_or_else(|| 1.mebibytes());
+    let text = data
+        .open(limit)
+        .into_string()
+        .await
+        .map_err(|e| ApiError::new(Status::BadRequest, "bad_request", e.to_string()))?;
+    if !text.is_complete() {
+        return Err(ApiError::from_status(Status::PayloadTooLarge));
+    }
+    Ok(Json(summarize(&parse_csv(&text)?, q)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_the_sample_data() {
+        let data = [3.0, 1.0, 6.0, 1.0, 5.0, 8.0, 1.0, 8.0, 10.0, 11.0];
+        let summary = summarize(&data, vec![0.0, 0.25, 0.375, 1.0]).unwrap();
+        assert_eq!(summary.count, 10);
+        assert_eq!(summary.mean, 5.4);
+        assert_eq!(summary.median, 5.5);
+        assert_eq!(summary.mode, vec![1.0]);
+        assert_eq!((summary.min, summary.max), (1.0, 11.0));
+        let quantiles: Vec<_> = summary.quantiles.iter().map(|q| (q.p, q.value)).collect();
+        assert_eq!(quantiles, vec![(0.0, 1.0), (0.25, 1.5), (0.375, 3.75), (1.0, 11.0)]);
+    }
+
+    #[test]
+    fn parses_csv() {
+        assert_eq!(parse_csv("value\n1.5, 2\n\n-3,\n").unwrap(), vec![1.5, 2.0, -3.0]);
+        assert_eq!(parse_csv("1,2\n3,4").unwrap(), vec![1.0, 2.0, 3.0, 4.0]);
+        let e = parse_csv("1,2\nthree").unwrap_err();
+        assert_eq!((e.code, e.message.as_str()), ("invalid_csv", "line 2: \"three\" is not a number"));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::auth::User;
+use crate::db::Todos;
+use crate::error::ApiError;
+use crate::events::{Change, Changes};
+use rocket::response::status;
+use rocket::serde::json::{self, Json};
+use rocket::State;
+use rocket_okapi::openapi;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+// the todo-app's entry, as it keeps it in local storage
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Entry {
+    pub description: String,
+    pub completed: bool,
+    #[serde(default)]
+    pub editing: bool,
+}
+
+// an entry with the id the server gave it
+#[derive(Debug, Clone, PartialEq, Serialize, JsonSchema)]
+pub struct Todo {
+    pub id: u64,
+    #[serde(flatten)]
+    pub entry: Entry,
+}
+
+// the todo-app trims descriptions and ignores empty ones
+fn validate(entry: Result<Json<Entry>, json::Error<'_>>) -> Result<Entry, ApiError> {
+    let entry = entry?.into_inner();
+    let description = entry.description.trim().to_string();
+    if description.is_empty() {
+        return Err(ApiError::invalid("empty_description", "the description is empty"));
+    }
+    Ok(Entry { description, ..entry })
+}
+
+fn not_found(id: u64) -> ApiError {
+    ApiError::not_found(format!("no todo {}", id))
+}
+
+// every route only sees the todos of the user making the request
+#[openapi(tag = "Todos")]
+#[get("/")]
+pub async fn list(user: User, todos: &State<Todos>) -> Result<Json<Vec<Todo>>, ApiError> {
+    Ok(Json(todos.list(user.id).await?))
+}
+
+#[openapi(tag = "Todos")]
+#[get("/<id>")]
+pub async fn get(id: u64, user: User, todos: &State<Todos>) -> Result<Json<Todo>, ApiError> {
+    todos.get(user.id, id).await?.map(Json).ok_or_else(|| not_found(id))
+}
+
+#[openapi(tag = "Todos")]
+#[post("/", format = "json", data = "<entry>")]
+pub async fn create(
+    entry: Result<Json<Entry>, json::Error<'_>>,
+    user: User,
+    todos: &State<Todos>,
+    changes: &State<Changes>,
+) -> Result<status::Created<Json<Todo>>, ApiError> {
+    let entry = validate(entry)?;
+    let todo = todos.create(user.id, entry).await?;
+    changes.publish(user.id, Change::Created(todo.clone()));
+    Ok(status::Created::new(format!("/api/todos/{}", todo.id)).body(Json(todo)))
+}
+
+#[openapi(tag = "Todos")]
+#[put("/<id>", format = "json", data = "<entry>")]
+pub async fn update(
+    id: u64,
+    entry: Result<Json<Entry>, json::Error<'_>>,
+    user: User,
+    todos: &State<Todos>,
+    changes: &State<Changes>,
+) -> Result<Json<Todo>, ApiError> {
+    let entry = validate(entry)?;
+    let todo = todos.update(user.id, id, entry).await?.ok_or_else(|| not_found(id))?;
+    changes.publish(user.id, Change::Updated(todo.clone()));
+    Ok(Json(todo))
+}
+
+#[openapi(tag = "Todos")]
+#[delete("/<id>")]
+pub async fn delete(
+    id: u64,
+    user: User,
+    todos: &State<Todos>,
+    changes: &State<Changes>,
+) -> Result<status::NoContent, ApiError> {
+    if todos.delete(user.id, id).await? {
+        changes.publish(user.id, Change::Deleted(id));
+        Ok(status::NoContent)
+    } else {
+        Err(not_found(id))
+    }
+}
+
+// the "clear completed" button, returns what is left
+#[openapi(tag = "Todos")]
+#[delete("/completed")]
+pub async fn clear_completed(
+    user: User,
+    todos: &State<Todos>,
+    changes: &State<Changes>,
+) -> Result<Json<Vec<Todo>>, ApiError> {
+    let rest = todos.clear_completed(user.id).await?;
+    changes.publish(user.id, Change::Cleared(rest.clone()));
+    Ok(Json(rest))
+}
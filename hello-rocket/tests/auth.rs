@@ -0,0 +1,96 @@
+use common::{assert_error, bearer, logged_in, register, server};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::{json, Value};
+
+mod common;
+
+#[test]
+fn signs_up_logs_in_and_out() {
+    let server = server();
+    let response = register(&server, "alice", "correct horse");
+    assert_eq!(response.status(), Status::Created);
+    assert_eq!(response.headers().get_one("Location"), Some("/api/session"));
+    let alice: Value = response.into_json().unwrap();
+    assert_eq!(alice["username"], "alice");
+
+    // registering logged alice in
+    let me = server.get("/api/session").dispatch();
+    assert_eq!(me.status(), Status::Ok);
+    assert_eq!(me.into_json::<Value>().unwrap(), alice);
+
+    assert_eq!(server.delete("/api/session").dispatch().status(), Status::NoContent);
+    assert_error(server.get("/api/session").dispatch(), Status::Unauthorized, "unauthorized");
+
+    let login = |password: &str| {
+        let credentials = json!({ "username": "alice", "password": password });
+        server.post("/api/session").json(&credentials).dispatch()
+    };
+    let wrong_password = assert_error(login("wrong horse"), Status::Unauthorized, "unauthorized");
+    assert_eq!(wrong_password, "wrong username or password");
+    assert_eq!(server.get("/api/session").dispatch().status(), Status::Unauthorized);
+    let response = login("correct horse");
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_json::<Value>().unwrap(), alice);
+    assert_eq!(server.get("/api/session").dispatch().status(), Status::Ok);
+}
+
+#[test]
+fn rejects_bad_registrations() {
+    let server = logged_in("alice");
+    assert_error(register(&server, "alice", "another password"), Status::Conflict, "username_taken");
+    let invalid = [
+        ("al", "correct horse", "invalid_username"),
+        ("al ice", "correct horse", "invalid_username"),
+        ("bob", "short", "invalid_password"),
+    ];
+    for (username, password, code) in invalid {
+        assert_error(register(&server, username, password), Status::UnprocessableEntity, code);
+    }
+    let missing_password = server.post("/api/users").json(&json!({ "username": "bob" })).dispatch();
+    let message = assert_error(missing_password, Status::UnprocessableEntity, "invalid_body");
+    assert!(message.contains("missing field `password`"), "{}", message);
+    let broken = server.post("/api/users").header(ContentType::JSON).body("{\"username\": ").dispatch();
+    assert_error(broken, Status::BadRequest, "invalid_json");
+    let not_json = server.post("/api/users").header(ContentType::Form).body("username=bob").dispatch();
+    assert_eq!(assert_error(not_json, Status::NotFound, "not_found"), "no route for POST /api/users");
+}
+
+#[test]
+fn issues_and_revokes_api_tokens() {
+    let server = logged_in("alice");
+    let unnamed = server.post("/api/tokens").json(&json!({ "name": " " })).dispatch();
+    assert_error(unnamed, Status::UnprocessableEntity, "empty_name");
+    let response = server.post("/api/tokens").json(&json!({ "name": "ci" })).dispatch();
+    assert_eq!(response.status(), Status::Created);
+    let token: Value = response.into_json().unwrap();
+    assert_eq!(token["name"], "ci");
+    let secret = token["secret"].as_str().unwrap().to_string();
+    let id = token["id"].as_u64().unwrap();
+    let listed: Value = server.get("/api/tokens").dispatch().into_json().unwrap();
+    // the secret is only shown once
+    assert_eq!(listed, json!([{ "id": id, "name": "ci", "created_at": token["created_at"] }]));
+
+    // the token works without the session cookie
+    server.delete("/api/session").dispatch();
+    assert_eq!(server.get("/api/tokens").dispatch().status(), Status::Unauthorized);
+    let me: Value = server.get("/api/session").header(bearer(&secret)).dispatch().into_json().unwrap();
+    assert_eq!(me["username"], "alice");
+    assert_eq!(server.get("/api/session").header(bearer("not a token")).dispatch().status(), Status::Unauthorized);
+
+    let revoke = |id: u64| server.delete(format!("/api/tokens/{}", id)).header(bearer(&secret)).dispatch().status();
+    assert_eq!(revoke(id + 1), Status::NotFound);
+    assert_eq!(revoke(u64::MAX), Status::NotFound);
+    assert_eq!(revoke(id), Status::NoContent);
+    assert_eq!(revoke(id), Status::Unauthorized);
+}
+
+#[test]
+fn keeps_users_apart() {
+    let server = logged_in("alice");
+    let token: Value = server.post("/api/tokens").json(&json!({ "name": "ci" })).dispatch().into_json().unwrap();
+    server.delete("/api/session").dispatch();
+    assert_eq!(register(&server, "bob", "correct horse").status(), Status::Created);
+    assert_eq!(server.get("/api/tokens").dispatch().into_json::<Value>().unwrap(), json!([]));
+    let revoke = server.delete(format!("/api/tokens/{}", token["id"])).dispatch();
+    assert_eq!(revoke.status(), Status::NotFound);
+}
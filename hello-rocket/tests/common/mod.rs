@@ -0,0 +1,70 @@
+// shared by the integration tests, tests/common/mod.rs is not a test crate of its own. each test
+// crate uses only some of it.
+#![allow(dead_code)]
+
+use rocket::http::{ContentType, Header, Status};
+use rocket::local::blocking::{Client, LocalResponse};
+use rocket::figment::Figment;
+use rocket::{Build, Rocket};
+use rocket::serde::json::{json, Value};
+use std::ops::Deref;
+use tempfile::TempDir;
+
+// a client for the whole server, on a fresh SQLite file that goes away with it. the local client
+// dispatches requests in process, no port is bound. it keeps cookies like a browser does.
+pub struct Server {
+    client: Client,
+    _dir: TempDir,
+}
+
+impl Deref for Server {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        &self.client
+    }
+}
+
+pub fn server() -> Server {
+    server_with(|figment| figment)
+}
+
+// with Rocket.toml and the environment as read by the server, changed by `configure`
+pub fn server_with(configure: impl FnOnce(Figment) -> Figment) -> Server {
+    let (rocket, dir) = rocket_with(configure);
+    Server { client: Client::tracked(rocket).unwrap(), _dir: dir }
+}
+
+// the server for a client of its own, such as the async one. it uses the SQLite file in the
+// directory for as long as that is around.
+pub fn rocket_with(configure: impl FnOnce(Figment) -> Figment) -> (Rocket<Build>, TempDir) {
+    let dir = tempfile::tempdir().unwrap();
+    let database = dir.path().join("todos.sqlite");
+    let figment = configure(hello_rocket::rocket().figment().clone().merge(("database", database)));
+    (hello_rocket::rocket().configure(figment), dir)
+}
+
+// a server with `username` signed up and logged in
+pub fn logged_in(username: &str) -> Server {
+    let server = server();
+    assert_eq!(register(&server, username, "correct horse").status(), Status::Created);
+    server
+}
+
+pub fn register<'c>(client: &'c Client, username: &str, password: &str) -> LocalResponse<'c> {
+    let credentials = json!({ "username": username, "password": password });
+    client.post("/api/users").json(&credentials).dispatch()
+}
+
+pub fn bearer(secret: &str) -> Header<'static> {
+    Header::new("Authorization", format!("Bearer {}", secret))
+}
+
+// every API error is `{"code": .., "message": ..}`, returns the message
+pub fn assert_error(response: LocalResponse<'_>, status: Status, code: &str) -> String {
+    assert_eq!(response.status(), status);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    let body: Value = response.into_json().unwrap();
+    assert_eq!(body["code"], code, "{}", body);
+    body["message"].as_str().unwrap().to_string()
+}
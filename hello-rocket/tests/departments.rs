@@ -0,0 +1,56 @@
+use common::{assert_error, logged_in};
+use rocket::http::Status;
+use rocket::serde::json::{json, Value};
+
+mod common;
+
+#[test]
+fn lists_departments_sorted() {
+    let server = logged_in("alice");
+    for (name, department) in [("Sally", "Engineering"), ("Amir", "Sales"), ("Bea", "Engineering")] {
+        let path = format!("/api/departments/{}/employees", department);
+        let response = server.post(path).json(&json!({ "name": name })).dispatch();
+        assert_eq!(response.status(), Status::Created);
+    }
+    let hr = "/api/departments/Human%20Resources/employees";
+    let response = server.post(hr).json(&json!({ "name": " Zoe " })).dispatch();
+    assert_eq!(response.headers().get_one("Location"), Some(hr));
+    assert_eq!(response.into_json::<Value>().unwrap(), json!(["Zoe"]));
+
+    let everyone: Value = server.get("/api/departments").dispatch().into_json().unwrap();
+    let expected = json!({ "Engineering": ["Bea", "Sally"], "Human Resources": ["Zoe"], "Sales": ["Amir"] });
+    assert_eq!(everyone, expected);
+    assert_eq!(everyone.as_object().unwrap().keys().collect::<Vec<_>>(), ["Engineering", "Human Resources", "Sales"]);
+    let engineering: Value = server.get("/api/departments/Engineering/employees").dispatch().into_json().unwrap();
+    assert_eq!(engineering, json!(["Bea", "Sally"]));
+    let padded: Value = server.get("/api/departments/%20Engineering/employees").dispatch().into_json().unwrap();
+    assert_eq!(padded, engineering);
+    let marketing = server.get("/api/departments/Marketing/employees").dispatch();
+    let message = assert_error(marketing, Status::NotFound, "not_found");
+    assert_eq!(message, "nobody works in Marketing");
+}
+
+#[test]
+fn is_shared_by_everyone_logged_in() {
+    let server = logged_in("alice");
+    server.post("/api/departments/Sales/employees").json(&json!({ "name": "Amir" })).dispatch();
+    server.delete("/api/session").dispatch();
+    assert_error(server.get("/api/departments").dispatch(), Status::Unauthorized, "unauthorized");
+    assert_eq!(common::register(&server, "bob", "correct horse").status(), Status::Created);
+    let everyone: Value = server.get("/api/departments").dispatch().into_json().unwrap();
+    assert_eq!(everyone, json!({ "Sales": ["Amir"] }));
+}
+
+#[test]
+fn rejects_blank_names() {
+    let server = logged_in("alice");
+    let blank = server.post("/api/departments/Sales/employees").json(&json!({ "name": "  " })).dispatch();
+    assert_error(blank, Status::UnprocessableEntity, "invalid_name");
+    let blank = server.post("/api/departments/%20/employees").json(&json!({ "name": "Amir" })).dispatch();
+    assert_error(blank, Status::UnprocessableEntity, "invalid_department");
+    let blank = server.get("/api/departments/%20/employees").dispatch();
+    assert_error(blank, Status::UnprocessableEntity, "invalid_department");
+    let missing = server.post("/api/departments/Sales/employees").json(&json!({})).dispatch();
+    assert_error(missing, Status::UnprocessableEntity, "invalid_body");
+    assert_eq!(server.get("/api/departments").dispatch().into_json::<Value>().unwrap(), json!({}));
+}
@@ -0,0 +1,78 @@
+use common::{bearer, rocket_with};
+use rocket::http::{ContentType, Status};
+use rocket::local::asynchronous::Client;
+use rocket::serde::json::{self, json, Value};
+
+mod common;
+
+async fn register(client: &Client, username: &str) {
+    let credentials = json!({ "username": username, "password": "correct horse" });
+    assert_eq!(client.post("/api/users").json(&credentials).dispatch().await.status(), Status::Created);
+}
+
+// the `event:` and `data:` lines of each event in the stream
+fn events(stream: &str) -> Vec<(String, Value)> {
+    stream
+        .split("\n\n")
+        .filter_map(|event| {
+            let mut name = None;
+            let mut data = None;
+            for line in event.lines() {
+                if let Some(value) = line.strip_prefix("event:") {
+                    name = Some(value.to_string());
+                } else if let Some(value) = line.strip_prefix("data:") {
+                    data = Some(json::from_str(value).unwrap());
+                }
+            }
+            Some((name?, data?))
+        })
+        .collect()
+}
+
+#[rocket::async_test]
+async fn streams_the_changes_of_the_users_list() {
+    let (rocket, _dir) = rocket_with(|figment| figment);
+    let client = Client::tracked(rocket).await.unwrap();
+    // bob's changes do not show up in alice's stream
+    register(&client, "bob").await;
+    let token = client.post("/api/tokens").json(&json!({ "name": "ci" })).dispatch().await;
+    let token: Value = token.into_json().await.unwrap();
+    let bob = bearer(token["secret"].as_str().unwrap());
+    client.delete("/api/session").dispatch().await;
+    register(&client, "alice").await;
+
+    let stream = client.get("/api/todos/events").dispatch().await;
+    assert_eq!(stream.status(), Status::Ok);
+    assert_eq!(stream.content_type(), Some(ContentType::EventStream));
+
+    let milk = json!({ "description": "milk", "completed": false });
+    let bread = client.post("/api/todos").json(&milk).header(bob).dispatch().await;
+    assert_eq!(bread.status(), Status::Created);
+    let milk: Value = client.post("/api/todos").json(&milk).dispatch().await.into_json().await.unwrap();
+    let location = format!("/api/todos/{}", milk["id"]);
+    let done = json!({ "description": "milk", "completed": true });
+    let done: Value = client.put(&location).json(&done).dispatch().await.into_json().await.unwrap();
+    client.delete("/api/todos/completed").dispatch().await;
+    client.post("/api/todos").json(&json!({ "description": "eggs", "completed": false })).dispatch().await;
+    let eggs: Value = client.get("/api/todos").dispatch().await.into_json().await.unwrap();
+    client.delete(format!("/api/todos/{}", eggs[0]["id"])).dispatch().await;
+
+    // the stream ends with the server
+    client.rocket().shutdown().notify();
+    let stream = stream.into_string().await.unwrap();
+    let expected = vec![
+        ("created".to_string(), milk),
+        ("updated".to_string(), done),
+        ("cleared".to_string(), json!([])),
+        ("created".to_string(), eggs[0].clone()),
+        ("deleted".to_string(), json!({ "id": eggs[0]["id"] })),
+    ];
+    assert_eq!(events(&stream), expected);
+}
+
+#[rocket::async_test]
+async fn needs_a_user() {
+    let (rocket, _dir) = rocket_with(|figment| figment);
+    let client = Client::tracked(rocket).await.unwrap();
+    assert_eq!(client.get("/api/todos/events").dispatch().await.status(), Status::Unauthorized);
+}
@@ -0,0 +1,57 @@
+use common::{server, server_with};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::json;
+
+mod common;
+
+// Rocket.toml serves ../todo-app/static at / and ../yew-app/static at /yew, their index.html is
+// checked in even when the wasm is not built
+#[test]
+fn serves_the_frontends_with_a_fallback() {
+    let server = server();
+    for path in ["/", "/active", "/completed/again", "/yew", "/yew/counter"] {
+        let response = server.get(path).dispatch();
+        assert_eq!(response.status(), Status::Ok, "{}", path);
+        assert_eq!(response.content_type(), Some(ContentType::HTML));
+        assert_eq!(response.headers().get_one("Cache-Control"), Some("no-cache"));
+        let expected = if path.starts_with("/yew") { "/yew/wasm.js" } else { "\"/wasm.js\"" };
+        assert!(response.into_string().unwrap().contains(expected), "{}", path);
+    }
+    // missing files, hidden files, escapes and unknown API paths are not the app
+    for path in ["/missing.js", "/.gitignore", "/../Cargo.toml", "/api", "/api/unknown"] {
+        assert_eq!(server.get(path).dispatch().status(), Status::NotFound, "{}", path);
+    }
+}
+
+#[test]
+fn serves_assets_by_type() {
+    let dir = tempfile::tempdir().unwrap();
+    for file in ["index.html", "wasm.js", "wasm_bg.wasm", "app-1c9ef5e4a8b2d7f3.css"] {
+        std::fs::write(dir.path().join(file), file).unwrap();
+    }
+    let server = server_with(|figment| {
+        figment.merge(("frontends", json!({ "/": dir.path() }))).merge(("frontend_max_age", 60))
+    });
+    let expected = [
+        ("/wasm.js", ContentType::JavaScript, "public, max-age=60"),
+        ("/wasm_bg.wasm", ContentType::WASM, "public, max-age=60"),
+        ("/app-1c9ef5e4a8b2d7f3.css", ContentType::CSS, "public, max-age=31536000, immutable"),
+        ("/index.html", ContentType::HTML, "no-cache"),
+    ];
+    for (path, content_type, cache_control) in expected {
+        let response = server.get(path).dispatch();
+        assert_eq!(response.status(), Status::Ok);
+        assert_eq!(response.content_type(), Some(content_type));
+        assert_eq!(response.headers().get_one("Cache-Control"), Some(cache_control));
+        assert_eq!(response.into_string().unwrap(), path[1..]);
+    }
+}
+
+#[test]
+fn says_hello_without_a_frontend() {
+    let empty = tempfile::tempdir().unwrap();
+    let server = server_with(|figment| figment.merge(("frontends", json!({ "/": empty.path() }))));
+    let response = server.get("/").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.into_string().unwrap(), "hello, world!");
+}
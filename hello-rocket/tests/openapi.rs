@@ -0,0 +1,49 @@
+use common::server;
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::Value;
+
+mod common;
+
+#[test]
+fn describes_every_api_route() {
+    let server = server();
+    let response = server.get("/openapi.json").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    assert_eq!(response.content_type(), Some(ContentType::JSON));
+    let spec: Value = response.into_json().unwrap();
+
+    // the rate limiter's catch-all is not an operation
+    let routes: Vec<_> = server
+        .rocket()
+        .routes()
+        .filter(|route| route.uri.path().starts_with("/api") && route.name.as_deref() != Some("rate_limit"))
+        .collect();
+    assert!(!routes.is_empty());
+    for route in routes {
+        let path = route.uri.path().trim_end_matches('/').replace('<', "{").replace('>', "}");
+        let operation = &spec["paths"][&path][route.method.as_str().to_lowercase()];
+        assert!(operation.is_object(), "{} {} is not in the spec", route.method, path);
+    }
+
+    let put = &spec["paths"]["/api/todos/{id}"]["put"];
+    let schema = |body: &Value| body["content"]["application/json"]["schema"]["$ref"].clone();
+    assert_eq!(schema(&put["requestBody"]), "#/components/schemas/Entry");
+    assert_eq!(schema(&put["responses"]["200"]), "#/components/schemas/Todo");
+    assert_eq!(schema(&put["responses"]["422"]), "#/components/schemas/ApiError");
+    assert_eq!(put["security"][0]["token"], Value::Array(Vec::new()));
+    assert!(spec["paths"]["/api/users"]["post"]["security"].is_null());
+    assert_eq!(spec["components"]["securitySchemes"]["token"]["scheme"], "bearer");
+}
+
+#[test]
+fn serves_swagger_ui() {
+    let server = server();
+    let index = server.get("/docs/").dispatch();
+    assert_eq!(index.status(), Status::SeeOther);
+    let location = index.headers().get_one("Location").unwrap().to_string();
+    let page = server.get(location).dispatch();
+    assert_eq!(page.status(), Status::Ok);
+    assert_eq!(page.content_type(), Some(ContentType::HTML));
+    let config: Value = server.get("/docs/swagger-ui-config.json").dispatch().into_json().unwrap();
+    assert_eq!(config["url"], "../openapi.json");
+}
@@ -0,0 +1,66 @@
+use common::{assert_error, bearer, logged_in, register, server_with};
+use rocket::http::{Header, Status};
+use rocket::serde::json::{json, Value};
+use std::net::SocketAddr;
+
+mod common;
+
+#[test]
+fn limits_each_ip_and_token() {
+    let server = server_with(|figment| {
+        figment.merge(("rate_limit", json!({ "per_ip": 2, "per_token": 1, "window": 3600 })))
+    });
+    let from = |ip: &str| ip.parse::<SocketAddr>().unwrap();
+    let first = server.get("/api/session").remote(from("10.0.0.1:1000")).dispatch();
+    assert_eq!(first.status(), Status::Unauthorized);
+    assert_eq!(first.headers().get_one("X-RateLimit-Limit"), Some("2"));
+    assert_eq!(first.headers().get_one("X-RateLimit-Remaining"), Some("1"));
+    server.get("/api/session").remote(from("10.0.0.1:1001")).dispatch();
+
+    let limited = server.get("/api/session").remote(from("10.0.0.1:1002")).dispatch();
+    assert_eq!(limited.headers().get_one("Retry-After"), Some("1800"));
+    assert_eq!(limited.headers().get_one("X-RateLimit-Remaining"), Some("0"));
+    assert_error(limited, Status::TooManyRequests, "rate_limited");
+    // nothing ran for the refused request
+    let refused = server.post("/api/users").json(&json!({ "username": "alice", "password": "correct horse" }));
+    assert_eq!(refused.remote(from("10.0.0.1:1003")).dispatch().status(), Status::TooManyRequests);
+
+    // other clients and the frontends are not affected
+    assert_eq!(server.get("/api/session").remote(from("10.0.0.2:1000")).dispatch().status(), Status::Unauthorized);
+    assert_eq!(server.get("/").remote(from("10.0.0.1:1004")).dispatch().status(), Status::Ok);
+
+    // a made up token counts against the IP, and so does a request naming another IP itself
+    let made_up = server.get("/api/session").header(bearer("a token")).remote(from("10.0.0.1:1005")).dispatch();
+    assert_eq!(made_up.status(), Status::TooManyRequests);
+    let forged = server.get("/api/session").header(Header::new("X-Real-IP", "10.9.9.9"));
+    assert_eq!(forged.remote(from("10.0.0.1:1006")).dispatch().status(), Status::TooManyRequests);
+
+    // a valid one has a bucket of its own
+    assert_eq!(register(&server, "alice", "correct horse").status(), Status::Created);
+    let created = server.post("/api/tokens").json(&json!({ "name": "script" })).dispatch();
+    let secret = created.into_json::<Value>().unwrap()["secret"].as_str().unwrap().to_string();
+    let token = server.get("/api/session").header(bearer(&secret)).remote(from("10.0.0.1:1007")).dispatch();
+    assert_eq!(token.status(), Status::Ok);
+    assert_eq!(token.headers().get_one("X-RateLimit-Limit"), Some("1"));
+    let token = server.get("/api/session").header(bearer(&secret)).remote(from("10.0.0.1:1008")).dispatch();
+    assert_eq!(token.status(), Status::TooManyRequests);
+}
+
+#[test]
+fn ids_every_request() {
+    let server = logged_in("alice");
+    let response = server.get("/api/todos").dispatch();
+    let id = response.headers().get_one("X-Request-Id").unwrap().to_string();
+    assert_eq!(id.len(), 32);
+    let next = server.get("/api/todos").dispatch();
+    assert_ne!(next.headers().get_one("X-Request-Id"), Some(id.as_str()));
+
+    // one from upstream is kept, unless it is unfit for a log line
+    let forwarded = server.get("/api/todos").header(Header::new("X-Request-Id", "proxy-42")).dispatch();
+    assert_eq!(forwarded.headers().get_one("X-Request-Id"), Some("proxy-42"));
+    let forged = server.get("/").header(Header::new("X-Request-Id", "a b")).dispatch();
+    assert_ne!(forged.headers().get_one("X-Request-Id"), Some("a b"));
+    let missing = server.get("/api/nowhere").dispatch();
+    assert!(missing.headers().get_one("X-Request-Id").is_some());
+    assert_eq!(missing.into_json::<Value>().unwrap()["code"], "not_found");
+}
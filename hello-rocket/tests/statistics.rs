@@ -0,0 +1,66 @@
+use common::{assert_error, server, server_with};
+use rocket::http::{ContentType, Status};
+use rocket::serde::json::{json, Value};
+
+mod common;
+
+// the data statistics/src/main.rs started with
+const SAMPLE: [i32; 10] = [3, 1, 6, 1, 5, 8, 1, 8, 10, 11];
+
+#[test]
+fn summarizes_json_and_csv() {
+    let server = server();
+    let response = server.post("/api/statistics").json(&SAMPLE).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let summary: Value = response.into_json().unwrap();
+    let expected = json!({
+        "count": 10,
+        "mean": 5.4,
+        "median": 5.5,
+        "mode": [1.0],
+        "min": 1.0,
+        "max": 11.0,
+        "quantiles": [{ "p": 0.25, "value": 1.5 }, { "p": 0.5, "value": 5.5 }, { "p": 0.75, "value": 8.0 }],
+    });
+    assert_eq!(summary, expected);
+
+    let csv = "value\n3,1,6\n1,5\n8\n1,8,10,11\n";
+    let response = server.post("/api/statistics").header(ContentType::CSV).body(csv).dispatch();
+    assert_eq!(response.into_json::<Value>().unwrap(), expected);
+
+    let response = server.post("/api/statistics?q=0&q=1").json(&[2.5, -1.0, 2.5, -1.0]).dispatch();
+    let summary: Value = response.into_json().unwrap();
+    assert_eq!(summary["mode"], json!([-1.0, 2.5]));
+    assert_eq!(summary["quantiles"], json!([{ "p": 0.0, "value": -1.0 }, { "p": 1.0, "value": 2.5 }]));
+}
+
+#[test]
+fn rejects_bad_data() {
+    let server = server();
+    let post = |body: &'static str, content_type: ContentType| {
+        server.post("/api/statistics").header(content_type).body(body).dispatch()
+    };
+    assert_error(post("[]", ContentType::JSON), Status::UnprocessableEntity, "empty_data");
+    assert_error(post("value\n", ContentType::CSV), Status::UnprocessableEntity, "empty_data");
+    assert_error(post("[1, \"two\"]", ContentType::JSON), Status::UnprocessableEntity, "invalid_body");
+    assert_error(post("1,two", ContentType::CSV), Status::UnprocessableEntity, "invalid_csv");
+    assert_error(post("1,inf", ContentType::CSV), Status::UnprocessableEntity, "invalid_number");
+    let quantile = server.post("/api/statistics?q=1.5").json(&SAMPLE).dispatch();
+    assert_error(quantile, Status::UnprocessableEntity, "invalid_quantile");
+    assert_error(post("1 2 3", ContentType::Plain), Status::NotFound, "not_found");
+
+    let many = vec![1; hello_rocket::stats::MAX_VALUES + 1];
+    let response = server.post("/api/statistics").json(&many).dispatch();
+    assert_error(response, Status::PayloadTooLarge, "too_many_values");
+}
+
+#[test]
+fn caps_the_body_size() {
+    let server = server_with(|figment| figment.merge(("limits.json", 16)).merge(("limits.csv", 16)));
+    let large = server.post("/api/statistics").json(&SAMPLE).dispatch();
+    assert_error(large, Status::PayloadTooLarge, "payload_too_large");
+    let large = server.post("/api/statistics").header(ContentType::CSV).body("1,2,3,4,5,6,7,8,9,10").dispatch();
+    assert_error(large, Status::PayloadTooLarge, "payload_too_large");
+    let small = server.post("/api/statistics").header(ContentType::CSV).body("1,2,3").dispatch();
+    assert_eq!(small.status(), Status::Ok);
+}
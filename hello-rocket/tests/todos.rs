@@ -0,0 +1,107 @@
+use common::{assert_error, bearer, logged_in, server};
+use rocket::http::Status;
+use rocket::serde::json::{json, Value};
+
+mod common;
+
+#[test]
+fn creates_updates_and_deletes_todos() {
+    let server = logged_in("alice");
+    assert_eq!(server.get("/api/todos").dispatch().into_json::<Value>().unwrap(), json!([]));
+
+    let response = server.post("/api/todos").json(&json!({ "description": "  milk ", "completed": false })).dispatch();
+    assert_eq!(response.status(), Status::Created);
+    let location = response.headers().get_one("Location").unwrap().to_string();
+    let milk: Value = response.into_json().unwrap();
+    assert_eq!(milk["description"], "milk");
+    assert_eq!(milk["editing"], false);
+    assert_eq!(location, format!("/api/todos/{}", milk["id"]));
+    assert_eq!(server.get(&location).dispatch().into_json::<Value>().unwrap(), milk);
+
+    let oat_milk = json!({ "description": "oat milk", "completed": true, "editing": false });
+    let response = server.put(&location).json(&oat_milk).dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let updated: Value = response.into_json().unwrap();
+    assert_eq!(updated["description"], "oat milk");
+    assert_eq!(server.get("/api/todos").dispatch().into_json::<Value>().unwrap(), json!([updated]));
+
+    assert_eq!(server.delete(&location).dispatch().status(), Status::NoContent);
+    let message = assert_error(server.get(&location).dispatch(), Status::NotFound, "not_found");
+    assert_eq!(message, format!("no todo {}", milk["id"]));
+    assert_eq!(server.put(&location).json(&oat_milk).dispatch().status(), Status::NotFound);
+    assert_eq!(server.delete(&location).dispatch().status(), Status::NotFound);
+
+    // beyond what SQLite can store, still just no such todo
+    let beyond = "/api/todos/18446744073709551615";
+    assert_error(server.get(beyond).dispatch(), Status::NotFound, "not_found");
+    assert_eq!(server.put(beyond).json(&oat_milk).dispatch().status(), Status::NotFound);
+    assert_eq!(server.delete(beyond).dispatch().status(), Status::NotFound);
+}
+
+#[test]
+fn clears_completed_todos() {
+    let server = logged_in("alice");
+    for (description, completed) in [("milk", false), ("eggs", true), ("bread", true)] {
+        let todo = json!({ "description": description, "completed": completed });
+        assert_eq!(server.post("/api/todos").json(&todo).dispatch().status(), Status::Created);
+    }
+    let response = server.delete("/api/todos/completed").dispatch();
+    assert_eq!(response.status(), Status::Ok);
+    let rest: Value = response.into_json().unwrap();
+    assert_eq!(rest.as_array().unwrap().len(), 1);
+    assert_eq!(rest[0]["description"], "milk");
+    assert_eq!(server.get("/api/todos").dispatch().into_json::<Value>().unwrap(), rest);
+}
+
+#[test]
+fn rejects_invalid_todos() {
+    let server = logged_in("alice");
+    let blank = server.post("/api/todos").json(&json!({ "description": " ", "completed": false })).dispatch();
+    assert_error(blank, Status::UnprocessableEntity, "empty_description");
+    let missing = server.post("/api/todos").json(&json!({ "description": "milk" })).dispatch();
+    assert_error(missing, Status::UnprocessableEntity, "invalid_body");
+    let malformed = server.post("/api/todos").json(&json!([])).dispatch();
+    assert_error(malformed, Status::UnprocessableEntity, "invalid_body");
+    assert_error(server.get("/api/todos/milk").dispatch(), Status::NotFound, "not_found");
+}
+
+#[test]
+fn needs_a_user() {
+    let server = server();
+    let todo = json!({ "description": "milk", "completed": false });
+    let responses = [
+        server.get("/api/todos").dispatch(),
+        server.get("/api/todos/1").dispatch(),
+        server.post("/api/todos").json(&todo).dispatch(),
+        server.put("/api/todos/1").json(&todo).dispatch(),
+        server.delete("/api/todos/1").dispatch(),
+        server.delete("/api/todos/completed").dispatch(),
+        server.get("/api/tokens").dispatch(),
+        server.post("/api/tokens").json(&json!({ "name": "ci" })).dispatch(),
+        server.delete("/api/tokens/1").dispatch(),
+        server.get("/api/todos").header(bearer("not a token")).dispatch(),
+    ];
+    for response in responses {
+        assert_error(response, Status::Unauthorized, "unauthorized");
+    }
+}
+
+#[test]
+fn keeps_todos_to_their_user() {
+    let server = logged_in("alice");
+    let todo = json!({ "description": "milk", "completed": false });
+    let milk: Value = server.post("/api/todos").json(&todo).dispatch().into_json().unwrap();
+    let token: Value = server.post("/api/tokens").json(&json!({ "name": "ci" })).dispatch().into_json().unwrap();
+    let alice = bearer(token["secret"].as_str().unwrap());
+
+    server.delete("/api/session").dispatch();
+    assert_eq!(common::register(&server, "bob", "correct horse").status(), Status::Created);
+    let location = format!("/api/todos/{}", milk["id"]);
+    assert_eq!(server.get("/api/todos").dispatch().into_json::<Value>().unwrap(), json!([]));
+    assert_eq!(server.get(&location).dispatch().status(), Status::NotFound);
+    assert_eq!(server.put(&location).json(&todo).dispatch().status(), Status::NotFound);
+    assert_eq!(server.delete(&location).dispatch().status(), Status::NotFound);
+    // alice's token still sees them while bob is logged in
+    let listed: Value = server.get("/api/todos").header(alice).dispatch().into_json().unwrap();
+    assert_eq!(listed, json!([milk]));
+}
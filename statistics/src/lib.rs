@@ -0,0 +1,140 @@
+use std::cmp::Ordering;
+
+// the values below the first one (the pivot), those equal to it and those above. keeping the equal
+// ones apart means data with many duplicates is not split one value at a time.
+pub fn partition<T: PartialOrd + Copy>(data: &[T]) -> Option<(Vec<T>, Vec<T>, Vec<T>)> {
+    let (&pivot, tail) = data.split_first()?;
+    // the pivot goes in by itself, so that one which is not equal to itself (NaN) is still taken out
+    let (mut less, mut equal, mut greater) = (vec![], vec![pivot], vec![]);
+    for &next in tail {
+        if next < pivot {
+            less.push(next);
+        } else if next == pivot {
+            equal.push(next);
+        } else {
+            greater.push(next);
+        }
+    }
+    Some((less, equal, greater))
+}
+
+// the value that would be at index k if data were sorted (quickselect)
+pub fn select<T: PartialOrd + Copy>(data: &[T], k: usize) -> Option<T> {
+    // partitions around the middle value rather than the first, sorted data would otherwise take
+    // n rounds. a loop and not recursion, so large inputs do not run out of stack.
+    let mut data = data.to_vec();
+    let mut k = k;
+    loop {
+        if data.is_empty() {
+            return None;
+        }
+        let middle = data.len() / 2;
+        data.swap(0, middle);
+        let (less, equal, greater) = partition(&data)?;
+
+        if k < less.len() {
+            data = less;
+        } else if k < less.len() + equal.len() {
+            return Some(equal[0]);
+        } else {
+            k -= less.len() + equal.len();
+            data = greater;
+        }
+    }
+}
+
+pub fn median<T: PartialOrd + Copy + Into<f64>>(data: &[T]) -> Option<f64> {
+    let size = data.len();
+    match size {
+        even if even % 2 == 0 => {
+            let fst_med = select(data, (even / 2).checked_sub(1)?);
+            let snd_med = select(data, even / 2);
+
+            match (fst_med, snd_med) {
+                (Some(fst), Some(snd)) => Some((fst.into() + snd.into()) / 2.0),
+                _ => None,
+            }
+        }
+        odd => select(data, odd / 2).map(Into::into),
+    }
+}
+
+// the value below which a fraction p (0 to 1) of data lies, interpolated between the two closest
+// values as spreadsheets do. quantile(data, 0.5) is the median.
+pub fn quantile<T: PartialOrd + Copy + Into<f64>>(data: &[T], p: f64) -> Option<f64> {
+    if !(0.0..=1.0).contains(&p) {
+        return None;
+    }
+    let position = (data.len().checked_sub(1)?) as f64 * p;
+    let below = position.floor() as usize;
+    let low: f64 = select(data, below)?.into();
+    if position == below as f64 {
+        return Some(low);
+    }
+    let high: f64 = select(data, below + 1)?.into();
+    Some(low + (high - low) * (position - below as f64))
+}
+
+pub fn mean<T: Copy + Into<f64>>(data: &[T]) -> Option<f64> {
+    match data.len() {
+        0 => None,
+        size => Some(data.iter().map(|&value| value.into()).sum::<f64>() / size as f64),
+    }
+}
+
+// the most frequent values, smallest first. counted over a sorted copy so that it also works for
+// floats, which cannot be hash map keys.
+pub fn modes<T: PartialOrd + Copy>(data: &[T]) -> Vec<T> {
+    let mut sorted = data.to_vec();
+    sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+    let mut modes = Vec::new();
+    let mut best = 0;
+    let mut start = 0;
+    while start < sorted.len() {
+        let count = sorted[start..].iter().take_while(|&&value| value == sorted[start]).count();
+        match count.cmp(&best) {
+            Ordering::Greater => {
+                best = count;
+                modes = vec![sorted[start]];
+            }
+            Ordering::Equal => modes.push(sorted[start]),
+            Ordering::Less => {}
+        }
+        start += count;
+    }
+    modes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn selects_in_order() {
+        let data = [3, 1, 6, 1, 5, 8, 1, 8, 10, 11];
+        let mut sorted = data.to_vec();
+        sorted.sort_unstable();
+        for (k, &value) in sorted.iter().enumerate() {
+            assert_eq!(select(&data, k), Some(value));
+        }
+        assert_eq!(select(&data, data.len()), None);
+        assert_eq!(partition(&data), Some((vec![1, 1, 1], vec![3], vec![6, 5, 8, 8, 10, 11])));
+    }
+
+    #[test]
+    fn selects_from_duplicates_quickly() {
+        let data = vec![7.5; 100_000];
+        let start = Instant::now();
+        assert_eq!(select(&data, 0), Some(7.5));
+        assert_eq!(median(&data), Some(7.5));
+        assert_eq!(quantile(&data, 0.9), Some(7.5));
+        assert!(start.elapsed() < Duration::from_secs(1), "took {:?}", start.elapsed());
+
+        let mut data = vec![2; 50_000];
+        data.extend(vec![1; 50_000]);
+        assert_eq!(select(&data, 49_999), Some(1));
+        assert_eq!(select(&data, 50_000), Some(2));
+    }
+}
@@ -1,60 +1,4 @@
-use std::cmp::Ordering;
-use std::collections::HashMap;
-
-fn partition(data: &[i32]) -> Option<(Vec<i32>, i32, Vec<i32>)> {
-    match data.len() {
-        0 => None,
-        _ => {
-            let (pivot_slice, tail) = data.split_at(1);
-            let pivot = pivot_slice[0];
-            let (left, right) = tail.iter().fold((vec![], vec![]), |mut splits, next| {
-                {
-                    let (ref mut left, ref mut right) = &mut splits;
-                    if next < &pivot {
-                        left.push(*next);
-                    } else {
-                        right.push(*next);
-                    }
-            }
-            splits
-            });
-            Some((left, pivot, right))
-        }
-    }
-}
-
-fn select(data: &[i32], k: usize) -> Option<i32> {
-    let part = partition(data);
-
-    match part {
-        None => None,
-        Some((left, pivot, right)) => {
-            let pivot_idx = left.len();
-
-            match pivot_idx.cmp(&k) {
-                Ordering::Equal => Some(pivot),
-                Ordering::Greater => select(&left, k),
-                Ordering::Less => select(&right, k - (pivot_idx + 1)),
-            }
-        },
-    }
-}
-
-fn median(data: &[i32]) -> Option<f32> {
-    let size = data.len();
-    match size {
-        even if even % 2 == 0 => {
-            let fst_med = select(data, (even / 2) - 1);
-            let snd_med = select(data, even / 2);
-
-            match (fst_med, snd_med) {
-                (Some(fst), Some(snd)) => Some((fst + snd) as f32 / 2.0),
-                _ => None
-            }
-        },
-        odd => select(data, odd / 2).map(|x| x as f32)
-    }
-}
+use statistics::{median, modes, partition, select};
 
 fn main() {
     let data = [3, 1, 6, 1, 5, 8, 1 , 8, 10, 11];
@@ -68,12 +12,7 @@ fn main() {
     let med = median(&data);
     println!("Median is {:?}", med);
 
-    let frequencies = data.iter().fold(HashMap::new(), |mut freqs, value| {
-        *freqs.entry(value).or_insert(0) += 1;
-        freqs
-    });
-
-    let mode = frequencies.into_iter().max_by_key(|&(_, count)| count).map(|(value, _)| *value);
+    let mode = modes(&data).first().copied();
 
     println!("Mode of data is {:?}", mode);
 }
@@ -176,7 +176,7 @@ impl Component for Model{
             <footer class="info">
                 <p>{ "Double click to edit a todo" }</p>
                 <p>{ "Written by " }<a href="https://github.com/tranvietphuoc" target="_blank">{ "Tran Viet Phuoc" }</a></p>
-                <p>{ "Part of " }<a href="http://localhost:8000" target="_blank">{ "TodoApp" }</a></p>
+                <p>{ "Part of " }<a href="/" target="_blank">{ "TodoApp" }</a></p>
             </footer>
             </div>
         }
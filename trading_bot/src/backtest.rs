@@ -0,0 +1,116 @@
+use crate::bot::{TradingBot, TradingConfig};
+use crate::market::SimulatedMarket;
+use crate::money::{Money, Price};
+use crate::BoxError;
+use rust_decimal::Decimal;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// one row of historical data
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PricePoint {
+    pub time: SystemTime,
+    pub price: Price,
+}
+
+// reads `timestamp,price` rows, timestamps in unix seconds. a header line is skipped.
+pub fn load_prices(path: &Path) -> Result<Vec<PricePoint>, BoxError> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+    parse_prices(&text).map_err(|e| format!("{}: {}", path.display(), e).into())
+}
+
+pub fn parse_prices(text: &str) -> Result<Vec<PricePoint>, BoxError> {
+    let mut points = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (idx == 0 && line.starts_with(|c: char| c.is_ascii_alphabetic())) {
+            continue;
+        }
+        let (time, price) = line.split_once(',').ok_or_else(|| format!("line {}: expected `timestamp,price`", idx + 1))?;
+        let seconds: u64 = time.trim().parse().map_err(|e| format!("line {}: bad timestamp: {}", idx + 1, e))?;
+        let price: Price = price.trim().parse().map_err(|e| format!("line {}: bad price: {}", idx + 1, e))?;
+        points.push(PricePoint { time: UNIX_EPOCH + Duration::from_secs(seconds), price });
+    }
+    Ok(points)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BacktestReport {
+    pub cycles: usize,
+    pub trades: usize,
+    pub start_equity: Money,
+    pub final_equity: Money,
+    // both in percent
+    pub total_return: Decimal,
+    pub max_drawdown: Decimal,
+}
+
+impl fmt::Display for BacktestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "cycles:        {}", self.cycles)?;
+        writeln!(f, "trades:        {}", self.trades)?;
+        writeln!(f, "start equity:  {} $", self.start_equity)?;
+        writeln!(f, "final equity:  {} $", self.final_equity)?;
+        writeln!(f, "total return:  {} %", self.total_return.round_dp(2))?;
+        write!(f, "max drawdown:  {} %", self.max_drawdown.round_dp(2))
+    }
+}
+
+// replays `prices` through a simulated market, one bot cycle per price
+pub async fn run_backtest(config: TradingConfig, balance: Money, prices: &[PricePoint]) -> Result<BacktestReport, BoxError> {
+    let market = Arc::new(SimulatedMarket::new(prices.iter().map(|p| p.price).collect(), balance, config.symbol_rules));
+    let mut bot = TradingBot::new(config, Box::new(market.clone()));
+
+    let mut peak = balance;
+    let mut max_drawdown = Decimal::ZERO;
+    for point in prices {
+        bot.run_cycle_at(point.time).await?;
+        let equity = market.equity().ok_or("equity overflowed")?;
+        peak = peak.max(equity);
+        if let Some(drawdown) = equity.percentage_change_from(peak) {
+            max_drawdown = max_drawdown.max(-drawdown);
+        }
+    }
+
+    let final_equity = market.equity().unwrap_or(balance);
+    let total_return = final_equity.percentage_change_from(balance).ok_or("starting balance must be positive")?;
+    Ok(BacktestReport {
+        cycles: prices.len(),
+        trades: market.fills().len(),
+        start_equity: balance,
+        final_equity,
+        total_return,
+        max_drawdown,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::risk::RiskConfig;
+
+    #[test]
+    fn parses_price_rows() {
+        let points = parse_prices("timestamp,price\n60,40000.5\n\n120, 39000\n").unwrap();
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[1].time, UNIX_EPOCH + Duration::from_secs(120));
+        assert_eq!(points[1].price, "39000".parse().unwrap());
+        assert!(parse_prices("60;40000").unwrap_err().to_string().contains("line 1"));
+    }
+
+    #[tokio::test]
+    async fn replays_prices_with_their_own_clock() {
+        let prices = parse_prices("0,40000\n60,38000\n90,37500\n120,39000\n180,39500\n").unwrap();
+        let risk = RiskConfig { position_fraction: Decimal::ONE, cooldown: Duration::from_secs(60), ..RiskConfig::default() };
+        let config = TradingConfig { risk, ..TradingConfig::default() };
+        let report = run_backtest(config, "1000".parse().unwrap(), &prices).await.unwrap();
+        assert_eq!(report.trades, 2);
+        assert_eq!(report.final_equity, "1026.31".parse().unwrap());
+        assert_eq!(report.total_return, "2.631".parse().unwrap());
+        // 0.02631 BTC at 37500 plus 0.22 $ left over is 986.845 $
+        assert_eq!(report.max_drawdown, "1.3155".parse().unwrap());
+    }
+}
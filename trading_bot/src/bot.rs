@@ -0,0 +1,734 @@
+use crate::feed::{PriceFeed, Tick};
+use crate::journal::{self, Journal, JournalEvent, JournalRecord};
+use crate::logging::{self, Cycle};
+use crate::market::{Market, Order, OrderStatus, Side};
+use crate::metrics::Metrics;
+use crate::money::{Money, Price, Quantity, SymbolRules};
+use crate::notify::{Alert, Notifier};
+use crate::portfolio::{Allocator, SymbolStatus};
+use crate::risk::{ExitReason, RiskConfig, RiskManager};
+use crate::BoxError;
+use futures_util::StreamExt;
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::mem;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum State {
+    Buy,
+    Sell,
+}
+
+// how the bot sends its orders
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderKind {
+    Market,
+    // a limit `offset` percent better than the current price, cancelled after `timeout`
+    // with whatever filled by then
+    Limit { offset: Decimal, timeout: Duration },
+}
+
+// a limit order the bot is waiting on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenOrder {
+    pub id: String,
+    pub side: Side,
+    pub placed_at: SystemTime,
+}
+
+// thresholds are percentages relative to last_operation_price
+#[derive(Debug, Clone)]
+pub struct TradingConfig {
+    pub symbol: String,
+    pub upward_trend_threshold: Decimal,
+    pub dip_threshold: Decimal,
+    pub profit_threshold: Decimal,
+    pub stop_loss_threshold: Decimal,
+    pub polling_interval: Duration,
+    pub symbol_rules: SymbolRules,
+    pub orders: OrderKind,
+    pub risk: RiskConfig,
+    pub last_operation_price: Price,
+    pub next_operation: State,
+    // base asset bought by the last buy and not sold yet
+    pub position: Quantity,
+}
+
+impl Default for TradingConfig {
+    fn default() -> Self {
+        TradingConfig {
+            symbol: "BTCUSD".to_string(),
+            upward_trend_threshold: Decimal::new(150, 2),
+            dip_threshold: Decimal::new(-225, 2),
+            profit_threshold: Decimal::new(125, 2),
+            stop_loss_threshold: Decimal::new(-200, 2),
+            polling_interval: Duration::from_secs(30),
+            symbol_rules: SymbolRules::default(),
+            orders: OrderKind::Market,
+            risk: RiskConfig::default(),
+            last_operation_price: Price::ZERO,
+            next_operation: State::Buy,
+            position: Quantity::ZERO,
+        }
+    }
+}
+
+pub struct TradingBot {
+    pub trading_config: TradingConfig,
+    pub market: Box<dyn Market>,
+    pub risk: RiskManager,
+    // shared by every bot of a portfolio, records carry the symbol
+    pub journal: Option<Arc<Mutex<Journal>>>,
+    pub allocator: Option<Arc<Allocator>>,
+    pub metrics: Option<Arc<Metrics>>,
+    pub notifier: Option<Arc<dyn Notifier>>,
+    pub open_order: Option<OpenOrder>,
+    last_price: Option<Price>,
+    trades: usize,
+    // numbers the cycles for their log lines
+    cycles: u64,
+    // raised during the current cycle, sent once it is over
+    alerts: Vec<Alert>,
+}
+
+impl TradingBot {
+    pub fn new(trading_config: TradingConfig, market: Box<dyn Market>) -> Self {
+        let risk = RiskManager::new(trading_config.risk.clone());
+        TradingBot {
+            trading_config,
+            market,
+            risk,
+            journal: None,
+            allocator: None,
+            metrics: None,
+            notifier: None,
+            open_order: None,
+            last_price: None,
+            trades: 0,
+            cycles: 0,
+            alerts: Vec::new(),
+        }
+    }
+
+    // restore the state recorded in the journal at `path`, then keep recording to it
+    pub fn with_journal(self, path: &Path) -> Result<Self, BoxError> {
+        let journal = Journal::open(path)?;
+        self.with_shared_journal(Arc::new(Mutex::new(journal)))
+    }
+
+    // same as with_journal, for a journal other bots write to as well
+    pub fn with_shared_journal(mut self, journal: Arc<Mutex<Journal>>) -> Result<Self, BoxError> {
+        let path = journal.lock().unwrap().path().to_path_buf();
+        let records = Journal::read(&path)?;
+        self.open_order = journal::restore(&records, &mut self.trading_config, &mut self.risk);
+        let config = &self.trading_config;
+        if records.iter().any(|record| record.symbol == config.symbol) {
+            info!(
+                "[RESTORE] {} next operation {:?} from {} holding {}",
+                config.symbol, config.next_operation, config.last_operation_price, config.position
+            );
+        }
+        if let Some(order) = &self.open_order {
+            info!("[RESTORE] {} {:?} order {} is still open", config.symbol, order.side, order.id);
+        }
+        self.journal = Some(journal);
+        Ok(self)
+    }
+
+    // buys are sized by the allocator too, so this bot keeps to its share of a portfolio
+    pub fn with_allocator(mut self, allocator: Arc<Allocator>) -> Self {
+        self.allocator = Some(allocator);
+        self
+    }
+
+    // counts signals, orders and fills, and exports the status after every cycle. market calls
+    // are timed by wrapping the market in an InstrumentedMarket.
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    // fills, risk exits and the daily loss limit are sent to `notifier` at the end of each cycle
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    pub fn status(&self) -> SymbolStatus {
+        SymbolStatus {
+            symbol: self.trading_config.symbol.clone(),
+            last_price: self.last_price,
+            next_operation: self.trading_config.next_operation,
+            position: self.trading_config.position,
+            realized_pnl: self.risk.realized_pnl(),
+            trades: self.trades,
+        }
+    }
+
+    fn metric(&self, update: impl FnOnce(&Metrics, &str)) {
+        if let Some(metrics) = &self.metrics {
+            update(metrics, &self.trading_config.symbol);
+        }
+    }
+
+    fn next_cycle(&mut self) -> Cycle {
+        self.cycles += 1;
+        let symbol = self.trading_config.symbol.clone();
+        Cycle { id: format!("{}-{}", symbol, self.cycles), symbol }
+    }
+
+    // counts the cycle and exports where it left the bot
+    fn observe_cycle(&self, result: &Result<(), BoxError>) {
+        self.metric(|metrics, symbol| {
+            metrics.cycle(symbol, result.is_ok());
+            metrics.set_status(&self.status());
+        });
+    }
+
+    fn alert(&mut self, alert: Alert) {
+        if self.notifier.is_some() {
+            self.alerts.push(alert);
+        }
+    }
+
+    // delivers `alert` right away, a failure is only logged
+    pub async fn notify(&self, alert: Alert) {
+        if let Some(notifier) = &self.notifier {
+            if let Err(e) = notifier.notify(&alert).await {
+                warn!("[ALERT] {} cannot deliver \"{}\": {}", self.trading_config.symbol, alert, e);
+            }
+        }
+    }
+
+    async fn send_alerts(&mut self) {
+        for alert in mem::take(&mut self.alerts) {
+            self.notify(alert).await;
+        }
+    }
+
+    fn record(&mut self, now: SystemTime, event: JournalEvent) -> Result<(), BoxError> {
+        match &self.journal {
+            Some(journal) => journal.lock().unwrap().append(&JournalRecord::new(now, &self.trading_config.symbol, event)),
+            None => Ok(()),
+        }
+    }
+
+    fn record_state(&mut self, now: SystemTime) -> Result<(), BoxError> {
+        let config = &self.trading_config;
+        let event = JournalEvent::State {
+            last_operation_price: config.last_operation_price,
+            next_operation: config.next_operation,
+            position: config.position,
+        };
+        self.record(now, event)
+    }
+
+    // main trading logic
+    // high sell, low buy
+    pub async fn start(&mut self) -> Result<(), BoxError> {
+        loop {
+            self.run_cycle().await?;
+            tokio::time::sleep(self.trading_config.polling_interval).await;
+        }
+    }
+
+    pub async fn run_cycle(&mut self) -> Result<(), BoxError> {
+        self.run_cycle_at(SystemTime::now()).await
+    }
+
+    // one polling cycle; `now` drives cooldowns and daily limits, so backtests pass the time of
+    // the replayed price instead of the wall clock
+    pub async fn run_cycle_at(&mut self, now: SystemTime) -> Result<(), BoxError> {
+        let cycle = self.next_cycle();
+        let result = logging::in_cycle(cycle, async {
+            let current_price = self.market.get_market_price().await?;
+            info!("[PRICE] {} current market price: {}", self.trading_config.symbol, current_price);
+            let result = self.on_price(current_price, now).await;
+            self.send_alerts().await;
+            result
+        })
+        .await;
+        self.observe_cycle(&result);
+        result
+    }
+
+    // trade on every tick of the feed instead of polling the market, until the feed ends
+    pub async fn start_streaming(&mut self, feed: &dyn PriceFeed) -> Result<(), BoxError> {
+        let mut ticks = feed.subscribe(&self.trading_config.symbol).await?;
+        while let Some(tick) = ticks.next().await {
+            self.on_tick(&tick?).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn on_tick(&mut self, tick: &Tick) -> Result<(), BoxError> {
+        let cycle = self.next_cycle();
+        let result = logging::in_cycle(cycle, async {
+            debug!("[TICK] {} {} at {:?}", tick.symbol, tick.price, tick.time);
+            let result = self.on_price(tick.price, tick.time).await;
+            self.send_alerts().await;
+            result
+        })
+        .await;
+        self.observe_cycle(&result);
+        result
+    }
+
+    async fn on_price(&mut self, current_price: Price, now: SystemTime) -> Result<(), BoxError> {
+        self.last_price = Some(current_price);
+
+        // nothing new goes out while a limit order is still working
+        if self.reconcile(current_price, now).await? {
+            return Ok(());
+        }
+
+        // risk exits come before anything the strategy wants to do
+        if self.trading_config.next_operation == State::Sell {
+            if let Some(reason) = self.risk.exit_signal(current_price) {
+                info!("[RISK] {} {:?} triggered at {}", self.trading_config.symbol, reason, current_price);
+                let signal = match reason {
+                    ExitReason::StopLoss => "stop_loss",
+                    ExitReason::TakeProfit => "take_profit",
+                };
+                self.metric(|metrics, symbol| metrics.signal(symbol, signal));
+                let symbol = self.trading_config.symbol.clone();
+                self.alert(Alert::RiskExit { symbol, reason, price: current_price });
+                self.sell(now).await?;
+                return Ok(());
+            }
+        }
+
+        // the first price seen becomes the reference for the next operation
+        let percentage_diff = match current_price.percentage_change_from(self.trading_config.last_operation_price) {
+            Some(diff) => diff,
+            None => {
+                self.trading_config.last_operation_price = current_price;
+                return self.record_state(now);
+            }
+        };
+
+        match self.trading_config.next_operation {
+            State::Buy => self.try_to_buy(current_price, percentage_diff, now).await?,
+            State::Sell => self.try_to_sell(current_price, percentage_diff, now).await?,
+        };
+        Ok(())
+    }
+
+    // get the buy point
+    // buy action
+    async fn try_to_buy(&mut self, current_price: Price, diff: Decimal, now: SystemTime) -> Result<Price, BoxError> {
+        let config = &self.trading_config;
+        if diff >= config.upward_trend_threshold || diff <= config.dip_threshold {
+            if let Err(rejection) = self.risk.check_buy(now) {
+                info!("[RISK] {} buy skipped: {}", config.symbol, rejection);
+                self.metric(|metrics, symbol| metrics.signal(symbol, "buy_blocked"));
+                return Ok(self.trading_config.last_operation_price);
+            }
+            self.metric(|metrics, symbol| metrics.signal(symbol, "buy"));
+            let current_balance = self.market.get_balance().await?;
+            info!("[BALANCE] {} current balance {}", config.symbol, current_balance);
+            let budget = self
+                .risk
+                .position_budget(current_balance, config.position, current_price)
+                .ok_or("position budget overflowed")?;
+            let price = self.order_price(Side::Buy, current_price).ok_or("order price overflowed")?;
+            let (quantity, reserved) = match &self.allocator {
+                Some(allocator) => {
+                    let granted = allocator.reserve(&config.symbol, budget, current_balance);
+                    match config.symbol_rules.quantity_for(granted, price) {
+                        Ok(quantity) => (quantity, Some(granted)),
+                        Err(e) => {
+                            allocator.settle(&config.symbol, granted, Money::ZERO);
+                            if granted < budget {
+                                info!("[PORTFOLIO] {} buy skipped, {} allocated: {}", config.symbol, granted, e);
+                                return Ok(config.last_operation_price);
+                            }
+                            return Err(e.into());
+                        }
+                    }
+                }
+                None => (config.symbol_rules.quantity_for(budget, price)?, None),
+            };
+            self.place(Side::Buy, quantity, price, reserved, now).await?;
+        }
+        Ok(self.trading_config.last_operation_price)
+    }
+
+    // get the sell point
+    // sell action
+    async fn try_to_sell(&mut self, current_price: Price, diff: Decimal, now: SystemTime) -> Result<Price, BoxError> {
+        let config = &self.trading_config;
+        if diff >= config.profit_threshold || diff <= config.stop_loss_threshold {
+            if let Err(rejection) = self.risk.check_sell(now) {
+                info!("[RISK] {} sell skipped: {}", config.symbol, rejection);
+                self.metric(|metrics, symbol| metrics.signal(symbol, "sell_blocked"));
+                return Ok(self.trading_config.last_operation_price);
+            }
+            self.metric(|metrics, symbol| metrics.signal(symbol, "sell"));
+            let quantity = config.symbol_rules.floor_quantity(config.position).ok_or("position overflowed")?;
+            let price = self.order_price(Side::Sell, current_price).ok_or("order price overflowed")?;
+            self.place(Side::Sell, quantity, price, None, now).await?;
+        }
+        Ok(self.trading_config.last_operation_price)
+    }
+
+    // risk exits do not wait for a limit order to fill
+    async fn sell(&mut self, now: SystemTime) -> Result<Price, BoxError> {
+        let config = &self.trading_config;
+        let quantity = config.symbol_rules.floor_quantity(config.position).ok_or("position overflowed")?;
+        let placed = self.market.place_sell_order(quantity).await;
+        self.metric(|metrics, symbol| metrics.order_placed(symbol, Side::Sell, "market", placed.is_ok()));
+        let price = placed?;
+        self.book(Side::Sell, quantity, price, now)?;
+        self.record_state(now)?;
+        Ok(price)
+    }
+
+    // the price a new order goes out at: the current one for market orders, `offset` percent
+    // better and on the tick grid for limit orders
+    fn order_price(&self, side: Side, current_price: Price) -> Option<Price> {
+        let rules = &self.trading_config.symbol_rules;
+        let offset = match self.trading_config.orders {
+            OrderKind::Market => return Some(current_price),
+            OrderKind::Limit { offset, .. } => offset.checked_div(Decimal::ONE_HUNDRED)?,
+        };
+        match side {
+            Side::Buy => rules.floor_price(current_price.checked_scale(Decimal::ONE.checked_sub(offset)?)?),
+            Side::Sell => rules.ceil_price(current_price.checked_scale(Decimal::ONE.checked_add(offset)?)?),
+        }
+    }
+
+    // sends an order for `quantity` at `price`. a market order is booked right away, a limit
+    // order once reconcile finds it done. `reserved` is what the allocator set aside for a buy.
+    async fn place(
+        &mut self,
+        side: Side,
+        quantity: Quantity,
+        price: Price,
+        reserved: Option<Money>,
+        now: SystemTime,
+    ) -> Result<(), BoxError> {
+        let placed = match (self.trading_config.orders, side) {
+            (OrderKind::Market, Side::Buy) => self.market.place_buy_order(quantity).await.map(|price| (price, None)),
+            (OrderKind::Market, Side::Sell) => self.market.place_sell_order(quantity).await.map(|price| (price, None)),
+            (OrderKind::Limit { .. }, _) => {
+                self.market.place_limit_order(side, quantity, price).await.map(|order| (price, Some(order)))
+            }
+        };
+        let order_type = match self.trading_config.orders {
+            OrderKind::Market => "market",
+            OrderKind::Limit { .. } => "limit",
+        };
+        self.metric(|metrics, symbol| metrics.order_placed(symbol, side, order_type, placed.is_ok()));
+        if let (Some(allocator), Some(reserved)) = (&self.allocator, reserved) {
+            // the funds count against the symbol as soon as the order is out
+            let spent = placed.as_ref().ok().and_then(|(price, _)| price.checked_notional(quantity));
+            allocator.settle(&self.trading_config.symbol, reserved, spent.unwrap_or(Money::ZERO));
+        }
+
+        match placed? {
+            (price, None) => {
+                self.book(side, quantity, price, now)?;
+                self.record_state(now)
+            }
+            (price, Some(order)) => {
+                let id = order.id.clone();
+                self.record(now, JournalEvent::OrderPlaced { id: id.clone(), side, quantity, price })?;
+                info!("[ORDER] {} placed {:?} order {} for {} at {}", self.trading_config.symbol, side, id, quantity, price);
+                self.open_order = Some(OpenOrder { id, side, placed_at: now });
+                if order.status.is_done() {
+                    self.finish(order, now)?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    // follows up on the open limit order and cancels it once it timed out, or when a risk exit
+    // needs the position back. returns whether the order is still working.
+    async fn reconcile(&mut self, current_price: Price, now: SystemTime) -> Result<bool, BoxError> {
+        let open = match &self.open_order {
+            Some(open) => open.clone(),
+            None => return Ok(false),
+        };
+        let mut order = self.market.get_order(&open.id).await?;
+        if !order.status.is_done() {
+            let timed_out = match self.trading_config.orders {
+                OrderKind::Limit { timeout, .. } => now.duration_since(open.placed_at).unwrap_or_default() >= timeout,
+                // placed before a restart with a different config
+                OrderKind::Market => true,
+            };
+            let exit = open.side == Side::Sell && self.risk.exit_signal(current_price).is_some();
+            if !timed_out && !exit {
+                debug!(
+                    "[ORDER] {} order {} filled {} of {}",
+                    self.trading_config.symbol, order.id, order.filled_quantity, order.quantity
+                );
+                return Ok(true);
+            }
+            order = self.market.cancel_order(&open.id).await?;
+        }
+        self.finish(order, now)?;
+        Ok(false)
+    }
+
+    // books whatever a limit order filled once nothing more will
+    fn finish(&mut self, order: Order, now: SystemTime) -> Result<(), BoxError> {
+        self.open_order = None;
+        info!(
+            "[ORDER] {} order {} {:?}, filled {} of {}",
+            self.trading_config.symbol, order.id, order.status, order.filled_quantity, order.quantity
+        );
+        let status = match order.status {
+            OrderStatus::Filled => "filled",
+            OrderStatus::Rejected => "rejected",
+            _ => "cancelled",
+        };
+        self.metric(|metrics, symbol| metrics.order_closed(symbol, status));
+        let fill = order.average_price.filter(|_| order.filled_quantity.is_positive());
+        if let Some(price) = fill {
+            self.book(order.side, order.filled_quantity, price, now)?;
+        }
+        if let (Some(allocator), Side::Buy) = (&self.allocator, order.side) {
+            // the order held funds for its whole quantity, only what filled stays allocated
+            allocator.release(&self.trading_config.symbol);
+            let spent = fill.and_then(|price| price.checked_notional(order.filled_quantity));
+            allocator.settle(&self.trading_config.symbol, Money::ZERO, spent.unwrap_or(Money::ZERO));
+        }
+        self.record(now, JournalEvent::OrderClosed { id: order.id })?;
+        self.record_state(now)
+    }
+
+    // applies a fill to the position, the caller records the state afterwards
+    fn book(&mut self, side: Side, quantity: Quantity, price: Price, now: SystemTime) -> Result<(), BoxError> {
+        self.trades += 1;
+        self.metric(|metrics, symbol| metrics.fill(symbol, side));
+        self.alert(Alert::Fill { symbol: self.trading_config.symbol.clone(), side, quantity, price });
+        self.record(now, JournalEvent::Order { side, quantity, price })?;
+        let halted = self.risk.is_halted(now);
+        let config = &mut self.trading_config;
+        match side {
+            Side::Buy => {
+                self.risk.record_buy(price, now);
+                config.last_operation_price = price;
+                config.position = config.position.checked_add(quantity).ok_or("position overflowed")?;
+                config.next_operation = State::Sell;
+                info!("[BUY] {} bought {} at {}", config.symbol, quantity, price);
+            }
+            Side::Sell => {
+                let entry = config.last_operation_price;
+                self.risk.record_sell(price, quantity, now);
+                // anything below one lot stays behind as dust
+                config.position = config.position.checked_sub(quantity).ok_or("position overflowed")?;
+                info!("[SELL] {} sold {} at {}", config.symbol, quantity, price);
+                let rest = config.symbol_rules.floor_quantity(config.position).unwrap_or(Quantity::ZERO);
+                if config.symbol_rules.check_order(rest, price).is_ok() {
+                    // a partly filled sell leaves a position that keeps its entry
+                    self.risk.record_buy(entry, now);
+                } else {
+                    config.last_operation_price = price;
+                    config.next_operation = State::Buy;
+                    if let Some(allocator) = &self.allocator {
+                        allocator.release(&config.symbol);
+                    }
+                }
+            }
+        }
+        // a losing sell may trip the daily loss limit
+        if !halted && self.risk.is_halted(now) {
+            let loss = self.risk.loss_today(now);
+            self.alert(Alert::DailyLossLimit { symbol: self.trading_config.symbol.clone(), loss });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::{InstrumentedMarket, SimulatedMarket};
+    use crate::money::Money;
+    use crate::notify::RecordingNotifier;
+    use std::time::UNIX_EPOCH;
+
+    fn bot(values: &[&str], risk: RiskConfig) -> TradingBot {
+        let market = SimulatedMarket::new(prices(values), "1000".parse::<Money>().unwrap(), SymbolRules::default());
+        TradingBot::new(TradingConfig { risk, ..TradingConfig::default() }, Box::new(market))
+    }
+
+    fn prices(values: &[&str]) -> Vec<Price> {
+        values.iter().map(|v| v.parse().unwrap()).collect()
+    }
+
+    #[tokio::test]
+    async fn buys_lot_aligned_quantity_and_sells_on_profit() {
+        let risk = RiskConfig { position_fraction: Decimal::ONE, cooldown: Duration::ZERO, ..RiskConfig::default() };
+        let mut bot = bot(&["40000", "38000", "39000"], risk);
+
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Buy);
+
+        // -5% is below the dip threshold
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Sell);
+        assert_eq!(bot.trading_config.position, "0.02631".parse().unwrap());
+
+        // +2.6% is above the profit threshold
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Buy);
+        assert_eq!(bot.trading_config.position, Quantity::ZERO);
+        assert_eq!(bot.market.get_balance().await.unwrap(), "1026.31".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn risk_limits_size_and_exit_positions() {
+        let mut bot = bot(&["40000", "38000", "37900", "36000", "30000"], RiskConfig::default());
+        bot.run_cycle().await.unwrap();
+
+        // only a quarter of the equity goes into the position
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.position, "0.00657".parse().unwrap());
+
+        // the strategy would not sell yet and the stop loss sits at 36100
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Sell);
+
+        // the stop loss ignores the cooldown
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Buy);
+        assert_eq!(bot.trading_config.position, Quantity::ZERO);
+
+        // -16.7% would be a dip buy, but the last operation was just now
+        bot.run_cycle().await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Buy);
+    }
+
+    #[tokio::test]
+    async fn alerts_on_fills_and_risk_limits() {
+        let notifier = Arc::new(RecordingNotifier::default());
+        let risk = RiskConfig {
+            position_fraction: Decimal::ONE,
+            max_daily_loss: Some("50".parse().unwrap()),
+            ..RiskConfig::default()
+        };
+        let mut bot = bot(&["40000", "38000", "36000"], risk).with_notifier(notifier.clone());
+        for _ in 0..3 {
+            bot.run_cycle().await.unwrap();
+        }
+
+        let symbol = "BTCUSD".to_string();
+        let quantity: Quantity = "0.02631".parse().unwrap();
+        assert_eq!(
+            *notifier.0.lock().unwrap(),
+            vec![
+                Alert::Fill { symbol: symbol.clone(), side: Side::Buy, quantity, price: "38000".parse().unwrap() },
+                Alert::RiskExit { symbol: symbol.clone(), reason: ExitReason::StopLoss, price: "36000".parse().unwrap() },
+                Alert::Fill { symbol: symbol.clone(), side: Side::Sell, quantity, price: "36000".parse().unwrap() },
+                // 0.02631 * 2000
+                Alert::DailyLossLimit { symbol, loss: "52.62".parse().unwrap() },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn picks_up_an_open_position_after_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let risk = RiskConfig { position_fraction: Decimal::ONE, cooldown: Duration::ZERO, ..RiskConfig::default() };
+
+        let mut first = bot(&["40000", "38000"], risk.clone()).with_journal(&path).unwrap();
+        first.run_cycle().await.unwrap();
+        first.run_cycle().await.unwrap();
+        drop(first);
+
+        let restarted = bot(&["39000"], risk.clone()).with_journal(&path).unwrap();
+        assert_eq!(restarted.trading_config.next_operation, State::Sell);
+        assert_eq!(restarted.trading_config.last_operation_price, "38000".parse().unwrap());
+        assert_eq!(restarted.trading_config.position, "0.02631".parse().unwrap());
+
+        // the simulated market picks up the position too, so the bot can sell it
+        let account = journal::replay_account(&Journal::read(&path).unwrap(), "1000".parse().unwrap()).unwrap();
+        let market = SimulatedMarket::new(prices(&["39000"]), account.balance, SymbolRules::default())
+            .with_holdings(account.holdings("BTCUSD"));
+        let config = TradingConfig { risk, ..TradingConfig::default() };
+        let mut restarted = TradingBot::new(config, Box::new(market)).with_journal(&path).unwrap();
+        restarted.run_cycle().await.unwrap();
+        assert_eq!(restarted.trading_config.next_operation, State::Buy);
+        assert_eq!(restarted.market.get_balance().await.unwrap(), "1026.31".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn exports_signals_orders_and_market_calls() {
+        let metrics = Arc::new(Metrics::new());
+        let market = SimulatedMarket::new(prices(&["40000", "38000"]), "1000".parse().unwrap(), SymbolRules::default());
+        let market = InstrumentedMarket::new(Box::new(market), "BTCUSD", metrics.clone());
+        let risk = RiskConfig { position_fraction: Decimal::ONE, ..RiskConfig::default() };
+        let config = TradingConfig { risk, ..TradingConfig::default() };
+        let mut bot = TradingBot::new(config, Box::new(market)).with_metrics(metrics.clone());
+
+        bot.run_cycle().await.unwrap();
+        bot.run_cycle().await.unwrap();
+        assert!(bot.run_cycle().await.is_err());
+
+        let text = metrics.render();
+        for line in &[
+            "trading_bot_signals_total{signal=\"buy\",symbol=\"BTCUSD\"} 1",
+            "trading_bot_orders_placed_total{side=\"buy\",symbol=\"BTCUSD\",type=\"market\"} 1",
+            "trading_bot_fills_total{side=\"buy\",symbol=\"BTCUSD\"} 1",
+            "trading_bot_cycles_total{outcome=\"ok\",symbol=\"BTCUSD\"} 2",
+            // the replay ran out of prices
+            "trading_bot_cycles_total{outcome=\"error\",symbol=\"BTCUSD\"} 1",
+            "trading_bot_market_call_duration_seconds_count{call=\"get_market_price\",outcome=\"error\",symbol=\"BTCUSD\"} 1",
+            "trading_bot_position{symbol=\"BTCUSD\"} 0.02631",
+            "trading_bot_balance 0.22",
+            "trading_bot_equity 1000",
+        ] {
+            assert!(text.contains(&format!("{}\n", line)), "{} missing from\n{}", line, text);
+        }
+    }
+
+    #[tokio::test]
+    async fn works_limit_orders_through_partial_fills_and_timeouts() {
+        let market = SimulatedMarket::new(
+            prices(&["40000", "38000", "37950", "38100", "38500", "38600"]),
+            "1000".parse().unwrap(),
+            SymbolRules::default(),
+        )
+        .with_liquidity("0.01".parse().unwrap());
+        let market = Arc::new(market);
+        let orders = OrderKind::Limit { offset: Decimal::new(1, 1), timeout: Duration::from_secs(60) };
+        let risk = RiskConfig { position_fraction: Decimal::ONE, cooldown: Duration::ZERO, ..RiskConfig::default() };
+        let config = TradingConfig { orders, risk, ..TradingConfig::default() };
+        let mut bot = TradingBot::new(config, Box::new(market.clone()));
+        let at = |seconds| UNIX_EPOCH + Duration::from_secs(seconds);
+
+        bot.run_cycle_at(at(0)).await.unwrap();
+        // the dip buy rests 0.1% below the price
+        bot.run_cycle_at(at(30)).await.unwrap();
+        let order = bot.open_order.clone().unwrap();
+        assert_eq!(market.get_order(&order.id).await.unwrap().limit_price, "37962".parse().unwrap());
+
+        // a third of it fills, the rest is cancelled once the order timed out
+        bot.run_cycle_at(at(60)).await.unwrap();
+        assert!(bot.open_order.is_some());
+        assert_eq!(bot.trading_config.next_operation, State::Buy);
+        bot.run_cycle_at(at(100)).await.unwrap();
+        assert_eq!(bot.open_order, None);
+        assert_eq!(bot.trading_config.next_operation, State::Sell);
+        assert_eq!(bot.trading_config.position, "0.01".parse().unwrap());
+        assert_eq!(bot.trading_config.last_operation_price, "37962".parse().unwrap());
+
+        // +1.4% takes profit with a limit sell that fills on the next price
+        bot.run_cycle_at(at(130)).await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Sell);
+        bot.run_cycle_at(at(150)).await.unwrap();
+        assert_eq!(bot.trading_config.next_operation, State::Buy);
+        assert_eq!(bot.trading_config.position, Quantity::ZERO);
+        assert_eq!(market.get_balance().await.unwrap(), "1005.765".parse().unwrap());
+    }
+}
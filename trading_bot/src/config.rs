@@ -0,0 +1,705 @@
+use crate::bot::{OrderKind, State, TradingConfig};
+use crate::money::{Money, Price, Quantity, SymbolRules};
+use crate::notify::SmtpSecurity;
+use crate::risk::RiskConfig;
+use crate::BoxError;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// the bot's TOML configuration file, see bot.toml for an example.
+// one process trades every symbol listed under [[symbols]] on the same market backend.
+// missing sections and fields fall back to the defaults of TradingConfig and RiskConfig.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default = "default_polling_interval")]
+    pub polling_interval_secs: u64,
+    // JSON lines journal of orders and state, relative to the config file
+    pub journal: Option<PathBuf>,
+    #[serde(default)]
+    pub portfolio: PortfolioSection,
+    pub market: MarketBackend,
+    // stream prices instead of polling the market every polling_interval_secs
+    pub feed: Option<FeedSection>,
+    // serve Prometheus metrics over HTTP
+    pub metrics: Option<MetricsSection>,
+    #[serde(default)]
+    pub paper: PaperSection,
+    // alerts on fills, risk limits and failures
+    pub notify: Option<NotifySection>,
+    pub symbols: Vec<SymbolConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SymbolConfig {
+    pub symbol: String,
+    // `timestamp,price` CSV file for the simulated backend, relative to the config file
+    pub prices: Option<PathBuf>,
+    #[serde(default)]
+    pub strategy: StrategyConfig,
+    #[serde(default)]
+    pub risk: RiskSection,
+    #[serde(default)]
+    pub rules: RulesSection,
+    #[serde(default)]
+    pub orders: OrdersSection,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PortfolioSection {
+    // largest share of the portfolio a single symbol may hold
+    pub max_allocation: Decimal,
+    pub report_interval_secs: u64,
+    // failed cycles in a row before a bot stops, and the whole portfolio with it
+    pub halt_after_errors: u32,
+}
+
+impl Default for PortfolioSection {
+    fn default() -> Self {
+        PortfolioSection { max_allocation: Decimal::ONE, report_interval_secs: 300, halt_after_errors: 5 }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeedSection {
+    // WebSocket endpoint of the exchange's trade stream
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MetricsSection {
+    // address of the /metrics endpoint, e.g. "127.0.0.1:9184"
+    pub listen: SocketAddr,
+}
+
+// the `paper` command trades live prices against a simulated wallet
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PaperSection {
+    // starting balance of the paper wallet, market.balance with the simulated backend
+    pub balance: Option<Money>,
+    // journal of the paper trades, relative to the config file. paper trades never go to the
+    // live journal, a restart of the live bot would pick them up as real positions.
+    pub journal: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NotifySection {
+    // failed cycles in a row before they are worth an alert
+    #[serde(default = "default_repeated_errors")]
+    pub repeated_errors: u32,
+    #[serde(default)]
+    pub stdout: bool,
+    pub webhook: Option<WebhookSection>,
+    pub email: Option<EmailSection>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSection {
+    pub url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EmailSection {
+    // SMTP server
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    // the password is read from the named environment variable
+    pub username: Option<String>,
+    pub password_env: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_repeated_errors() -> u32 {
+    3
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_polling_interval() -> u64 {
+    TradingConfig::default().polling_interval.as_secs()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum StrategyConfig {
+    // buy on an upward trend or a dip, sell on profit or loss, all in percent
+    Threshold {
+        upward_trend_threshold: Decimal,
+        dip_threshold: Decimal,
+        profit_threshold: Decimal,
+        stop_loss_threshold: Decimal,
+    },
+}
+
+impl Default for StrategyConfig {
+    fn default() -> Self {
+        let defaults = TradingConfig::default();
+        StrategyConfig::Threshold {
+            upward_trend_threshold: defaults.upward_trend_threshold,
+            dip_threshold: defaults.dip_threshold,
+            profit_threshold: defaults.profit_threshold,
+            stop_loss_threshold: defaults.stop_loss_threshold,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskSection {
+    pub position_fraction: Decimal,
+    pub stop_loss: Option<Decimal>,
+    pub take_profit: Option<Decimal>,
+    pub max_daily_loss: Option<Money>,
+    pub cooldown_secs: u64,
+}
+
+impl Default for RiskSection {
+    fn default() -> Self {
+        let defaults = RiskConfig::default();
+        RiskSection {
+            position_fraction: defaults.position_fraction,
+            stop_loss: defaults.stop_loss,
+            take_profit: defaults.take_profit,
+            max_daily_loss: defaults.max_daily_loss,
+            cooldown_secs: defaults.cooldown.as_secs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RulesSection {
+    pub tick_size: Price,
+    pub lot_size: Quantity,
+    pub min_quantity: Quantity,
+    pub min_notional: Money,
+}
+
+impl Default for RulesSection {
+    fn default() -> Self {
+        let defaults = SymbolRules::default();
+        RulesSection {
+            tick_size: defaults.tick_size,
+            lot_size: defaults.lot_size,
+            min_quantity: defaults.min_quantity,
+            min_notional: defaults.min_notional,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum OrdersSection {
+    #[default]
+    Market,
+    // limit orders `offset` percent better than the current price, cancelled after timeout_secs
+    Limit { offset: Decimal, timeout_secs: u64 },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "backend", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MarketBackend {
+    // replays each symbol's prices file, all symbols share one balance
+    Simulated { balance: Money },
+    // a REST exchange, credentials are read from the named environment variables
+    Rest {
+        base_url: String,
+        quote_asset: String,
+        #[serde(default = "default_api_key_env")]
+        api_key_env: String,
+        #[serde(default = "default_api_secret_env")]
+        api_secret_env: String,
+    },
+}
+
+fn default_api_key_env() -> String {
+    "EXCHANGE_API_KEY".to_string()
+}
+
+fn default_api_secret_env() -> String {
+    "EXCHANGE_API_SECRET".to_string()
+}
+
+// every problem found in a config file, reported together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigError {
+    pub problems: Vec<String>,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid configuration:")?;
+        for problem in &self.problems {
+            write!(f, "\n  - {}", problem)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConfigError {}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Config, BoxError> {
+        let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path.display(), e))?;
+        let mut config = Config::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or_else(|| Path::new(""));
+        config.journal = config.journal.map(|journal| base.join(journal));
+        config.paper.journal = config.paper.journal.map(|journal| base.join(journal));
+        for symbol in &mut config.symbols {
+            symbol.prices = symbol.prices.take().map(|prices| base.join(prices));
+        }
+        Ok(config)
+    }
+
+    pub fn parse(text: &str) -> Result<Config, BoxError> {
+        let config: Config = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, problem: String| {
+            if !ok {
+                problems.push(problem);
+            }
+        };
+        let zero = Decimal::ZERO;
+
+        check(self.polling_interval_secs > 0, "polling_interval_secs must be at least 1".to_string());
+        if let Some(journal) = &self.journal {
+            check(!journal.as_os_str().is_empty(), "journal must be a file path".to_string());
+        }
+        if let Some(balance) = self.paper.balance {
+            check(balance.is_positive(), format!("paper.balance must be positive, got {}", balance));
+        }
+        if let Some(journal) = &self.paper.journal {
+            check(!journal.as_os_str().is_empty(), "paper.journal must be a file path".to_string());
+            check(self.journal.as_ref() != Some(journal), "paper.journal must not be the live journal".to_string());
+        }
+        let portfolio = &self.portfolio;
+        check(
+            portfolio.max_allocation > zero && portfolio.max_allocation <= Decimal::ONE,
+            format!("portfolio.max_allocation must be above 0 and at most 1, got {}", portfolio.max_allocation),
+        );
+        check(portfolio.report_interval_secs > 0, "portfolio.report_interval_secs must be at least 1".to_string());
+        check(portfolio.halt_after_errors > 0, "portfolio.halt_after_errors must be at least 1".to_string());
+
+        if let Some(notify) = &self.notify {
+            check(
+                notify.stdout || notify.webhook.is_some() || notify.email.is_some(),
+                "notify needs stdout, a webhook or email to send alerts to".to_string(),
+            );
+            check(notify.repeated_errors > 0, "notify.repeated_errors must be at least 1".to_string());
+            // the portfolio would stop before the alert went out
+            check(
+                notify.repeated_errors < portfolio.halt_after_errors,
+                format!(
+                    "notify.repeated_errors must be below portfolio.halt_after_errors ({}), got {}",
+                    portfolio.halt_after_errors, notify.repeated_errors
+                ),
+            );
+            if let Some(webhook) = &notify.webhook {
+                check(
+                    webhook.url.starts_with("http://") || webhook.url.starts_with("https://"),
+                    format!("notify.webhook.url must be an http(s) URL, got {:?}", webhook.url),
+                );
+            }
+            if let Some(email) = &notify.email {
+                check(!email.host.is_empty(), "notify.email.host must name an SMTP server".to_string());
+                check(!email.to.is_empty(), "notify.email.to needs at least one recipient".to_string());
+                check(
+                    email.username.is_some() == email.password_env.is_some(),
+                    "notify.email.username and notify.email.password_env go together".to_string(),
+                );
+            }
+        }
+
+        if let Some(feed) = &self.feed {
+            check(
+                feed.url.starts_with("ws://") || feed.url.starts_with("wss://"),
+                format!("feed.url must be a ws(s) URL, got {:?}", feed.url),
+            );
+            check(
+                !matches!(self.market, MarketBackend::Simulated { .. }),
+                "feed needs the rest backend, the simulated one replays its prices files".to_string(),
+            );
+        }
+
+        check(!self.symbols.is_empty(), "at least one [[symbols]] entry is needed".to_string());
+        let simulated = matches!(self.market, MarketBackend::Simulated { .. });
+        for (idx, symbol) in self.symbols.iter().enumerate() {
+            if self.symbols[..idx].iter().any(|other| other.symbol == symbol.symbol) {
+                check(false, format!("symbol {} is listed more than once", symbol.symbol));
+            }
+            check(
+                !simulated || symbol.prices.is_some(),
+                format!("{}: prices is needed with the simulated backend", symbol.symbol),
+            );
+            for problem in symbol.problems() {
+                check(false, problem);
+            }
+        }
+
+        match &self.market {
+            MarketBackend::Simulated { balance, .. } => {
+                check(balance.is_positive(), format!("market.balance must be positive, got {}", balance));
+            }
+            MarketBackend::Rest { base_url, quote_asset, api_key_env, api_secret_env } => {
+                check(
+                    base_url.starts_with("http://") || base_url.starts_with("https://"),
+                    format!("market.base_url must be an http(s) URL, got {:?}", base_url),
+                );
+                check(
+                    !quote_asset.is_empty() && quote_asset.chars().all(|c| c.is_ascii_alphanumeric()),
+                    format!("market.quote_asset must be letters and digits only, got {:?}", quote_asset),
+                );
+                check(!api_key_env.is_empty(), "market.api_key_env must name an environment variable".to_string());
+                check(!api_secret_env.is_empty(), "market.api_secret_env must name an environment variable".to_string());
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError { problems })
+        }
+    }
+}
+
+impl SymbolConfig {
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, problem: String| {
+            if !ok {
+                problems.push(format!("{}: {}", self.symbol, problem));
+            }
+        };
+        let zero = Decimal::ZERO;
+
+        check(
+            !self.symbol.is_empty() && self.symbol.chars().all(|c| c.is_ascii_alphanumeric()),
+            "symbol must be letters and digits only".to_string(),
+        );
+
+        match &self.strategy {
+            StrategyConfig::Threshold { upward_trend_threshold, dip_threshold, profit_threshold, stop_loss_threshold } => {
+                check(
+                    *upward_trend_threshold > zero,
+                    format!("strategy.upward_trend_threshold must be positive, got {}", upward_trend_threshold),
+                );
+                check(*dip_threshold < zero, format!("strategy.dip_threshold must be negative, got {}", dip_threshold));
+                check(
+                    *profit_threshold > zero,
+                    format!("strategy.profit_threshold must be positive, got {}", profit_threshold),
+                );
+                check(
+                    *stop_loss_threshold < zero,
+                    format!("strategy.stop_loss_threshold must be negative, got {}", stop_loss_threshold),
+                );
+            }
+        }
+
+        let risk = &self.risk;
+        check(
+            risk.position_fraction > zero && risk.position_fraction <= Decimal::ONE,
+            format!("risk.position_fraction must be above 0 and at most 1, got {}", risk.position_fraction),
+        );
+        if let Some(stop_loss) = risk.stop_loss {
+            check(
+                stop_loss > zero && stop_loss < Decimal::ONE_HUNDRED,
+                format!("risk.stop_loss must be a percentage between 0 and 100, got {}", stop_loss),
+            );
+        }
+        if let Some(take_profit) = risk.take_profit {
+            check(take_profit > zero, format!("risk.take_profit must be positive, got {}", take_profit));
+        }
+        if let Some(max_daily_loss) = risk.max_daily_loss {
+            check(max_daily_loss.is_positive(), format!("risk.max_daily_loss must be positive, got {}", max_daily_loss));
+        }
+
+        let rules = &self.rules;
+        check(rules.tick_size.is_positive(), format!("rules.tick_size must be positive, got {}", rules.tick_size));
+        check(rules.lot_size.is_positive(), format!("rules.lot_size must be positive, got {}", rules.lot_size));
+        check(
+            rules.min_quantity >= Quantity::ZERO,
+            format!("rules.min_quantity must not be negative, got {}", rules.min_quantity),
+        );
+        check(
+            rules.min_notional >= Money::ZERO,
+            format!("rules.min_notional must not be negative, got {}", rules.min_notional),
+        );
+        if let OrdersSection::Limit { offset, timeout_secs } = self.orders {
+            check(
+                offset >= zero && offset < Decimal::ONE_HUNDRED,
+                format!("orders.offset must be a percentage from 0 up to 100, got {}", offset),
+            );
+            check(timeout_secs > 0, "orders.timeout_secs must be at least 1".to_string());
+        }
+        problems
+    }
+
+    pub fn trading_config(&self, config: &Config) -> TradingConfig {
+        let StrategyConfig::Threshold { upward_trend_threshold, dip_threshold, profit_threshold, stop_loss_threshold } =
+            self.strategy.clone();
+        TradingConfig {
+            symbol: self.symbol.clone(),
+            upward_trend_threshold,
+            dip_threshold,
+            profit_threshold,
+            stop_loss_threshold,
+            polling_interval: Duration::from_secs(config.polling_interval_secs),
+            symbol_rules: SymbolRules {
+                tick_size: self.rules.tick_size,
+                lot_size: self.rules.lot_size,
+                min_quantity: self.rules.min_quantity,
+                min_notional: self.rules.min_notional,
+            },
+            orders: match self.orders {
+                OrdersSection::Market => OrderKind::Market,
+                OrdersSection::Limit { offset, timeout_secs } => {
+                    OrderKind::Limit { offset, timeout: Duration::from_secs(timeout_secs) }
+                }
+            },
+            risk: RiskConfig {
+                position_fraction: self.risk.position_fraction,
+                stop_loss: self.risk.stop_loss,
+                take_profit: self.risk.take_profit,
+                max_daily_loss: self.risk.max_daily_loss,
+                cooldown: Duration::from_secs(self.risk.cooldown_secs),
+            },
+            last_operation_price: Price::ZERO,
+            next_operation: State::Buy,
+            position: Quantity::ZERO,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINIMAL: &str = r#"
+        [market]
+        backend = "simulated"
+        balance = 1000
+
+        [[symbols]]
+        symbol = "BTCUSD"
+        prices = "prices.csv"
+    "#;
+
+    fn trading_config(text: &str) -> TradingConfig {
+        let config = Config::parse(text).unwrap();
+        config.symbols[0].trading_config(&config)
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let trading_config = trading_config(MINIMAL);
+        let defaults = TradingConfig::default();
+        assert_eq!(trading_config.symbol, "BTCUSD");
+        assert_eq!(trading_config.dip_threshold, defaults.dip_threshold);
+        assert_eq!(trading_config.polling_interval, defaults.polling_interval);
+        assert_eq!(trading_config.risk, defaults.risk);
+        assert_eq!(trading_config.symbol_rules, defaults.symbol_rules);
+        assert_eq!(trading_config.orders, OrderKind::Market);
+    }
+
+    #[test]
+    fn parses_every_section() {
+        let text = format!(
+            "{}\n{}",
+            MINIMAL,
+            r#"
+            [symbols.strategy]
+            kind = "threshold"
+            upward_trend_threshold = 2
+            dip_threshold = -3.5
+            profit_threshold = 1
+            stop_loss_threshold = -1.5
+
+            [symbols.risk]
+            position_fraction = 0.5
+            take_profit = 8
+            cooldown_secs = 0
+
+            [symbols.orders]
+            type = "limit"
+            offset = 0.1
+            timeout_secs = 120
+            "#
+        );
+        let trading_config = trading_config(&text);
+        assert_eq!(
+            trading_config.orders,
+            OrderKind::Limit { offset: Decimal::new(1, 1), timeout: Duration::from_secs(120) }
+        );
+        assert_eq!(trading_config.dip_threshold, Decimal::new(-35, 1));
+        assert_eq!(trading_config.risk.position_fraction, Decimal::new(5, 1));
+        assert_eq!(trading_config.risk.take_profit, Some(Decimal::new(8, 0)));
+        assert_eq!(trading_config.risk.cooldown, Duration::ZERO);
+    }
+
+    #[test]
+    fn configures_each_symbol_separately() {
+        let text = format!(
+            "{}\n{}",
+            MINIMAL,
+            r#"
+            [[symbols]]
+            symbol = "ETHUSD"
+            prices = "eth.csv"
+
+            [symbols.risk]
+            position_fraction = 0.1
+            "#
+        );
+        let config = Config::parse(&text).unwrap();
+        assert_eq!(config.symbols.len(), 2);
+        assert_eq!(config.symbols[0].trading_config(&config).risk.position_fraction, Decimal::new(25, 2));
+        assert_eq!(config.symbols[1].trading_config(&config).risk.position_fraction, Decimal::new(1, 1));
+    }
+
+    #[test]
+    fn reports_every_bad_value() {
+        let text = MINIMAL.replace("BTCUSD", "BTC/USD").replace("1000", "0")
+            + "\n[symbols.risk]\nposition_fraction = 1.5\n\n[[symbols]]\nsymbol = \"ETHUSD\"\n"
+            + "\n[symbols.orders]\ntype = \"limit\"\noffset = -1\ntimeout_secs = 0\n";
+        let error = Config::parse(&text).unwrap_err().to_string();
+        assert!(error.contains("BTC/USD: symbol must be letters and digits only"), "{}", error);
+        assert!(error.contains("BTC/USD: risk.position_fraction must be above 0 and at most 1, got 1.5"), "{}", error);
+        assert!(error.contains("ETHUSD: prices is needed with the simulated backend"), "{}", error);
+        assert!(error.contains("ETHUSD: orders.offset must be a percentage from 0 up to 100, got -1"), "{}", error);
+        assert!(error.contains("ETHUSD: orders.timeout_secs must be at least 1"), "{}", error);
+        assert!(error.contains("market.balance must be positive, got 0"), "{}", error);
+    }
+
+    #[test]
+    fn parses_the_rest_backend() {
+        let text = r#"
+            [market]
+            backend = "rest"
+            base_url = "ftp://exchange.example"
+            quote_asset = "USD"
+
+            [[symbols]]
+            symbol = "BTCUSD"
+        "#;
+        let error = Config::parse(text).unwrap_err().to_string();
+        assert!(error.contains("market.base_url must be an http(s) URL"), "{}", error);
+
+        let config = Config::parse(&text.replace("ftp", "https")).unwrap();
+        match config.market {
+            MarketBackend::Rest { api_key_env, .. } => assert_eq!(api_key_env, "EXCHANGE_API_KEY"),
+            other => panic!("unexpected backend {:?}", other),
+        }
+        assert!(config.feed.is_none());
+    }
+
+    #[test]
+    fn streams_only_from_websocket_urls() {
+        let rest = r#"
+            [market]
+            backend = "rest"
+            base_url = "https://exchange.example"
+            quote_asset = "USD"
+
+            [feed]
+            url = "wss://exchange.example/ws"
+
+            [[symbols]]
+            symbol = "BTCUSD"
+        "#;
+        assert_eq!(Config::parse(rest).unwrap().feed.unwrap().url, "wss://exchange.example/ws");
+        let error = Config::parse(&rest.replace("wss://exchange.example/ws", "https://exchange.example/ws")).unwrap_err();
+        assert!(error.to_string().contains("feed.url must be a ws(s) URL"), "{}", error);
+
+        let error = Config::parse(&(MINIMAL.to_string() + "\n[feed]\nurl = \"ws://localhost:9000\"\n")).unwrap_err();
+        assert!(error.to_string().contains("feed needs the rest backend"), "{}", error);
+    }
+
+    #[test]
+    fn keeps_paper_trades_out_of_the_live_journal() {
+        let text = format!("journal = \"journal.jsonl\"\n{}\n[paper]\nbalance = 500\njournal = \"paper.jsonl\"\n", MINIMAL);
+        let paper = Config::parse(&text).unwrap().paper;
+        assert_eq!(paper.balance, Some("500".parse().unwrap()));
+        assert_eq!(paper.journal, Some(PathBuf::from("paper.jsonl")));
+
+        let error = Config::parse(&text.replace("paper.jsonl", "journal.jsonl").replace("500", "0")).unwrap_err();
+        assert!(error.to_string().contains("paper.balance must be positive, got 0"), "{}", error);
+        assert!(error.to_string().contains("paper.journal must not be the live journal"), "{}", error);
+    }
+
+    #[test]
+    fn parses_the_metrics_listener() {
+        assert!(Config::parse(MINIMAL).unwrap().metrics.is_none());
+        let config = Config::parse(&(MINIMAL.to_string() + "\n[metrics]\nlisten = \"127.0.0.1:9184\"\n")).unwrap();
+        assert_eq!(config.metrics.unwrap().listen, "127.0.0.1:9184".parse().unwrap());
+        let error = Config::parse(&(MINIMAL.to_string() + "\n[metrics]\nlisten = \"localhost\"\n")).unwrap_err();
+        assert!(error.to_string().contains("invalid socket address"), "{}", error);
+    }
+
+    #[test]
+    fn parses_the_notifiers() {
+        let text = MINIMAL.to_string()
+            + r#"
+            [notify]
+            stdout = true
+
+            [notify.webhook]
+            url = "https://hooks.example/bot"
+
+            [notify.email]
+            host = "smtp.example.com"
+            username = "bot"
+            password_env = "SMTP_PASSWORD"
+            from = "bot@example.com"
+            to = ["me@example.com"]
+            "#;
+        let notify = Config::parse(&text).unwrap().notify.unwrap();
+        assert_eq!(notify.repeated_errors, 3);
+        let email = notify.email.unwrap();
+        assert_eq!((email.port, email.security), (587, SmtpSecurity::Starttls));
+
+        let error = Config::parse(&text.replace("stdout = true", "repeated_errors = 0").replace("to = [\"me@example.com\"]", "to = []"))
+            .unwrap_err();
+        assert!(error.to_string().contains("notify.repeated_errors must be at least 1"), "{}", error);
+        assert!(error.to_string().contains("notify.email.to needs at least one recipient"), "{}", error);
+        let error = Config::parse(&(MINIMAL.to_string() + "\n[notify]\nrepeated_errors = 5\n")).unwrap_err();
+        assert!(error.to_string().contains("notify needs stdout, a webhook or email"), "{}", error);
+        assert!(
+            error.to_string().contains("notify.repeated_errors must be below portfolio.halt_after_errors (5), got 5"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields_and_strategies() {
+        let error = Config::parse(&(MINIMAL.to_string() + "\n[symbols.risk]\nstoploss = 2\n")).unwrap_err();
+        assert!(error.to_string().contains("unknown field `stoploss`"), "{}", error);
+        let error = Config::parse(&(MINIMAL.to_string() + "\n[symbols.strategy]\nkind = \"martingale\"\n")).unwrap_err();
+        assert!(error.to_string().contains("unknown variant `martingale`"), "{}", error);
+    }
+}
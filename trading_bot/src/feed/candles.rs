@@ -0,0 +1,176 @@
+use super::{Tick, TickStream};
+use crate::money::{Price, Quantity};
+use crate::BoxError;
+use futures_util::{Stream, StreamExt};
+use std::pin::Pin;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// an OHLC bar of every tick in [open_time, open_time + interval)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Candle {
+    pub open_time: SystemTime,
+    pub interval: Duration,
+    pub open: Price,
+    pub high: Price,
+    pub low: Price,
+    pub close: Price,
+    pub volume: Quantity,
+    pub ticks: usize,
+}
+
+impl Candle {
+    fn new(open_time: SystemTime, interval: Duration, tick: &Tick) -> Self {
+        Candle {
+            open_time,
+            interval,
+            open: tick.price,
+            high: tick.price,
+            low: tick.price,
+            close: tick.price,
+            volume: tick.quantity,
+            ticks: 1,
+        }
+    }
+
+    fn update(&mut self, tick: &Tick) {
+        self.high = self.high.max(tick.price);
+        self.low = self.low.min(tick.price);
+        self.close = tick.price;
+        self.volume = self.volume.checked_add(tick.quantity).unwrap_or(self.volume);
+        self.ticks += 1;
+    }
+}
+
+pub type CandleStream = Pin<Box<dyn Stream<Item = Result<Candle, BoxError>> + Send>>;
+
+// builds candles out of ticks. candles start at multiples of `interval` since the unix epoch,
+// an interval without ticks gets no candle at all. there is none for a zero interval.
+#[derive(Debug, Clone)]
+pub struct CandleAggregator {
+    interval: Duration,
+    current: Option<Candle>,
+}
+
+impl CandleAggregator {
+    pub fn new(interval: Duration) -> Option<Self> {
+        if interval.is_zero() {
+            return None;
+        }
+        Some(CandleAggregator { interval, current: None })
+    }
+
+    fn open_time(&self, time: SystemTime) -> SystemTime {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+        let interval = self.interval.as_nanos();
+        UNIX_EPOCH + Duration::from_nanos((since_epoch - since_epoch % interval) as u64)
+    }
+
+    // adds a tick, returning the previous candle once a tick of a later interval shows up.
+    // a late tick is folded into the current candle.
+    pub fn push(&mut self, tick: &Tick) -> Option<Candle> {
+        let open_time = self.open_time(tick.time);
+        match &mut self.current {
+            Some(candle) if open_time <= candle.open_time => {
+                candle.update(tick);
+                None
+            }
+            current => current.replace(Candle::new(open_time, self.interval, tick)),
+        }
+    }
+
+    // the candle still being built, if any
+    pub fn flush(&mut self) -> Option<Candle> {
+        self.current.take()
+    }
+}
+
+// candles of `interval` out of a tick stream. the last, possibly incomplete, candle comes out
+// when the tick stream ends. a zero interval is an error right away.
+pub fn candles(ticks: TickStream, interval: Duration) -> CandleStream {
+    let aggregator = match CandleAggregator::new(interval) {
+        Some(aggregator) => aggregator,
+        None => return Box::pin(futures_util::stream::once(async { Err("candle interval must not be zero".into()) })),
+    };
+    let state = Some((ticks, aggregator));
+    Box::pin(futures_util::stream::unfold(state, |state| async move {
+        let (mut ticks, mut aggregator) = state?;
+        while let Some(tick) = ticks.next().await {
+            match tick {
+                Ok(tick) => {
+                    if let Some(candle) = aggregator.push(&tick) {
+                        return Some((Ok(candle), Some((ticks, aggregator))));
+                    }
+                }
+                Err(e) => return Some((Err(e), None)),
+            }
+        }
+        aggregator.flush().map(|candle| (Ok(candle), None))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::parse_prices;
+    use crate::feed::{PriceFeed, ReplayFeed};
+
+    fn tick(seconds: u64, price: &str, quantity: &str) -> Tick {
+        Tick {
+            symbol: "BTCUSD".to_string(),
+            time: UNIX_EPOCH + Duration::from_secs(seconds),
+            price: price.parse().unwrap(),
+            quantity: quantity.parse().unwrap(),
+        }
+    }
+
+    fn p(value: &str) -> Price {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn aggregates_ticks_into_aligned_candles() {
+        let mut aggregator = CandleAggregator::new(Duration::from_secs(60)).unwrap();
+        assert_eq!(aggregator.push(&tick(65, "100", "1")), None);
+        assert_eq!(aggregator.push(&tick(70, "103", "0.5")), None);
+        assert_eq!(aggregator.push(&tick(90, "99", "2")), None);
+        assert_eq!(aggregator.push(&tick(100, "101", "1")), None);
+
+        // nothing traded between 120 and 180
+        let candle = aggregator.push(&tick(185, "104", "1")).unwrap();
+        assert_eq!(candle.open_time, UNIX_EPOCH + Duration::from_secs(60));
+        assert_eq!((candle.open, candle.high, candle.low, candle.close), (p("100"), p("103"), p("99"), p("101")));
+        assert_eq!(candle.volume, "4.5".parse().unwrap());
+        assert_eq!(candle.ticks, 4);
+
+        // a late tick still lands in the open candle
+        assert_eq!(aggregator.push(&tick(170, "98", "1")), None);
+        let candle = aggregator.flush().unwrap();
+        assert_eq!(candle.open_time, UNIX_EPOCH + Duration::from_secs(180));
+        assert_eq!((candle.low, candle.close), (p("98"), p("98")));
+        assert_eq!(aggregator.flush(), None);
+    }
+
+    #[tokio::test]
+    async fn streams_candles_from_a_replay() {
+        let prices = parse_prices("0,100\n30,110\n60,105\n150,90\n170,95\n").unwrap();
+        let feed = ReplayFeed::new().with_prices("BTCUSD", &prices);
+        let stream = candles(feed.subscribe("BTCUSD").await.unwrap(), Duration::from_secs(60));
+        let candles: Vec<Candle> = stream.map(Result::unwrap).collect().await;
+
+        let closes: Vec<Price> = candles.iter().map(|candle| candle.close).collect();
+        assert_eq!(closes, vec![p("110"), p("105"), p("95")]);
+        assert_eq!(candles[2].open_time, UNIX_EPOCH + Duration::from_secs(120));
+        assert_eq!(candles[2].open, p("90"));
+        assert!(feed.subscribe("ETHUSD").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn refuses_a_zero_interval() {
+        assert!(CandleAggregator::new(Duration::ZERO).is_none());
+        let feed = ReplayFeed::new().with_prices("BTCUSD", &parse_prices("0,100\n").unwrap());
+        let mut stream = candles(feed.subscribe("BTCUSD").await.unwrap(), Duration::ZERO);
+        let error = stream.next().await.unwrap().unwrap_err();
+        assert_eq!(error.to_string(), "candle interval must not be zero");
+        assert!(stream.next().await.is_none());
+    }
+}
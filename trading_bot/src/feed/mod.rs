@@ -0,0 +1,41 @@
+pub mod candles;
+pub mod replay;
+pub mod websocket;
+
+pub use candles::{candles, Candle, CandleAggregator, CandleStream};
+pub use replay::ReplayFeed;
+pub use websocket::WebSocketFeed;
+
+use crate::money::{Price, Quantity};
+use crate::BoxError;
+use async_trait::async_trait;
+use futures_util::Stream;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+// one trade reported by the market
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tick {
+    pub symbol: String,
+    pub time: SystemTime,
+    pub price: Price,
+    // traded quantity, zero when the source only knows prices
+    pub quantity: Quantity,
+}
+
+pub type TickStream = Pin<Box<dyn Stream<Item = Result<Tick, BoxError>> + Send>>;
+
+// market data pushed as it happens, instead of polled with Market::get_market_price.
+// a stream ends when the feed has nothing more to send, an error leaves it unusable.
+#[async_trait]
+pub trait PriceFeed: Send + Sync {
+    async fn subscribe(&self, symbol: &str) -> Result<TickStream, BoxError>;
+}
+
+#[async_trait]
+impl<F: PriceFeed + ?Sized> PriceFeed for Arc<F> {
+    async fn subscribe(&self, symbol: &str) -> Result<TickStream, BoxError> {
+        (**self).subscribe(symbol).await
+    }
+}
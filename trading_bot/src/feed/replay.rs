@@ -0,0 +1,51 @@
+use super::{PriceFeed, Tick, TickStream};
+use crate::backtest::PricePoint;
+use crate::money::Quantity;
+use crate::BoxError;
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+// replays recorded prices as a feed, for tests and for watching a strategy play out.
+// ticks keep the time they were recorded at, `delay` only paces how fast they come.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayFeed {
+    ticks: BTreeMap<String, Vec<Tick>>,
+    delay: Duration,
+}
+
+impl ReplayFeed {
+    pub fn new() -> Self {
+        ReplayFeed::default()
+    }
+
+    pub fn with_prices(mut self, symbol: &str, points: &[PricePoint]) -> Self {
+        let ticks = points
+            .iter()
+            .map(|point| Tick { symbol: symbol.to_string(), time: point.time, price: point.price, quantity: Quantity::ZERO })
+            .collect();
+        self.ticks.insert(symbol.to_string(), ticks);
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+#[async_trait]
+impl PriceFeed for ReplayFeed {
+    async fn subscribe(&self, symbol: &str) -> Result<TickStream, BoxError> {
+        let ticks = self.ticks.get(symbol).cloned().ok_or_else(|| format!("no prices to replay for {}", symbol))?;
+        let delay = self.delay;
+        let stream = futures_util::stream::iter(ticks).then(move |tick| async move {
+            if !delay.is_zero() {
+                tokio::time::sleep(delay).await;
+            }
+            Ok(tick)
+        });
+        Ok(Box::pin(stream))
+    }
+}
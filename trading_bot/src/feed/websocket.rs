@@ -0,0 +1,190 @@
+use super::{PriceFeed, Tick, TickStream};
+use crate::money::{Price, Quantity};
+use crate::BoxError;
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+// trades pushed over a WebSocket:
+//
+//   -> {"op": "subscribe", "symbol": "BTCUSD"}
+//   <- {"symbol": "BTCUSD", "price": "42000.5", "quantity": "0.01", "time": 1700000000000}
+//
+// anything else the exchange sends, acks, heartbeats or errors, is logged and skipped.
+// every subscription gets its own connection, and the exchange may start it off with the last
+// trade. time is in unix milliseconds, amounts are decimal strings as in the REST API.
+// a connection that drops is made again, after a wait that doubles with every failed attempt, and
+// the stream goes on from there. only a normal close from the exchange ends it.
+pub struct WebSocketFeed {
+    url: String,
+    min_backoff: Duration,
+    max_backoff: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Subscribe<'a> {
+    pub op: &'a str,
+    pub symbol: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Trade {
+    pub symbol: String,
+    pub price: Price,
+    pub quantity: Quantity,
+    pub time: u64,
+}
+
+impl From<Trade> for Tick {
+    fn from(trade: Trade) -> Tick {
+        Tick {
+            symbol: trade.symbol,
+            time: UNIX_EPOCH + Duration::from_millis(trade.time),
+            price: trade.price,
+            quantity: trade.quantity,
+        }
+    }
+}
+
+impl WebSocketFeed {
+    pub fn new(url: &str) -> Self {
+        let (min_backoff, max_backoff) = (Duration::from_secs(1), Duration::from_secs(60));
+        WebSocketFeed { url: url.to_string(), min_backoff, max_backoff }
+    }
+
+    // the wait before reconnecting starts at `min` and doubles up to `max`, back to `min` once a
+    // trade comes through again
+    pub fn with_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.min_backoff = min;
+        self.max_backoff = max.max(min);
+        self
+    }
+}
+
+async fn connect(url: &str, symbol: &str) -> Result<Socket, BoxError> {
+    let (mut socket, _) =
+        tokio_tungstenite::connect_async(url).await.map_err(|e| format!("cannot connect to {}: {}", url, e))?;
+    let request = serde_json::to_string(&Subscribe { op: "subscribe", symbol })?;
+    socket.send(Message::Text(request.into())).await?;
+    Ok(socket)
+}
+
+// what came of waiting for the next trade
+enum Next {
+    Tick(Tick),
+    // the exchange closed the connection normally, it has nothing more to send
+    Closed,
+    // dropped, or closed for a reason worth connecting again
+    Lost(String),
+}
+
+// the next trade of `symbol`. pings are answered by tungstenite while reading.
+async fn next_tick(socket: &mut Socket, symbol: &str) -> Next {
+    loop {
+        let text = match socket.next().await {
+            Some(Ok(Message::Text(text))) => text,
+            Some(Ok(Message::Close(None))) => return Next::Closed,
+            Some(Ok(Message::Close(Some(frame)))) if frame.code == CloseCode::Normal => return Next::Closed,
+            Some(Ok(Message::Close(Some(frame)))) => return Next::Lost(format!("closed with {}", frame)),
+            Some(Ok(_)) => continue,
+            Some(Err(e)) => return Next::Lost(e.to_string()),
+            None => return Next::Lost("connection ended".to_string()),
+        };
+        match serde_json::from_str::<Trade>(&text) {
+            Ok(trade) if trade.symbol == symbol => return Next::Tick(trade.into()),
+            Ok(_) => continue,
+            Err(_) => debug!("[FEED] {} skipped a message that is not a trade: {}", symbol, text.as_str()),
+        }
+    }
+}
+
+// one subscription, connected again as often as it takes
+struct Subscription {
+    url: String,
+    symbol: String,
+    socket: Socket,
+    backoff: Duration,
+    min_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl Subscription {
+    // none once the exchange closes the stream
+    async fn next(&mut self) -> Option<Tick> {
+        loop {
+            match next_tick(&mut self.socket, &self.symbol).await {
+                Next::Tick(tick) => {
+                    self.backoff = self.min_backoff;
+                    return Some(tick);
+                }
+                Next::Closed => return None,
+                Next::Lost(reason) => {
+                    warn!("[FEED] {} connection to {} lost: {}", self.symbol, self.url, reason);
+                    self.reconnect().await;
+                }
+            }
+        }
+    }
+
+    async fn reconnect(&mut self) {
+        loop {
+            tokio::time::sleep(self.backoff).await;
+            self.backoff = (self.backoff * 2).min(self.max_backoff);
+            match connect(&self.url, &self.symbol).await {
+                Ok(socket) => {
+                    info!("[FEED] {} reconnected to {}", self.symbol, self.url);
+                    self.socket = socket;
+                    return;
+                }
+                Err(e) => warn!("[FEED] {} {}, trying again in {:?}", self.symbol, e, self.backoff),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl PriceFeed for WebSocketFeed {
+    // only the first connection has to succeed, the ones after it are retried
+    async fn subscribe(&self, symbol: &str) -> Result<TickStream, BoxError> {
+        let subscription = Subscription {
+            url: self.url.clone(),
+            symbol: symbol.to_string(),
+            socket: connect(&self.url, symbol).await?,
+            backoff: self.min_backoff,
+            min_backoff: self.min_backoff,
+            max_backoff: self.max_backoff,
+        };
+        let stream = futures_util::stream::unfold(subscription, |mut subscription| async move {
+            let tick = subscription.next().await?;
+            Some((Ok(tick), subscription))
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_trades() {
+        let trade: Trade =
+            serde_json::from_str(r#"{"symbol": "BTCUSD", "price": "42000.5", "quantity": "0.01", "time": 1700000000250}"#)
+                .unwrap();
+        let tick = Tick::from(trade);
+        assert_eq!(tick.price, "42000.5".parse().unwrap());
+        assert_eq!(tick.time, UNIX_EPOCH + Duration::from_millis(1_700_000_000_250));
+        assert_eq!(
+            serde_json::to_string(&Subscribe { op: "subscribe", symbol: "BTCUSD" }).unwrap(),
+            r#"{"op":"subscribe","symbol":"BTCUSD"}"#
+        );
+    }
+}
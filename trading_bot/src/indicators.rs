@@ -0,0 +1,371 @@
+use crate::feed::candles::Candle;
+use rust_decimal::{Decimal, MathematicalOps};
+use std::collections::VecDeque;
+
+// technical indicators over streaming prices. every update is O(1) and returns the indicator once
+// enough values came in to compute it, none before. values are plain decimals so they work for
+// prices as well as for other indicators, e.g. the MACD signal line is an EMA of the MACD line.
+// like the money types, the arithmetic is checked: an update that would overflow returns None
+// rather than panicking, and the constructors return None for periods that make no sense.
+
+// simple moving average of the last `period` values
+#[derive(Debug, Clone)]
+pub struct Sma {
+    period: usize,
+    window: VecDeque<Decimal>,
+    sum: Decimal,
+}
+
+impl Sma {
+    pub fn new(period: usize) -> Option<Self> {
+        if period == 0 {
+            return None;
+        }
+        Some(Sma { period, window: VecDeque::with_capacity(period), sum: Decimal::ZERO })
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        let full = self.window.len() == self.period;
+        let oldest = if full { *self.window.front()? } else { Decimal::ZERO };
+        let sum = self.sum.checked_sub(oldest)?.checked_add(value)?;
+        if full {
+            self.window.pop_front();
+        }
+        self.window.push_back(value);
+        self.sum = sum;
+        self.value()
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        if self.window.len() < self.period {
+            return None;
+        }
+        self.sum.checked_div(Decimal::from(self.period as u64))
+    }
+}
+
+// exponential moving average weighting the newest value 2 / (period + 1), started off with the
+// simple average of the first `period` values
+#[derive(Debug, Clone)]
+pub struct Ema {
+    alpha: Decimal,
+    seed: Sma,
+    value: Option<Decimal>,
+}
+
+impl Ema {
+    pub fn new(period: usize) -> Option<Self> {
+        let seed = Sma::new(period)?;
+        let alpha = Decimal::TWO.checked_div(Decimal::from(period as u64).checked_add(Decimal::ONE)?)?;
+        Some(Ema { alpha, seed, value: None })
+    }
+
+    pub fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        let ema = match self.value {
+            Some(ema) => value.checked_sub(ema)?.checked_mul(self.alpha)?.checked_add(ema)?,
+            None => self.seed.update(value)?,
+        };
+        self.value = Some(ema);
+        self.value
+    }
+
+    pub fn value(&self) -> Option<Decimal> {
+        self.value
+    }
+}
+
+// Wilder's smoothing: the average moves 1 / period of the way to each new value. also started off
+// with a simple average.
+#[derive(Debug, Clone)]
+struct Wilder {
+    period: Decimal,
+    seed: Sma,
+    value: Option<Decimal>,
+}
+
+impl Wilder {
+    fn new(period: usize) -> Option<Self> {
+        Some(Wilder { period: Decimal::from(period as u64), seed: Sma::new(period)?, value: None })
+    }
+
+    fn update(&mut self, value: Decimal) -> Option<Decimal> {
+        let average = match self.value {
+            Some(average) => {
+                let weighted = average.checked_mul(self.period - Decimal::ONE)?;
+                weighted.checked_add(value)?.checked_div(self.period)?
+            }
+            None => self.seed.update(value)?,
+        };
+        self.value = Some(average);
+        self.value
+    }
+}
+
+// relative strength index from 0 to 100, Wilder's smoothing of gains and losses
+#[derive(Debug, Clone)]
+pub struct Rsi {
+    previous: Option<Decimal>,
+    gains: Wilder,
+    losses: Wilder,
+}
+
+impl Rsi {
+    pub fn new(period: usize) -> Option<Self> {
+        Some(Rsi { previous: None, gains: Wilder::new(period)?, losses: Wilder::new(period)? })
+    }
+
+    pub fn update(&mut self, price: Decimal) -> Option<Decimal> {
+        let change = price.checked_sub(self.previous.replace(price)?)?;
+        let gain = self.gains.update(change.max(Decimal::ZERO));
+        let (gain, loss) = match (gain, self.losses.update((-change).max(Decimal::ZERO))) {
+            (Some(gain), Some(loss)) => (gain, loss),
+            _ => return None,
+        };
+        if loss.is_zero() {
+            return Some(Decimal::ONE_HUNDRED);
+        }
+        let strength = gain.checked_div(loss)?.checked_add(Decimal::ONE)?;
+        Decimal::ONE_HUNDRED.checked_sub(Decimal::ONE_HUNDRED.checked_div(strength)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MacdValue {
+    // fast EMA minus slow EMA
+    pub macd: Decimal,
+    // EMA of the MACD line
+    pub signal: Decimal,
+    pub histogram: Decimal,
+}
+
+// moving average convergence divergence, 12, 26 and 9 being the usual periods
+#[derive(Debug, Clone)]
+pub struct Macd {
+    fast: Ema,
+    slow: Ema,
+    signal: Ema,
+}
+
+impl Macd {
+    // None unless the fast period is shorter than the slow one
+    pub fn new(fast: usize, slow: usize, signal: usize) -> Option<Self> {
+        if fast >= slow {
+            return None;
+        }
+        Some(Macd { fast: Ema::new(fast)?, slow: Ema::new(slow)?, signal: Ema::new(signal)? })
+    }
+
+    pub fn update(&mut self, price: Decimal) -> Option<MacdValue> {
+        // both averages have to see every price
+        let fast = self.fast.update(price);
+        let macd = match (fast, self.slow.update(price)) {
+            (Some(fast), Some(slow)) => fast.checked_sub(slow)?,
+            _ => return None,
+        };
+        let signal = self.signal.update(macd)?;
+        Some(MacdValue { macd, signal, histogram: macd.checked_sub(signal)? })
+    }
+}
+
+impl Default for Macd {
+    fn default() -> Self {
+        Macd::new(12, 26, 9).expect("the usual periods are valid")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Bands {
+    pub lower: Decimal,
+    pub middle: Decimal,
+    pub upper: Decimal,
+}
+
+// Bollinger bands: the simple moving average, `width` standard deviations of the window either
+// side of it. 20 and 2 are the usual choice.
+#[derive(Debug, Clone)]
+pub struct Bollinger {
+    width: Decimal,
+    sma: Sma,
+    squares: Sma,
+}
+
+impl Bollinger {
+    // None for a zero period or a negative width
+    pub fn new(period: usize, width: Decimal) -> Option<Self> {
+        if width.is_sign_negative() {
+            return None;
+        }
+        Some(Bollinger { width, sma: Sma::new(period)?, squares: Sma::new(period)? })
+    }
+
+    pub fn update(&mut self, price: Decimal) -> Option<Bands> {
+        // squared first, so that a price too large to square reaches neither average
+        let square = price.checked_mul(price)?;
+        let middle = self.sma.update(price);
+        let mean_square = self.squares.update(square)?;
+        let middle = middle?;
+        // the population variance, rounding can push a flat window a hair below zero
+        let variance = mean_square.checked_sub(middle.checked_mul(middle)?)?.max(Decimal::ZERO);
+        let offset = self.width.checked_mul(variance.sqrt()?)?;
+        Some(Bands { lower: middle.checked_sub(offset)?, middle, upper: middle.checked_add(offset)? })
+    }
+}
+
+// average true range, Wilder's smoothing of how far each candle reached including the gap from
+// the previous close
+#[derive(Debug, Clone)]
+pub struct Atr {
+    previous_close: Option<Decimal>,
+    average: Wilder,
+}
+
+impl Atr {
+    pub fn new(period: usize) -> Option<Self> {
+        Some(Atr { previous_close: None, average: Wilder::new(period)? })
+    }
+
+    pub fn update(&mut self, candle: &Candle) -> Option<Decimal> {
+        let (high, low) = (candle.high.value(), candle.low.value());
+        let range = high.checked_sub(low)?;
+        let range = match self.previous_close.replace(candle.close.value()) {
+            Some(close) => range.max(high.checked_sub(close)?.abs()).max(low.checked_sub(close)?.abs()),
+            None => range,
+        };
+        self.average.update(range)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::RoundingStrategy;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    fn values(text: &str) -> Vec<Decimal> {
+        text.split_whitespace().map(|v| v.parse().unwrap()).collect()
+    }
+
+    // half up, like the published tables
+    fn round(value: Decimal, dp: u32) -> Decimal {
+        value.round_dp_with_strategy(dp, RoundingStrategy::MidpointAwayFromZero)
+    }
+
+    // the outputs, rounded to `dp` places, of every update that produced one
+    fn run<T>(inputs: &[Decimal], mut update: impl FnMut(Decimal) -> Option<T>, value: impl Fn(T) -> Decimal, dp: u32) -> Vec<Decimal> {
+        inputs.iter().filter_map(|input| update(*input)).map(|output| round(value(output), dp)).collect()
+    }
+
+    // StockCharts' moving average example
+    const CLOSES: &str = "22.27 22.19 22.08 22.17 22.18 22.13 22.23 22.43 22.24 22.29 22.15 22.39 22.38 22.61 23.36 \
+                          24.05 23.75 23.83 23.95 23.63 23.82 23.87 23.65 23.19 23.10 23.33 22.68 23.10 22.40 22.17";
+
+    #[test]
+    fn moving_averages_match_reference_values() {
+        let closes = values(CLOSES);
+        let mut sma = Sma::new(10).unwrap();
+        assert_eq!(
+            run(&closes, |v| sma.update(v), |v| v, 2),
+            values("22.22 22.21 22.23 22.26 22.30 22.42 22.61 22.77 22.91 23.08 23.21 23.38 23.53 23.65 23.71 23.68 23.61 23.51 23.43 23.28 23.13")
+        );
+        let mut ema = Ema::new(10).unwrap();
+        assert_eq!(
+            run(&closes, |v| ema.update(v), |v| v, 2),
+            values("22.22 22.21 22.24 22.27 22.33 22.52 22.80 22.97 23.13 23.28 23.34 23.43 23.51 23.53 23.47 23.40 23.39 23.26 23.23 23.08 22.92")
+        );
+        assert_eq!(ema.value().map(|v| round(v, 2)), Some("22.92".parse().unwrap()));
+    }
+
+    #[test]
+    fn rsi_matches_wilders_reference_values() {
+        // StockCharts' RSI example
+        let closes = values(
+            "44.3389 44.0902 44.1497 43.6124 44.3278 44.8264 45.0955 45.4245 45.8433 46.0826 45.8931 46.0328 45.6140 \
+             46.2820 46.2820 46.0028 46.0328 46.4116 46.2222 45.6439 46.2122 46.2521 45.7137 46.4515 45.7835 45.3548 \
+             44.0288 44.1783 44.2181 44.5672 43.4205 42.6628 43.1314",
+        );
+        let mut rsi = Rsi::new(14).unwrap();
+        assert_eq!(
+            run(&closes, |v| rsi.update(v), |v| v, 2),
+            values("70.53 66.32 66.55 69.41 66.36 57.97 62.93 63.26 56.06 62.38 54.71 50.42 39.99 41.46 41.87 45.46 37.30 33.08 37.77")
+        );
+
+        let mut rising = Rsi::new(3).unwrap();
+        assert_eq!(run(&values("1 2 3 4 5"), |v| rising.update(v), |v| v, 2), values("100 100"));
+    }
+
+    // the MACD, Bollinger and ATR references were computed from the textbook definitions over the
+    // whole series rather than incrementally
+    #[test]
+    fn macd_and_bollinger_match_reference_values() {
+        // 40 prices zigzagging around 100
+        let closes: Vec<Decimal> = (0..40).map(|i| Decimal::from(100 + (i * 7) % 13 - i % 5)).collect();
+        let mut macd = Macd::default();
+        let outputs: Vec<MacdValue> = closes.iter().filter_map(|v| macd.update(*v)).collect();
+        // 26 prices for the slow EMA and 8 more for the signal line
+        assert_eq!(outputs.len(), 7);
+        let round4 = |values: Vec<Decimal>| values.into_iter().map(|v| round(v, 4)).collect::<Vec<_>>();
+        assert_eq!(round4(outputs.iter().map(|v| v.macd).collect()), values("0.2610 -0.0761 0.5381 0.4548 0.8630 0.6146 -0.1455"));
+        assert_eq!(round4(outputs.iter().map(|v| v.signal).collect()), values("0.1720 0.1224 0.2055 0.2554 0.3769 0.4244 0.3105"));
+        assert_eq!(round(outputs[6].histogram, 4), "-0.4559".parse().unwrap());
+
+        let mut bollinger = Bollinger::new(20, Decimal::TWO).unwrap();
+        let bands: Vec<Bands> = values(CLOSES).into_iter().filter_map(|v| bollinger.update(v)).collect();
+        assert_eq!(bands.len(), 11);
+        let last = bands[10];
+        assert_eq!(
+            (round(last.lower, 4), round(last.middle, 4), round(last.upper, 4)),
+            ("21.9055".parse().unwrap(), "23.1705".parse().unwrap(), "24.4355".parse().unwrap())
+        );
+        assert_eq!(round(bands[0].upper, 4), "24.1261".parse().unwrap());
+
+        let mut flat = Bollinger::new(3, Decimal::TWO).unwrap();
+        let bands: Vec<Bands> = values("5 5 5").into_iter().filter_map(|v| flat.update(v)).collect();
+        assert_eq!((bands[0].lower, bands[0].upper), (Decimal::from(5), Decimal::from(5)));
+    }
+
+    #[test]
+    fn rejects_bad_periods_and_overflow() {
+        assert!(Sma::new(0).is_none());
+        assert!(Rsi::new(0).is_none());
+        assert!(Macd::new(26, 12, 9).is_none());
+        assert!(Macd::new(12, 26, 0).is_none());
+        assert!(Bollinger::new(20, -Decimal::ONE).is_none());
+
+        let mut sma = Sma::new(2).unwrap();
+        sma.update(Decimal::MAX);
+        // the sum would overflow, that value is left out
+        assert_eq!(sma.update(Decimal::MAX), None);
+        assert_eq!(sma.update(-Decimal::MAX), Some(Decimal::ZERO));
+        let mut bollinger = Bollinger::new(2, Decimal::TWO).unwrap();
+        assert_eq!(bollinger.update(Decimal::MAX), None);
+        assert_eq!(bollinger.update(Decimal::ONE), None);
+        assert_eq!(bollinger.update(Decimal::ONE).map(|bands| bands.middle), Some(Decimal::ONE));
+    }
+
+    #[test]
+    fn atr_includes_gaps_from_the_previous_close() {
+        let candle = |high: &str, low: &str, close: &str| Candle {
+            open_time: UNIX_EPOCH,
+            interval: Duration::from_secs(60),
+            open: close.parse().unwrap(),
+            high: high.parse().unwrap(),
+            low: low.parse().unwrap(),
+            close: close.parse().unwrap(),
+            volume: Default::default(),
+            ticks: 1,
+        };
+        let candles = [
+            candle("48.70", "47.79", "48.16"),
+            candle("48.72", "48.14", "48.61"),
+            candle("48.90", "48.39", "48.75"),
+            candle("48.87", "48.37", "48.63"),
+            candle("48.82", "48.24", "48.74"),
+            // gaps up, the range from the last close is the true one
+            candle("50.10", "49.80", "50.00"),
+            candle("49.90", "49.20", "49.30"),
+        ];
+        let mut atr = Atr::new(5).unwrap();
+        let outputs: Vec<Decimal> = candles.iter().filter_map(|c| atr.update(c)).map(|v| round(v, 4)).collect();
+        assert_eq!(outputs, values("0.6160 0.7648 0.7718"));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::bot::{OpenOrder, State, TradingConfig};
+use crate::market::Side;
+use crate::money::{Money, Price, Quantity};
+use crate::risk::RiskManager;
+use crate::BoxError;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    // a filled market order, or what a limit order filled in the end
+    Order { side: Side, quantity: Quantity, price: Price },
+    // a limit order went out
+    OrderPlaced { id: String, side: Side, quantity: Quantity, price: Price },
+    // a limit order is done, its fills were recorded right before
+    OrderClosed { id: String },
+    // the bot state right after a transition
+    State { last_operation_price: Price, next_operation: State, position: Quantity },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalRecord {
+    // unix time in milliseconds
+    pub time: u64,
+    pub symbol: String,
+    #[serde(flatten)]
+    pub event: JournalEvent,
+}
+
+impl JournalRecord {
+    pub fn new(time: SystemTime, symbol: &str, event: JournalEvent) -> Self {
+        let time = time.duration_since(UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        JournalRecord { time, symbol: symbol.to_string(), event }
+    }
+
+    pub fn system_time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.time)
+    }
+}
+
+// append-only JSON lines file, one record per order or state transition of any symbol.
+// every append is flushed to disk before the bot moves on.
+pub struct Journal {
+    path: PathBuf,
+    file: File,
+}
+
+impl Journal {
+    pub fn open(path: &Path) -> Result<Journal, BoxError> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| format!("cannot open journal {}: {}", path.display(), e))?;
+        Ok(Journal { path: path.to_path_buf(), file })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn append(&mut self, record: &JournalRecord) -> Result<(), BoxError> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        self.file.write_all(line.as_bytes())?;
+        self.file.sync_data()?;
+        Ok(())
+    }
+
+    // all records of a journal, or none if it does not exist yet.
+    // a crash can leave the last line half written, that line is dropped.
+    pub fn read(path: &Path) -> Result<Vec<JournalRecord>, BoxError> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(format!("cannot read journal {}: {}", path.display(), e).into()),
+        };
+        let lines: Vec<&str> = text.lines().filter(|line| !line.trim().is_empty()).collect();
+        let mut records = Vec::with_capacity(lines.len());
+        for (idx, line) in lines.iter().enumerate() {
+            match serde_json::from_str(line) {
+                Ok(record) => records.push(record),
+                Err(_) if idx + 1 == lines.len() && !text.ends_with('\n') => break,
+                Err(e) => return Err(format!("journal {} line {}: {}", path.display(), idx + 1, e).into()),
+            }
+        }
+        Ok(records)
+    }
+}
+
+// rebuild the state of config.symbol from a journal, returning the limit order still open.
+// orders are applied on their own too, so an order whose state record never made it to disk
+// still counts.
+pub fn restore(records: &[JournalRecord], config: &mut TradingConfig, risk: &mut RiskManager) -> Option<OpenOrder> {
+    let symbol = config.symbol.clone();
+    let mut open_order = None;
+    for record in records.iter().filter(|record| record.symbol == symbol) {
+        match record.event {
+            JournalEvent::Order { side: Side::Buy, quantity, price } => {
+                risk.record_buy(price, record.system_time());
+                config.last_operation_price = price;
+                config.position = config.position.checked_add(quantity).unwrap_or(quantity);
+                config.next_operation = State::Sell;
+            }
+            JournalEvent::Order { side: Side::Sell, quantity, price } => {
+                risk.record_sell(price, quantity, record.system_time());
+                config.last_operation_price = price;
+                config.position = config.position.checked_sub(quantity).unwrap_or(Quantity::ZERO);
+                config.next_operation = State::Buy;
+            }
+            JournalEvent::OrderPlaced { ref id, side, .. } => {
+                open_order = Some(OpenOrder { id: id.clone(), side, placed_at: record.system_time() });
+            }
+            JournalEvent::OrderClosed { ref id } => {
+                if open_order.as_ref().is_some_and(|order: &OpenOrder| &order.id == id) {
+                    open_order = None;
+                }
+            }
+            JournalEvent::State { last_operation_price, next_operation, position } => {
+                config.last_operation_price = last_operation_price;
+                config.next_operation = next_operation;
+                config.position = position;
+            }
+        }
+    }
+    open_order
+}
+
+// a simulated or paper account as the journal's fills left it. those markets keep nothing on disk
+// themselves, so after a restart they start from this and not from an empty wallet.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Account {
+    pub balance: Money,
+    pub holdings: HashMap<String, Quantity>,
+}
+
+impl Account {
+    pub fn holdings(&self, symbol: &str) -> Quantity {
+        self.holdings.get(symbol).copied().unwrap_or_default()
+    }
+}
+
+// replays the fills of every symbol on an account that started with `balance`. a journal that
+// spends or sells more than the account had is refused rather than restored.
+pub fn replay_account(records: &[JournalRecord], balance: Money) -> Result<Account, BoxError> {
+    let mut account = Account { balance, holdings: HashMap::new() };
+    for record in records {
+        let (side, quantity, price) = match record.event {
+            JournalEvent::Order { side, quantity, price } => (side, quantity, price),
+            _ => continue,
+        };
+        let value = price.checked_notional(quantity).ok_or("order value overflowed")?;
+        let holdings = account.holdings.entry(record.symbol.clone()).or_default();
+        let (balance, held) = match side {
+            Side::Buy => (account.balance.checked_sub(value), holdings.checked_add(quantity)),
+            Side::Sell => (account.balance.checked_add(value), holdings.checked_sub(quantity)),
+        };
+        match (balance, held) {
+            (Some(balance), Some(held)) if balance >= Money::ZERO && held >= Quantity::ZERO => {
+                account.balance = balance;
+                *holdings = held;
+            }
+            _ => {
+                let message = format!("journal: {} {:?} {} at {} does not fit the account", record.symbol, side, quantity, price);
+                return Err(message.into());
+            }
+        }
+    }
+    Ok(account)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::risk::RiskConfig;
+
+    fn buy(time: u64) -> JournalRecord {
+        JournalRecord {
+            time,
+            symbol: "BTCUSD".to_string(),
+            event: JournalEvent::Order { side: Side::Buy, quantity: "0.01".parse().unwrap(), price: "40000".parse().unwrap() },
+        }
+    }
+
+    #[test]
+    fn appends_and_reads_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        assert!(Journal::read(&path).unwrap().is_empty());
+
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(&buy(1000)).unwrap();
+        drop(journal);
+        let mut journal = Journal::open(&path).unwrap();
+        journal.append(&buy(2000)).unwrap();
+
+        assert_eq!(Journal::read(&path).unwrap(), vec![buy(1000), buy(2000)]);
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            text.lines().next().unwrap(),
+            r#"{"time":1000,"symbol":"BTCUSD","event":"order","side":"buy","quantity":"0.01","price":"40000"}"#
+        );
+    }
+
+    #[test]
+    fn drops_a_torn_last_line_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("journal.jsonl");
+        let line = serde_json::to_string(&buy(1000)).unwrap();
+
+        fs::write(&path, format!("{}\n{{\"time\":20", line)).unwrap();
+        assert_eq!(Journal::read(&path).unwrap(), vec![buy(1000)]);
+
+        fs::write(&path, format!("{{\"time\":20\n{}\n", line)).unwrap();
+        assert!(Journal::read(&path).unwrap_err().to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn restores_a_position_even_without_its_state_record() {
+        let mut config = TradingConfig::default();
+        let mut risk = RiskManager::new(RiskConfig::default());
+        let records = vec![
+            JournalRecord {
+                time: 500,
+                symbol: "BTCUSD".to_string(),
+                event: JournalEvent::State {
+                    last_operation_price: "41000".parse().unwrap(),
+                    next_operation: State::Buy,
+                    position: Quantity::ZERO,
+                },
+            },
+            buy(1000),
+            JournalRecord { symbol: "ETHUSD".to_string(), ..buy(1500) },
+        ];
+        restore(&records, &mut config, &mut risk);
+        assert_eq!(config.next_operation, State::Sell);
+        assert_eq!(config.position, "0.01".parse().unwrap());
+        assert_eq!(config.last_operation_price, "40000".parse().unwrap());
+        // the stop loss of the restored position is back as well
+        assert!(risk.exit_signal("37000".parse().unwrap()).is_some());
+    }
+
+    #[test]
+    fn restores_the_open_limit_order() {
+        let placed = |id: &str, time| JournalRecord {
+            time,
+            symbol: "BTCUSD".to_string(),
+            event: JournalEvent::OrderPlaced {
+                id: id.to_string(),
+                side: Side::Buy,
+                quantity: "0.01".parse().unwrap(),
+                price: "39900".parse().unwrap(),
+            },
+        };
+        let closed = JournalRecord { time: 1500, symbol: "BTCUSD".to_string(), event: JournalEvent::OrderClosed { id: "1".to_string() } };
+        let mut config = TradingConfig::default();
+        let mut risk = RiskManager::new(RiskConfig::default());
+
+        let open = restore(&[placed("1", 1000), closed.clone(), placed("2", 2000)], &mut config, &mut risk).unwrap();
+        assert_eq!((open.id.as_str(), open.side), ("2", Side::Buy));
+        assert_eq!(open.placed_at, UNIX_EPOCH + Duration::from_millis(2000));
+        assert_eq!(restore(&[placed("1", 1000), closed], &mut config, &mut risk), None);
+    }
+
+    #[test]
+    fn replays_fills_on_the_account() {
+        let sell = |symbol: &str, time| JournalRecord {
+            time,
+            symbol: symbol.to_string(),
+            event: JournalEvent::Order { side: Side::Sell, quantity: "0.01".parse().unwrap(), price: "41000".parse().unwrap() },
+        };
+        let records = vec![buy(1000), JournalRecord { symbol: "ETHUSD".to_string(), ..buy(1500) }, sell("BTCUSD", 2000)];
+        let account = replay_account(&records, "1000".parse().unwrap()).unwrap();
+        // 1000 - 400 - 400 + 410
+        assert_eq!(account.balance, "610".parse().unwrap());
+        assert_eq!(account.holdings("BTCUSD"), Quantity::ZERO);
+        assert_eq!(account.holdings("ETHUSD"), "0.01".parse().unwrap());
+        assert_eq!(account.holdings("XRPUSD"), Quantity::ZERO);
+
+        assert!(replay_account(&[sell("ETHUSD", 1000)], "1000".parse().unwrap()).is_err());
+        assert!(replay_account(&[buy(1000)], "100".parse().unwrap()).is_err());
+    }
+}
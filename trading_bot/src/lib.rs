@@ -0,0 +1,27 @@
+use std::error::Error;
+
+pub mod backtest;
+pub mod bot;
+pub mod config;
+pub mod feed;
+pub mod indicators;
+pub mod journal;
+pub mod logging;
+pub mod market;
+pub mod metrics;
+pub mod money;
+pub mod notify;
+pub mod optimize;
+pub mod portfolio;
+pub mod risk;
+
+pub use bot::{State, TradingBot, TradingConfig};
+pub use config::Config;
+pub use feed::PriceFeed;
+pub use market::Market;
+pub use money::{Money, Price, Quantity, SymbolRules};
+pub use portfolio::Portfolio;
+pub use risk::{RiskConfig, RiskManager};
+
+// errors have to be Send + Sync so the bot can run on tokio tasks
+pub type BoxError = Box<dyn Error + Send + Sync>;
@@ -0,0 +1,120 @@
+use log::Record;
+use serde::Serialize;
+use std::future::Future;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    // one JSON object per line
+    Json,
+    // the plain env_logger format
+    Text,
+}
+
+// the cycle a log line belongs to, so the lines of one price update can be grepped together
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cycle {
+    pub id: String,
+    pub symbol: String,
+}
+
+tokio::task_local! {
+    static CYCLE: Cycle;
+}
+
+// runs `future` with every line it logs tagged with `cycle`
+pub async fn in_cycle<F: Future>(cycle: Cycle, future: F) -> F::Output {
+    CYCLE.scope(cycle, future).await
+}
+
+pub fn current_cycle() -> Option<Cycle> {
+    CYCLE.try_with(Cycle::clone).ok()
+}
+
+#[derive(Serialize)]
+struct Line<'a> {
+    ts: &'a str,
+    level: &'a str,
+    target: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    event: Option<String>,
+    msg: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cycle_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    symbol: Option<&'a str>,
+}
+
+// a log line as JSON. messages that start with a `[TAG]` get it split off as the event,
+// e.g. "[BUY] BTCUSD bought 0.01 at 40000" logs event "buy".
+pub fn json_line(ts: &str, record: &Record, cycle: Option<&Cycle>) -> String {
+    let message = record.args().to_string();
+    let (event, msg) = match message.strip_prefix('[').and_then(|rest| rest.split_once("] ")) {
+        Some((tag, rest)) => (Some(tag.to_lowercase()), rest),
+        None => (None, message.as_str()),
+    };
+    let line = Line {
+        ts,
+        level: record.level().as_str(),
+        target: record.target(),
+        event,
+        msg,
+        cycle_id: cycle.map(|cycle| cycle.id.as_str()),
+        symbol: cycle.map(|cycle| cycle.symbol.as_str()),
+    };
+    serde_json::to_string(&line).expect("log lines serialize")
+}
+
+// sets up env_logger, RUST_LOG overrides `level`
+pub fn init(format: LogFormat, level: &str) {
+    let mut builder = env_logger::Builder::from_env(env_logger::Env::default().default_filter_or(level));
+    if format == LogFormat::Json {
+        builder.format(|buf, record| {
+            let ts = buf.timestamp_millis().to_string();
+            writeln!(buf, "{}", json_line(&ts, record, current_cycle().as_ref()))
+        });
+    }
+    builder.init();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use log::Level;
+    use serde_json::{json, Value};
+
+    #[tokio::test]
+    async fn tags_lines_with_the_event_and_cycle() {
+        let cycle = Cycle { id: "BTCUSD-7".to_string(), symbol: "BTCUSD".to_string() };
+        let line = in_cycle(cycle, async {
+            let cycle = current_cycle();
+            json_line(
+                "2024-01-01T00:00:00.000Z",
+                &Record::builder()
+                    .level(Level::Info)
+                    .target("trading_bot::bot")
+                    .args(format_args!("[BUY] {} bought {} at {}", "BTCUSD", "0.01", "40000"))
+                    .build(),
+                cycle.as_ref(),
+            )
+        })
+        .await;
+        let line: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            line,
+            json!({
+                "ts": "2024-01-01T00:00:00.000Z",
+                "level": "INFO",
+                "target": "trading_bot::bot",
+                "event": "buy",
+                "msg": "BTCUSD bought 0.01 at 40000",
+                "cycle_id": "BTCUSD-7",
+                "symbol": "BTCUSD",
+            })
+        );
+
+        assert_eq!(current_cycle(), None);
+        let line = json_line("t", &Record::builder().level(Level::Warn).args(format_args!("no tag")).build(), None);
+        assert_eq!(line, r#"{"ts":"t","level":"WARN","target":"","msg":"no tag"}"#);
+    }
+}
@@ -1,38 +1,313 @@
-pub struct TradingBot {
-    pub trading_config: TradingConfig,
-    pub market: Box<dyn Market>,
+use clap::{Args, Parser, Subcommand};
+use log::info;
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+use trading_bot::backtest::{load_prices, run_backtest, PricePoint};
+use trading_bot::config::MarketBackend;
+use trading_bot::feed::WebSocketFeed;
+use trading_bot::journal::{self, Journal};
+use trading_bot::logging::{self, LogFormat};
+use trading_bot::market::{InstrumentedMarket, PaperMarket, RestMarket, SimulatedMarket, Wallet};
+use trading_bot::metrics::{self, Metrics};
+use trading_bot::notify::{EmailNotifier, Notifier, Notifiers, StdoutNotifier, WebhookNotifier};
+use trading_bot::optimize::{optimize, Parameter, ParameterSpace, Range};
+use trading_bot::portfolio::{Allocator, ErrorLimits};
+use trading_bot::{BoxError, Config, Market, Money, Portfolio, TradingBot, TradingConfig};
+
+#[derive(Parser)]
+#[command(name = "trading_bot", about = "high sell, low buy")]
+struct Cli {
+    /// path to the TOML configuration file
+    #[arg(short, long, default_value = "bot.toml")]
+    config: PathBuf,
+    /// log lines as JSON objects or as plain text
+    #[arg(long, value_enum, default_value = "json")]
+    log_format: LogFormat,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// trade every configured symbol on the configured market
+    Run,
+    /// replay historical prices of one symbol and print a report
+    Backtest {
+        #[command(flatten)]
+        history: History,
+    },
+    /// search strategy thresholds on historical prices, validated walk-forward
+    Optimize(Box<Sweep>),
+    /// trade live prices without sending orders to an exchange
+    Paper,
+}
+
+#[derive(Args)]
+struct Sweep {
+    #[command(flatten)]
+    history: History,
+    /// `from:to:step` or a single value, in percent like the config
+    #[arg(long, allow_hyphen_values = true)]
+    upward_trend_threshold: Option<Range>,
+    /// same as --upward-trend-threshold
+    #[arg(long, allow_hyphen_values = true)]
+    dip_threshold: Option<Range>,
+    /// same as --upward-trend-threshold
+    #[arg(long, allow_hyphen_values = true)]
+    profit_threshold: Option<Range>,
+    /// same as --upward-trend-threshold
+    #[arg(long, allow_hyphen_values = true)]
+    stop_loss_threshold: Option<Range>,
+    /// try this many random parameter sets instead of the whole grid
+    #[arg(long)]
+    samples: Option<usize>,
+    /// seed of the random search
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// number of tune and validate window pairs
+    #[arg(long, default_value_t = 4)]
+    folds: usize,
+    /// how many of the ranked parameter sets to print
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+}
+
+#[derive(Args)]
+struct History {
+    /// symbol to replay, defaults to the first configured one
+    #[arg(long)]
+    symbol: Option<String>,
+    /// `timestamp,price` CSV file, defaults to the symbol's prices
+    #[arg(long)]
+    prices: Option<PathBuf>,
+    /// starting balance, defaults to the simulated market's balance
+    #[arg(long)]
+    balance: Option<Money>,
+}
+
+// where orders go
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Live,
+    // to a simulated wallet, whatever the backend
+    Paper,
+}
+
+fn portfolio_for(config: &Config, mode: Mode, metrics: Option<&Arc<Metrics>>) -> Result<Portfolio, BoxError> {
+    let journal = match mode {
+        Mode::Live => &config.journal,
+        Mode::Paper => &config.paper.journal,
+    };
+    let (journal, records) = match journal {
+        Some(path) => (Some(Arc::new(Mutex::new(Journal::open(path)?))), Journal::read(path)?),
+        None => (None, Vec::new()),
+    };
+    // the simulated and paper symbols all draw from one balance, like they would on an exchange
+    // account
+    let mut wallet = None;
+    let balance = match (mode, &config.market) {
+        (Mode::Paper, _) if config.paper.balance.is_some() => config.paper.balance,
+        (_, MarketBackend::Simulated { balance }) => Some(*balance),
+        (Mode::Live, MarketBackend::Rest { .. }) => None,
+        (Mode::Paper, MarketBackend::Rest { .. }) => return Err("paper trading on the rest backend needs paper.balance".into()),
+    };
+    // the wallet and holdings are only in memory, they start from where the journal left them
+    let account = journal::replay_account(&records, balance.unwrap_or_default())?;
+
+    let notifier = notifier_for(config)?;
+    let error_limits = ErrorLimits {
+        alert_after: config.notify.as_ref().map_or(ErrorLimits::default().alert_after, |notify| notify.repeated_errors),
+        halt_after: config.portfolio.halt_after_errors,
+    };
+    let mut portfolio =
+        Portfolio::new(Arc::new(Allocator::new(config.portfolio.max_allocation))).with_error_limits(error_limits);
+    if let Some(feed) = &config.feed {
+        portfolio = portfolio.with_feed(Arc::new(WebSocketFeed::new(&feed.url)));
+    }
+    for symbol in &config.symbols {
+        let trading_config = symbol.trading_config(config);
+        let market: Box<dyn Market> = match &config.market {
+            // the simulated backend never reaches an exchange, so it is a paper market already
+            MarketBackend::Simulated { .. } => {
+                let wallet = wallet.get_or_insert_with(|| Arc::new(Wallet::new(account.balance)));
+                let prices = symbol.prices.as_ref().ok_or_else(|| format!("{}: no prices file", symbol.symbol))?;
+                let prices = load_prices(prices)?.into_iter().map(|p| p.price).collect();
+                let market = SimulatedMarket::with_wallet(prices, wallet.clone(), trading_config.symbol_rules);
+                Box::new(market.with_holdings(account.holdings(&symbol.symbol)))
+            }
+            MarketBackend::Rest { base_url, quote_asset, api_key_env, api_secret_env } => {
+                let api_key = env_var(api_key_env)?;
+                let api_secret = env_var(api_secret_env)?;
+                let market = Box::new(RestMarket::new(base_url, &symbol.symbol, quote_asset, &api_key, &api_secret));
+                match balance {
+                    Some(_) if mode == Mode::Paper => {
+                        let wallet = wallet.get_or_insert_with(|| Arc::new(Wallet::new(account.balance)));
+                        let market = PaperMarket::new(market, wallet.clone(), trading_config.symbol_rules);
+                        Box::new(market.with_holdings(account.holdings(&symbol.symbol)))
+                    }
+                    _ => market,
+                }
+            }
+        };
+        let bot = match metrics {
+            Some(metrics) => {
+                let market = Box::new(InstrumentedMarket::new(market, &symbol.symbol, metrics.clone()));
+                TradingBot::new(trading_config, market).with_metrics(metrics.clone())
+            }
+            None => TradingBot::new(trading_config, market),
+        };
+        let bot = match &notifier {
+            Some(notifier) => bot.with_notifier(notifier.clone()),
+            None => bot,
+        };
+        let bot = match &journal {
+            Some(journal) => bot.with_shared_journal(journal.clone())?,
+            None => bot,
+        };
+        portfolio.add(bot);
+    }
+    Ok(portfolio)
+}
+
+// everything [notify] sends alerts to
+fn notifier_for(config: &Config) -> Result<Option<Arc<dyn Notifier>>, BoxError> {
+    let section = match &config.notify {
+        Some(section) => section,
+        None => return Ok(None),
+    };
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+    if section.stdout {
+        notifiers.push(Box::new(StdoutNotifier));
+    }
+    if let Some(webhook) = &section.webhook {
+        notifiers.push(Box::new(WebhookNotifier::new(&webhook.url)));
+    }
+    if let Some(email) = &section.email {
+        let credentials = match (&email.username, &email.password_env) {
+            (Some(username), Some(password_env)) => Some((username.clone(), env_var(password_env)?)),
+            _ => None,
+        };
+        notifiers.push(Box::new(EmailNotifier::new(
+            &email.host,
+            email.port,
+            email.security,
+            credentials,
+            &email.from,
+            &email.to,
+        )?));
+    }
+    Ok(Some(Arc::new(Notifiers(notifiers))))
+}
+
+fn env_var(name: &str) -> Result<String, BoxError> {
+    env::var(name).map_err(|_| format!("environment variable {} is not set", name).into())
 }
 
-pub trait Market {
-    async fn get_balance(&self) -> Result<f32, Box<dyn Error>>;
-    async fn get_market_price(&self) -> Result<f32, Box<dyn Error>>;
-    async fn place_sell_order(&self, amount: f32) -> Result<f32, Box<dyn Error>>;
-    async fn place_buy_order(&self, amount: f32) -> Result<f32, Box<dyn Error>>;
+fn symbols(config: &Config) -> String {
+    config.symbols.iter().map(|symbol| symbol.symbol.as_str()).collect::<Vec<_>>().join(", ")
 }
 
-impl TradingBot {
-    // main trading logic
-    // high sell, low buy
-    //
+// runs the portfolio, next to the metrics endpoint if one is configured
+async fn trade(config: &Config, mode: Mode) -> Result<(), BoxError> {
+    let report_interval = Duration::from_secs(config.portfolio.report_interval_secs);
+    let section = match &config.metrics {
+        Some(section) => section,
+        None => return portfolio_for(config, mode, None)?.start(report_interval).await,
+    };
+    let metrics = Arc::new(Metrics::new());
+    let portfolio = portfolio_for(config, mode, Some(&metrics))?;
+    let listener = TcpListener::bind(section.listen)
+        .await
+        .map_err(|e| format!("cannot listen for metrics on {}: {}", section.listen, e))?;
+    info!("[METRICS] serving http://{}/metrics", section.listen);
+    tokio::select! {
+        result = portfolio.start(report_interval) => result,
+        result = metrics::serve(listener, metrics) => result,
+    }
+}
 
-    pub async fn start(&mut self) -> Result<(), Box<dyn Error>> {
-        let current_price = self.get_market_price().await?;
-        info!("[PRICE] current market price: {:?} $", current_price);
+// what a backtest or an optimization replays: the symbol's config, the starting balance and
+// its prices
+fn replay(config: &Config, history: History) -> Result<(TradingConfig, Money, Vec<PricePoint>), BoxError> {
+    let symbol = match history.symbol {
+        Some(name) => config
+            .symbols
+            .iter()
+            .find(|s| s.symbol == name)
+            .ok_or_else(|| format!("symbol {} is not configured", name))?,
+        None => &config.symbols[0],
+    };
+    let prices = history
+        .prices
+        .or_else(|| symbol.prices.clone())
+        .ok_or_else(|| format!("{} has no prices file, pass --prices", symbol.symbol))?;
+    let balance = match (&config.market, history.balance) {
+        (_, Some(balance)) => balance,
+        (MarketBackend::Simulated { balance }, None) => *balance,
+        _ => return Err("backtesting without the simulated backend needs --balance".into()),
+    };
+    Ok((symbol.trading_config(config), balance, load_prices(&prices)?))
+}
 
-        let percentage_diff = (current_price - self.trading_config.last_operation_price)
+async fn run(cli: Cli) -> Result<(), BoxError> {
+    let config = Config::load(&cli.config)?;
+    match cli.command {
+        Command::Run => {
+            info!("[START] trading {}", symbols(&config));
+            trade(&config, Mode::Live).await
+        }
+        Command::Backtest { history } => {
+            let (trading_config, balance, prices) = replay(&config, history)?;
+            let report = run_backtest(trading_config, balance, &prices).await?;
+            println!("{}", report);
+            Ok(())
+        }
+        Command::Optimize(sweep) => {
+            let (trading_config, balance, prices) = replay(&config, sweep.history)?;
+            let ranges = [
+                (Parameter::UpwardTrendThreshold, sweep.upward_trend_threshold),
+                (Parameter::DipThreshold, sweep.dip_threshold),
+                (Parameter::ProfitThreshold, sweep.profit_threshold),
+                (Parameter::StopLossThreshold, sweep.stop_loss_threshold),
+            ];
+            let mut space = ParameterSpace::new();
+            for (parameter, range) in ranges.iter() {
+                if let Some(range) = range {
+                    space = space.with(*parameter, *range);
+                }
+            }
+            let sets = match sweep.samples {
+                Some(samples) => space.random(samples, sweep.seed)?,
+                None => space.grid()?,
+            };
+            let mut report = optimize(trading_config, balance, prices, sets, sweep.folds).await?;
+            report.top = sweep.top;
+            println!("{}", report);
+            Ok(())
+        }
+        Command::Paper => {
+            info!("[START] paper trading {}", symbols(&config));
+            trade(&config, Mode::Paper).await
+        }
     }
 }
 
-// get the buy point
-// buy action
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    // a backtest logs every replayed price, only its report is interesting by default
+    let level = match cli.command {
+        Command::Backtest { .. } | Command::Optimize(_) => "warn",
+        _ => "info",
+    };
+    logging::init(cli.log_format, level);
 
-async fn try_to_buy(&mut self, diff: f32) -> Result<f32, Box<dyn Error>> {
-    if diff >= self.trading_config.upward_trend_threshold || diff <= self.trading_config.dip_threshold {
-        let current_balance = self.get_balance().await?;
-        info!("[BALANCE] current amount balance {:?} $ USD", current_balance);
-        self.trading_config.last_operation_price = self.place_buy_order(current_balance).await?;
-        self.trading_config.next_operation = State::SELL;
-        info!("[BUY] Bought 0.002 BTC for {:?} $ USD", self.trading_config.last_operation_price);
+    if let Err(e) = run(cli).await {
+        eprintln!("error: {}", e);
+        process::exit(1);
     }
-    Ok(self.trading_config.last_operation_price)
 }
@@ -0,0 +1,82 @@
+use super::{Market, Order, Side};
+use crate::metrics::Metrics;
+use crate::money::{Money, Price, Quantity};
+use crate::BoxError;
+use async_trait::async_trait;
+use log::warn;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Instant;
+
+// times every call to the wrapped market and keeps the balance gauge up to date. the balance is
+// fetched again after every order that moved funds, so equity does not count them twice.
+pub struct InstrumentedMarket {
+    market: Box<dyn Market>,
+    symbol: String,
+    metrics: Arc<Metrics>,
+}
+
+impl InstrumentedMarket {
+    pub fn new(market: Box<dyn Market>, symbol: &str, metrics: Arc<Metrics>) -> Self {
+        InstrumentedMarket { market, symbol: symbol.to_string(), metrics }
+    }
+
+    async fn timed<T>(&self, call: &str, future: impl Future<Output = Result<T, BoxError>>) -> Result<T, BoxError> {
+        let started = Instant::now();
+        let result = future.await;
+        self.metrics.market_call(&self.symbol, call, started.elapsed(), result.is_ok());
+        result
+    }
+
+    async fn refresh_balance(&self) {
+        if let Err(e) = self.get_balance().await {
+            warn!("[METRICS] {} cannot refresh the balance: {}", self.symbol, e);
+        }
+    }
+}
+
+#[async_trait]
+impl Market for InstrumentedMarket {
+    async fn get_balance(&self) -> Result<Money, BoxError> {
+        let balance = self.timed("get_balance", self.market.get_balance()).await?;
+        self.metrics.set_balance(balance);
+        Ok(balance)
+    }
+
+    async fn get_market_price(&self) -> Result<Price, BoxError> {
+        self.timed("get_market_price", self.market.get_market_price()).await
+    }
+
+    async fn place_sell_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        let price = self.timed("place_sell_order", self.market.place_sell_order(amount)).await?;
+        self.refresh_balance().await;
+        Ok(price)
+    }
+
+    async fn place_buy_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        let price = self.timed("place_buy_order", self.market.place_buy_order(amount)).await?;
+        self.refresh_balance().await;
+        Ok(price)
+    }
+
+    async fn place_limit_order(&self, side: Side, amount: Quantity, price: Price) -> Result<Order, BoxError> {
+        let order = self.timed("place_limit_order", self.market.place_limit_order(side, amount, price)).await?;
+        self.refresh_balance().await;
+        Ok(order)
+    }
+
+    async fn get_order(&self, id: &str) -> Result<Order, BoxError> {
+        let order = self.timed("get_order", self.market.get_order(id)).await?;
+        // resting orders move funds as they fill, it is enough to catch up once they are done
+        if order.status.is_done() {
+            self.refresh_balance().await;
+        }
+        Ok(order)
+    }
+
+    async fn cancel_order(&self, id: &str) -> Result<Order, BoxError> {
+        let order = self.timed("cancel_order", self.market.cancel_order(id)).await?;
+        self.refresh_balance().await;
+        Ok(order)
+    }
+}
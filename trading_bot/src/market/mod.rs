@@ -0,0 +1,151 @@
+use crate::money::{Money, Price, Quantity};
+use crate::BoxError;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+pub mod instrumented;
+pub mod paper;
+pub mod rest;
+pub mod simulated;
+
+pub use instrumented::InstrumentedMarket;
+pub use paper::PaperMarket;
+pub use rest::RestMarket;
+pub use simulated::{Fill, SimulatedMarket, Wallet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OrderStatus {
+    Open,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Rejected,
+}
+
+impl OrderStatus {
+    // nothing more will fill
+    pub fn is_done(self) -> bool {
+        matches!(self, OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected)
+    }
+}
+
+// a limit order as the exchange last reported it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Order {
+    pub id: String,
+    pub side: Side,
+    pub quantity: Quantity,
+    pub limit_price: Price,
+    pub filled_quantity: Quantity,
+    // average price of the fills so far
+    pub average_price: Option<Price>,
+    pub status: OrderStatus,
+}
+
+impl Order {
+    pub fn new(id: String, side: Side, quantity: Quantity, limit_price: Price) -> Self {
+        Order {
+            id,
+            side,
+            quantity,
+            limit_price,
+            filled_quantity: Quantity::ZERO,
+            average_price: None,
+            status: OrderStatus::Open,
+        }
+    }
+
+    pub fn remaining(&self) -> Quantity {
+        self.quantity.checked_sub(self.filled_quantity).unwrap_or(Quantity::ZERO)
+    }
+
+    // books a fill of `quantity` at `price`, moving the status along
+    pub fn add_fill(&mut self, quantity: Quantity, price: Price) -> Option<()> {
+        let filled = self.filled_quantity.checked_add(quantity)?;
+        let average = match self.average_price {
+            Some(average) => average
+                .value()
+                .checked_mul(self.filled_quantity.value())?
+                .checked_add(price.value().checked_mul(quantity.value())?)?
+                .checked_div(filled.value())?,
+            None => price.value(),
+        };
+        self.filled_quantity = filled;
+        self.average_price = Some(Price::from(average.normalize()));
+        self.status = if self.remaining().is_positive() { OrderStatus::PartiallyFilled } else { OrderStatus::Filled };
+        Some(())
+    }
+}
+
+// an exchange the bot can trade on.
+// market orders execute immediately and return the fill price. limit orders rest on the book
+// until they fill, maybe in parts, or get cancelled; the bot polls them by id.
+#[async_trait]
+pub trait Market: Send + Sync {
+    // free balance of the quote currency
+    async fn get_balance(&self) -> Result<Money, BoxError>;
+    async fn get_market_price(&self) -> Result<Price, BoxError>;
+    async fn place_sell_order(&self, amount: Quantity) -> Result<Price, BoxError>;
+    async fn place_buy_order(&self, amount: Quantity) -> Result<Price, BoxError>;
+    async fn place_limit_order(&self, side: Side, amount: Quantity, price: Price) -> Result<Order, BoxError>;
+    async fn get_order(&self, id: &str) -> Result<Order, BoxError>;
+    // cancels whatever has not filled yet and returns the final state of the order
+    async fn cancel_order(&self, id: &str) -> Result<Order, BoxError>;
+}
+
+// lets callers keep a handle on a market the bot owns, e.g. to read a backtest ledger
+#[async_trait]
+impl<M: Market + ?Sized> Market for Arc<M> {
+    async fn get_balance(&self) -> Result<Money, BoxError> {
+        (**self).get_balance().await
+    }
+
+    async fn get_market_price(&self) -> Result<Price, BoxError> {
+        (**self).get_market_price().await
+    }
+
+    async fn place_sell_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        (**self).place_sell_order(amount).await
+    }
+
+    async fn place_buy_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        (**self).place_buy_order(amount).await
+    }
+
+    async fn place_limit_order(&self, side: Side, amount: Quantity, price: Price) -> Result<Order, BoxError> {
+        (**self).place_limit_order(side, amount, price).await
+    }
+
+    async fn get_order(&self, id: &str) -> Result<Order, BoxError> {
+        (**self).get_order(id).await
+    }
+
+    async fn cancel_order(&self, id: &str) -> Result<Order, BoxError> {
+        (**self).cancel_order(id).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_partial_fills() {
+        let mut order = Order::new("1".to_string(), Side::Buy, "1".parse().unwrap(), "100".parse().unwrap());
+        order.add_fill("0.25".parse().unwrap(), "100".parse().unwrap()).unwrap();
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        order.add_fill("0.75".parse().unwrap(), "96".parse().unwrap()).unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.average_price, Some("97".parse().unwrap()));
+        assert_eq!(order.remaining(), Quantity::ZERO);
+    }
+}
@@ -0,0 +1,70 @@
+use super::{Market, Order, Side, SimulatedMarket, Wallet};
+use crate::money::{Money, Price, Quantity, SymbolRules};
+use crate::BoxError;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+// trades live prices without sending a single order. prices come from the wrapped market, orders
+// go to a simulated ledger instead. the ledger is quoted the live price before every order call,
+// so market orders fill at it and resting limit orders fill once it reaches them.
+pub struct PaperMarket {
+    live: Box<dyn Market>,
+    ledger: SimulatedMarket,
+}
+
+impl PaperMarket {
+    pub fn new(live: Box<dyn Market>, wallet: Arc<Wallet>, rules: SymbolRules) -> Self {
+        PaperMarket { live, ledger: SimulatedMarket::with_wallet(Vec::new(), wallet, rules) }
+    }
+
+    // starts off holding `holdings`, as restored from the paper journal
+    pub fn with_holdings(self, holdings: Quantity) -> Self {
+        PaperMarket { ledger: self.ledger.with_holdings(holdings), ..self }
+    }
+
+    // the simulated side, for its fills and equity
+    pub fn ledger(&self) -> &SimulatedMarket {
+        &self.ledger
+    }
+
+    async fn quote_live(&self) -> Result<Price, BoxError> {
+        let price = self.live.get_market_price().await?;
+        self.ledger.quote(price)?;
+        Ok(price)
+    }
+}
+
+#[async_trait]
+impl Market for PaperMarket {
+    async fn get_balance(&self) -> Result<Money, BoxError> {
+        self.ledger.get_balance().await
+    }
+
+    async fn get_market_price(&self) -> Result<Price, BoxError> {
+        self.quote_live().await
+    }
+
+    async fn place_sell_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        self.quote_live().await?;
+        self.ledger.place_sell_order(amount).await
+    }
+
+    async fn place_buy_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        self.quote_live().await?;
+        self.ledger.place_buy_order(amount).await
+    }
+
+    async fn place_limit_order(&self, side: Side, amount: Quantity, price: Price) -> Result<Order, BoxError> {
+        self.quote_live().await?;
+        self.ledger.place_limit_order(side, amount, price).await
+    }
+
+    async fn get_order(&self, id: &str) -> Result<Order, BoxError> {
+        self.quote_live().await?;
+        self.ledger.get_order(id).await
+    }
+
+    async fn cancel_order(&self, id: &str) -> Result<Order, BoxError> {
+        self.ledger.cancel_order(id).await
+    }
+}
@@ -0,0 +1,260 @@
+use super::{Market, Order, OrderStatus, Side};
+use crate::money::{Money, Price, Quantity};
+use crate::BoxError;
+use async_trait::async_trait;
+use hmac::{Hmac, Mac};
+use reqwest::{Client, Method, StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const API_KEY_HEADER: &str = "X-API-KEY";
+pub const TIMESTAMP_HEADER: &str = "X-TIMESTAMP";
+pub const SIGNATURE_HEADER: &str = "X-SIGNATURE";
+
+// a generic REST exchange:
+//
+//   GET    /api/v1/ticker?symbol=BTCUSD             -> {"symbol": "BTCUSD", "price": "42000.5"}
+//   GET    /api/v1/balance?asset=USD                -> {"asset": "USD", "free": "1000"}   (signed)
+//   POST   /api/v1/order                            -> order                              (signed)
+//   GET    /api/v1/order?symbol=BTCUSD&order_id=1   -> order                              (signed)
+//   DELETE /api/v1/order?symbol=BTCUSD&order_id=1   -> order, cancelled                   (signed)
+//
+// an order is {"order_id": "1", "side": "buy", "status": "partially_filled", "quantity": "0.02",
+// "filled_quantity": "0.01", "price": "42000.5", "limit_price": "42001"}, price being the average
+// fill price or null before any fill. market orders have no limit_price.
+// failures come back as a non 2xx status with {"code": "...", "message": "..."}.
+// amounts travel as decimal strings so nothing gets rounded through a float on the way.
+pub struct RestMarket {
+    client: Client,
+    base_url: String,
+    symbol: String,
+    quote_asset: String,
+    api_key: String,
+    api_secret: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Ticker {
+    pub symbol: String,
+    pub price: Price,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Balance {
+    pub asset: String,
+    pub free: Money,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OrderRequest<'a> {
+    pub symbol: &'a str,
+    pub side: Side,
+    #[serde(rename = "type")]
+    pub order_type: &'a str,
+    pub quantity: Quantity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price: Option<Price>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OrderResponse {
+    pub order_id: String,
+    pub side: Side,
+    pub status: OrderStatus,
+    pub quantity: Quantity,
+    pub filled_quantity: Quantity,
+    // average fill price
+    pub price: Option<Price>,
+    pub limit_price: Option<Price>,
+}
+
+impl From<OrderResponse> for Order {
+    fn from(response: OrderResponse) -> Order {
+        Order {
+            id: response.order_id,
+            side: response.side,
+            quantity: response.quantity,
+            // a market order has no limit, its fill price stands in for one
+            limit_price: response.limit_price.or(response.price).unwrap_or(Price::ZERO),
+            filled_quantity: response.filled_quantity,
+            average_price: response.price,
+            status: response.status,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiError {
+    pub code: String,
+    pub message: String,
+}
+
+// hex HMAC-SHA256 of timestamp, method, path with query and body, concatenated
+pub fn sign(secret: &str, timestamp: u64, method: &str, path_and_query: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(method.as_bytes());
+    mac.update(path_and_query.as_bytes());
+    mac.update(body.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+impl RestMarket {
+    pub fn new(base_url: &str, symbol: &str, quote_asset: &str, api_key: &str, api_secret: &str) -> Self {
+        RestMarket {
+            client: client(Duration::from_secs(10)),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            symbol: symbol.to_string(),
+            quote_asset: quote_asset.to_string(),
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        }
+    }
+
+    // how long a request may take in all, 10 seconds by default
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.client = client(timeout);
+        self
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path_and_query: &str,
+        body: Option<String>,
+        signed: bool,
+    ) -> Result<T, BoxError> {
+        let url = format!("{}{}", self.base_url, path_and_query);
+        let mut request = self.client.request(method.clone(), &url);
+        if signed {
+            let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as u64;
+            let signature =
+                sign(&self.api_secret, timestamp, method.as_str(), path_and_query, body.as_deref().unwrap_or(""));
+            request = request
+                .header(API_KEY_HEADER, &self.api_key)
+                .header(TIMESTAMP_HEADER, timestamp)
+                .header(SIGNATURE_HEADER, signature);
+        }
+        if let Some(body) = body {
+            request = request.header(reqwest::header::CONTENT_TYPE, "application/json").body(body);
+        }
+
+        let response = request.send().await.map_err(|e| format!("{} {} failed: {}", method, path_and_query, e))?;
+        let status = response.status();
+        let text = response.text().await?;
+        if !status.is_success() {
+            return Err(api_error(status, &text).into());
+        }
+        serde_json::from_str(&text).map_err(|e| format!("{} {}: unexpected response: {}", method, path_and_query, e).into())
+    }
+
+    async fn place_order(&self, side: Side, quantity: Quantity, price: Option<Price>) -> Result<Order, BoxError> {
+        let order_type = if price.is_some() { "limit" } else { "market" };
+        let order = OrderRequest { symbol: &self.symbol, side, order_type, quantity, price };
+        let body = serde_json::to_string(&order)?;
+        let response: OrderResponse = self.request(Method::POST, "/api/v1/order", Some(body), true).await?;
+        Ok(response.into())
+    }
+
+    async fn place_market_order(&self, side: Side, quantity: Quantity) -> Result<Price, BoxError> {
+        let order = self.place_order(side, quantity, None).await?;
+        match (order.status, order.average_price) {
+            (OrderStatus::Filled, Some(price)) => Ok(price),
+            (status, _) => Err(format!("order {} was not filled: {:?}", order.id, status).into()),
+        }
+    }
+
+    fn order_path(&self, id: &str) -> String {
+        with_query("/api/v1/order", &[("symbol", &self.symbol), ("order_id", id)])
+    }
+}
+
+// `path` with the pairs as its query, percent-encoded. the URL is sent as built here, so what is
+// signed is what the exchange receives.
+fn with_query(path: &str, pairs: &[(&str, &str)]) -> String {
+    let mut url = Url::parse("http://localhost").expect("a valid URL");
+    url.query_pairs_mut().extend_pairs(pairs);
+    format!("{}?{}", path, url.query().unwrap_or(""))
+}
+
+// a hung exchange fails the cycle, as a down one would, instead of stalling it for good
+fn client(timeout: Duration) -> Client {
+    Client::builder().timeout(timeout).build().unwrap_or_default()
+}
+
+fn api_error(status: StatusCode, body: &str) -> String {
+    match serde_json::from_str::<ApiError>(body) {
+        Ok(error) => format!("exchange returned {}: {} ({})", status, error.message, error.code),
+        Err(_) => format!("exchange returned {}: {}", status, body),
+    }
+}
+
+#[async_trait]
+impl Market for RestMarket {
+    async fn get_balance(&self) -> Result<Money, BoxError> {
+        let path = with_query("/api/v1/balance", &[("asset", &self.quote_asset)]);
+        let balance: Balance = self.request(Method::GET, &path, None, true).await?;
+        Ok(balance.free)
+    }
+
+    async fn get_market_price(&self) -> Result<Price, BoxError> {
+        let path = with_query("/api/v1/ticker", &[("symbol", &self.symbol)]);
+        let ticker: Ticker = self.request(Method::GET, &path, None, false).await?;
+        Ok(ticker.price)
+    }
+
+    async fn place_sell_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        self.place_market_order(Side::Sell, amount).await
+    }
+
+    async fn place_buy_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        self.place_market_order(Side::Buy, amount).await
+    }
+
+    async fn place_limit_order(&self, side: Side, amount: Quantity, price: Price) -> Result<Order, BoxError> {
+        self.place_order(side, amount, Some(price)).await
+    }
+
+    async fn get_order(&self, id: &str) -> Result<Order, BoxError> {
+        let response: OrderResponse = self.request(Method::GET, &self.order_path(id), None, true).await?;
+        Ok(response.into())
+    }
+
+    async fn cancel_order(&self, id: &str) -> Result<Order, BoxError> {
+        let response: OrderResponse = self.request(Method::DELETE, &self.order_path(id), None, true).await?;
+        Ok(response.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_query_values() {
+        assert_eq!(with_query("/api/v1/ticker", &[("symbol", "BTCUSD")]), "/api/v1/ticker?symbol=BTCUSD");
+        let path = with_query("/api/v1/order", &[("symbol", "BTC/USD"), ("order_id", "a&b=c d")]);
+        assert_eq!(path, "/api/v1/order?symbol=BTC%2FUSD&order_id=a%26b%3Dc+d");
+    }
+
+    #[test]
+    fn signature_matches_reference() {
+        // echo -n '1700000000000GET/api/v1/balance?asset=USD' | openssl dgst -sha256 -hmac secret
+        assert_eq!(
+            sign("secret", 1_700_000_000_000, "GET", "/api/v1/balance?asset=USD", ""),
+            "393441ebcfc626a9396f3a1fbe6ae3e23d0a5334faf326ce6ee82a32365b5f5f"
+        );
+    }
+
+    #[test]
+    fn formats_api_errors() {
+        let body = r#"{"code": "insufficient_balance", "message": "not enough USD"}"#;
+        assert_eq!(
+            api_error(StatusCode::BAD_REQUEST, body),
+            "exchange returned 400 Bad Request: not enough USD (insufficient_balance)"
+        );
+        assert_eq!(api_error(StatusCode::BAD_GATEWAY, "oops"), "exchange returned 502 Bad Gateway: oops");
+    }
+}
@@ -0,0 +1,325 @@
+use super::{Market, Order, OrderStatus, Side};
+use crate::money::{Money, Price, Quantity, SymbolRules};
+use crate::BoxError;
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex};
+
+// an in-memory exchange replaying a fixed list of prices.
+// every call to get_market_price moves to the next price, unless the prices come from outside
+// through quote. market orders fill at the last quoted one and are checked against the symbol
+// rules like a real exchange would.
+// a limit order fills right away at the last price if it crosses it, otherwise it rests and fills
+// at its limit once a later price reaches it. funds and holdings an open order may need are locked
+// until it fills or is cancelled.
+pub struct SimulatedMarket {
+    rules: SymbolRules,
+    wallet: Arc<Wallet>,
+    ledger: Mutex<Ledger>,
+}
+
+// the quote currency balance, shared by the simulated markets of a portfolio
+#[derive(Debug)]
+pub struct Wallet {
+    balance: Mutex<Money>,
+}
+
+impl Wallet {
+    pub fn new(balance: Money) -> Self {
+        Wallet { balance: Mutex::new(balance) }
+    }
+
+    pub fn balance(&self) -> Money {
+        *self.balance.lock().unwrap()
+    }
+
+    fn add(&self, amount: Money) -> Result<(), BoxError> {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = balance.checked_add(amount).ok_or("balance overflowed")?;
+        Ok(())
+    }
+
+    fn take(&self, amount: Money) -> Result<(), BoxError> {
+        let mut balance = self.balance.lock().unwrap();
+        *balance = balance.checked_sub(amount).filter(|b| *b >= Money::ZERO).ok_or("insufficient balance")?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fill {
+    pub side: Side,
+    pub price: Price,
+    pub quantity: Quantity,
+}
+
+#[derive(Debug)]
+struct Ledger {
+    prices: Vec<Price>,
+    next_price: usize,
+    last_price: Option<Price>,
+    holdings: Quantity,
+    fills: Vec<Fill>,
+    orders: Vec<Order>,
+    // most a limit order can fill per quoted price, unlimited if none
+    liquidity: Option<Quantity>,
+}
+
+impl Ledger {
+    fn last_price(&self) -> Result<Price, BoxError> {
+        self.last_price.ok_or_else(|| "no price has been quoted yet".into())
+    }
+
+    // holdings not locked by open sell orders
+    fn free_holdings(&self) -> Quantity {
+        self.orders
+            .iter()
+            .filter(|order| order.side == Side::Sell && !order.status.is_done())
+            .fold(self.holdings, |free, order| free.checked_sub(order.remaining()).unwrap_or(free))
+    }
+
+    // quote currency locked by open buy orders
+    fn locked_funds(&self) -> Money {
+        self.orders
+            .iter()
+            .filter(|order| order.side == Side::Buy && !order.status.is_done())
+            .filter_map(|order| order.limit_price.checked_notional(order.remaining()))
+            .fold(Money::ZERO, |sum, funds| sum.checked_add(funds).unwrap_or(sum))
+    }
+
+    fn order(&mut self, id: &str) -> Result<&mut Order, BoxError> {
+        self.orders.iter_mut().find(|order| order.id == id).ok_or_else(|| format!("unknown order {}", id).into())
+    }
+
+    // fills as much of order `idx` as `price` allows, settling with the wallet
+    fn fill(&mut self, idx: usize, price: Price, wallet: &Wallet) -> Result<(), BoxError> {
+        let order = &self.orders[idx];
+        let quantity = match self.liquidity {
+            Some(liquidity) => order.remaining().min(liquidity),
+            None => order.remaining(),
+        };
+        if !quantity.is_positive() {
+            return Ok(());
+        }
+        let notional = price.checked_notional(quantity).ok_or("order value overflowed")?;
+        match order.side {
+            Side::Buy => {
+                // the funds were locked at the limit price, anything cheaper comes back
+                let locked = order.limit_price.checked_notional(quantity).ok_or("order value overflowed")?;
+                wallet.add(locked.checked_sub(notional).ok_or("order value overflowed")?)?;
+                self.holdings = self.holdings.checked_add(quantity).ok_or("holdings overflowed")?;
+            }
+            Side::Sell => {
+                wallet.add(notional)?;
+                self.holdings = self.holdings.checked_sub(quantity).ok_or("holdings overflowed")?;
+            }
+        }
+        let order = &mut self.orders[idx];
+        order.add_fill(quantity, price).ok_or("order fill overflowed")?;
+        self.fills.push(Fill { side: order.side, price, quantity });
+        Ok(())
+    }
+}
+
+fn crosses(order: &Order, price: Price) -> bool {
+    match order.side {
+        Side::Buy => price <= order.limit_price,
+        Side::Sell => price >= order.limit_price,
+    }
+}
+
+impl SimulatedMarket {
+    pub fn new(prices: Vec<Price>, balance: Money, rules: SymbolRules) -> Self {
+        SimulatedMarket::with_wallet(prices, Arc::new(Wallet::new(balance)), rules)
+    }
+
+    pub fn with_wallet(prices: Vec<Price>, wallet: Arc<Wallet>, rules: SymbolRules) -> Self {
+        let ledger = Ledger {
+            prices,
+            next_price: 0,
+            last_price: None,
+            holdings: Quantity::ZERO,
+            fills: Vec::new(),
+            orders: Vec::new(),
+            liquidity: None,
+        };
+        SimulatedMarket { rules, wallet, ledger: Mutex::new(ledger) }
+    }
+
+    // caps how much a limit order fills per quoted price, to play out partial fills
+    pub fn with_liquidity(self, liquidity: Quantity) -> Self {
+        self.ledger.lock().unwrap().liquidity = Some(liquidity);
+        self
+    }
+
+    // starts off holding `holdings`, as restored from a journal
+    pub fn with_holdings(self, holdings: Quantity) -> Self {
+        self.ledger.lock().unwrap().holdings = holdings;
+        self
+    }
+
+    // moves the market to `price` as if it had been quoted next. resting orders the price reaches
+    // fill at their limit.
+    pub fn quote(&self, price: Price) -> Result<(), BoxError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        ledger.last_price = Some(price);
+        for idx in 0..ledger.orders.len() {
+            let order = &ledger.orders[idx];
+            if !order.status.is_done() && crosses(order, price) {
+                let limit = order.limit_price;
+                ledger.fill(idx, limit, &self.wallet)?;
+            }
+        }
+        Ok(())
+    }
+
+    // base asset bought and not yet sold
+    pub fn holdings(&self) -> Quantity {
+        self.ledger.lock().unwrap().holdings
+    }
+
+    pub fn fills(&self) -> Vec<Fill> {
+        self.ledger.lock().unwrap().fills.clone()
+    }
+
+    // balance plus holdings valued at the last quoted price, open orders included
+    pub fn equity(&self) -> Option<Money> {
+        let ledger = self.ledger.lock().unwrap();
+        let price = ledger.last_price().ok()?;
+        let balance = self.wallet.balance().checked_add(ledger.locked_funds())?;
+        balance.checked_add(price.checked_notional(ledger.holdings)?)
+    }
+}
+
+#[async_trait]
+impl Market for SimulatedMarket {
+    async fn get_balance(&self) -> Result<Money, BoxError> {
+        Ok(self.wallet.balance())
+    }
+
+    async fn get_market_price(&self) -> Result<Price, BoxError> {
+        let price = {
+            let mut ledger = self.ledger.lock().unwrap();
+            let price = *ledger.prices.get(ledger.next_price).ok_or("price feed exhausted")?;
+            ledger.next_price += 1;
+            price
+        };
+        self.quote(price)?;
+        Ok(price)
+    }
+
+    async fn place_sell_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let price = ledger.last_price()?;
+        self.rules.check_order(amount, price)?;
+        if ledger.free_holdings() < amount {
+            return Err("insufficient holdings".into());
+        }
+        let proceeds = price.checked_notional(amount).ok_or("order value overflowed")?;
+        self.wallet.add(proceeds)?;
+        ledger.holdings = ledger.holdings.checked_sub(amount).ok_or("holdings overflowed")?;
+        ledger.fills.push(Fill { side: Side::Sell, price, quantity: amount });
+        Ok(price)
+    }
+
+    async fn place_buy_order(&self, amount: Quantity) -> Result<Price, BoxError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let price = ledger.last_price()?;
+        self.rules.check_order(amount, price)?;
+        let cost = price.checked_notional(amount).ok_or("order value overflowed")?;
+        let holdings = ledger.holdings.checked_add(amount).ok_or("holdings overflowed")?;
+        self.wallet.take(cost)?;
+        ledger.holdings = holdings;
+        ledger.fills.push(Fill { side: Side::Buy, price, quantity: amount });
+        Ok(price)
+    }
+
+    async fn place_limit_order(&self, side: Side, amount: Quantity, price: Price) -> Result<Order, BoxError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let last_price = ledger.last_price()?;
+        self.rules.check_order(amount, price)?;
+        match side {
+            Side::Buy => self.wallet.take(price.checked_notional(amount).ok_or("order value overflowed")?)?,
+            Side::Sell if ledger.free_holdings() < amount => return Err("insufficient holdings".into()),
+            Side::Sell => {}
+        }
+
+        let id = format!("sim-{}", ledger.orders.len() + 1);
+        ledger.orders.push(Order::new(id, side, amount, price));
+        let idx = ledger.orders.len() - 1;
+        if crosses(&ledger.orders[idx], last_price) {
+            ledger.fill(idx, last_price, &self.wallet)?;
+        }
+        Ok(ledger.orders[idx].clone())
+    }
+
+    async fn get_order(&self, id: &str) -> Result<Order, BoxError> {
+        Ok(self.ledger.lock().unwrap().order(id)?.clone())
+    }
+
+    async fn cancel_order(&self, id: &str) -> Result<Order, BoxError> {
+        let mut ledger = self.ledger.lock().unwrap();
+        let order = ledger.order(id)?;
+        if order.status.is_done() {
+            return Ok(order.clone());
+        }
+        if order.side == Side::Buy {
+            let locked = order.limit_price.checked_notional(order.remaining()).ok_or("order value overflowed")?;
+            self.wallet.add(locked)?;
+        }
+        order.status = OrderStatus::Cancelled;
+        Ok(order.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market(prices: &[&str]) -> SimulatedMarket {
+        let prices = prices.iter().map(|p| p.parse().unwrap()).collect();
+        SimulatedMarket::new(prices, "1000".parse().unwrap(), SymbolRules::default())
+    }
+
+    #[tokio::test]
+    async fn rests_limit_orders_until_the_price_reaches_them() {
+        let market = market(&["100", "99", "97", "98"]).with_liquidity("3".parse().unwrap());
+        market.get_market_price().await.unwrap();
+
+        let order = market.place_limit_order(Side::Buy, "5".parse().unwrap(), "98".parse().unwrap()).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Open);
+        // 490 are locked, but still count as equity
+        assert_eq!(market.get_balance().await.unwrap(), "510".parse().unwrap());
+        assert_eq!(market.equity(), Some("1000".parse().unwrap()));
+
+        market.get_market_price().await.unwrap();
+        assert_eq!(market.get_order(&order.id).await.unwrap().status, OrderStatus::Open);
+        market.get_market_price().await.unwrap();
+        let order = market.get_order(&order.id).await.unwrap();
+        assert_eq!(order.status, OrderStatus::PartiallyFilled);
+        assert_eq!(order.filled_quantity, "3".parse().unwrap());
+        assert_eq!(order.average_price, Some("98".parse().unwrap()));
+
+        let order = market.cancel_order(&order.id).await.unwrap();
+        assert_eq!(order.status, OrderStatus::Cancelled);
+        assert_eq!(market.holdings(), "3".parse().unwrap());
+        assert_eq!(market.get_balance().await.unwrap(), "706".parse().unwrap());
+        // nothing fills after a cancel
+        market.get_market_price().await.unwrap();
+        assert_eq!(market.get_order(&order.id).await.unwrap().filled_quantity, "3".parse().unwrap());
+    }
+
+    #[tokio::test]
+    async fn fills_crossing_limit_orders_at_the_last_price() {
+        let market = market(&["100"]);
+        market.get_market_price().await.unwrap();
+        let order = market.place_limit_order(Side::Buy, "2".parse().unwrap(), "101".parse().unwrap()).await.unwrap();
+        assert_eq!((order.status, order.average_price), (OrderStatus::Filled, Some("100".parse().unwrap())));
+        assert_eq!(market.get_balance().await.unwrap(), "800".parse().unwrap());
+
+        let error = market.place_limit_order(Side::Sell, "3".parse().unwrap(), "105".parse().unwrap()).await.unwrap_err();
+        assert_eq!(error.to_string(), "insufficient holdings");
+        market.place_limit_order(Side::Sell, "2".parse().unwrap(), "105".parse().unwrap()).await.unwrap();
+        // the open sell order holds on to everything
+        assert!(market.place_sell_order("1".parse().unwrap()).await.is_err());
+    }
+}
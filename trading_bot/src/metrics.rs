@@ -0,0 +1,223 @@
+use crate::market::Side;
+use crate::money::Money;
+use crate::portfolio::SymbolStatus;
+use crate::BoxError;
+use axum::extract::State;
+use axum::http::header::CONTENT_TYPE;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{Encoder, Gauge, GaugeVec, HistogramOpts, HistogramVec, IntCounterVec, Opts, Registry, TextEncoder};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpListener;
+
+// everything the bots report to Prometheus, shared by the bots of a portfolio.
+// amounts are in the quote currency and lose precision on the way to f64, which is fine for
+// dashboards but nothing else.
+pub struct Metrics {
+    registry: Registry,
+    balance: Gauge,
+    equity: Gauge,
+    position: GaugeVec,
+    position_value: GaugeVec,
+    last_price: GaugeVec,
+    realized_pnl: GaugeVec,
+    cycles: IntCounterVec,
+    signals: IntCounterVec,
+    orders_placed: IntCounterVec,
+    orders_failed: IntCounterVec,
+    orders_closed: IntCounterVec,
+    fills: IntCounterVec,
+    market_calls: HistogramVec,
+    // position values by symbol, equity is the balance plus all of them
+    position_values: Mutex<BTreeMap<String, f64>>,
+}
+
+fn float(value: Decimal) -> f64 {
+    value.to_f64().unwrap_or(f64::NAN)
+}
+
+fn side_label(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "buy",
+        Side::Sell => "sell",
+    }
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let gauge = |name: &str, help: &str| Gauge::new(name, help).expect("valid metric");
+        let gauges = |name: &str, help: &str| GaugeVec::new(Opts::new(name, help), &["symbol"]).expect("valid metric");
+        let counters =
+            |name: &str, help: &str, labels: &[&str]| IntCounterVec::new(Opts::new(name, help), labels).expect("valid metric");
+        let market_calls = HistogramVec::new(
+            HistogramOpts::new("trading_bot_market_call_duration_seconds", "latency of market calls")
+                .buckets(vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]),
+            &["symbol", "call", "outcome"],
+        )
+        .expect("valid metric");
+
+        let metrics = Metrics {
+            registry: Registry::new(),
+            balance: gauge("trading_bot_balance", "free quote currency balance, as of the last balance call"),
+            equity: gauge("trading_bot_equity", "balance plus every open position at its last price"),
+            position: gauges("trading_bot_position", "base asset held"),
+            position_value: gauges("trading_bot_position_value", "position valued at the last price"),
+            last_price: gauges("trading_bot_last_price", "last price seen"),
+            realized_pnl: gauges("trading_bot_realized_pnl", "realized profit and loss since the start"),
+            cycles: counters("trading_bot_cycles_total", "price updates handled", &["symbol", "outcome"]),
+            signals: counters("trading_bot_signals_total", "strategy and risk signals", &["symbol", "signal"]),
+            orders_placed: counters("trading_bot_orders_placed_total", "orders accepted", &["symbol", "side", "type"]),
+            orders_failed: counters("trading_bot_orders_failed_total", "orders refused", &["symbol", "side", "type"]),
+            orders_closed: counters("trading_bot_orders_closed_total", "limit orders done", &["symbol", "status"]),
+            fills: counters("trading_bot_fills_total", "orders booked into the position", &["symbol", "side"]),
+            market_calls,
+            position_values: Mutex::new(BTreeMap::new()),
+        };
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(metrics.balance.clone()),
+            Box::new(metrics.equity.clone()),
+            Box::new(metrics.position.clone()),
+            Box::new(metrics.position_value.clone()),
+            Box::new(metrics.last_price.clone()),
+            Box::new(metrics.realized_pnl.clone()),
+            Box::new(metrics.cycles.clone()),
+            Box::new(metrics.signals.clone()),
+            Box::new(metrics.orders_placed.clone()),
+            Box::new(metrics.orders_failed.clone()),
+            Box::new(metrics.orders_closed.clone()),
+            Box::new(metrics.fills.clone()),
+            Box::new(metrics.market_calls.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).expect("metric names are unique");
+        }
+        // unknown until the first balance call, rather than an empty account
+        metrics.balance.set(f64::NAN);
+        metrics.equity.set(f64::NAN);
+        metrics
+    }
+
+    pub fn set_balance(&self, balance: Money) {
+        self.balance.set(float(balance.value()));
+        self.update_equity();
+    }
+
+    pub fn set_status(&self, status: &SymbolStatus) {
+        let symbol = [status.symbol.as_str()];
+        let value = float(status.position_value().value());
+        self.position.with_label_values(&symbol).set(float(status.position.value()));
+        self.position_value.with_label_values(&symbol).set(value);
+        if let Some(price) = status.last_price {
+            self.last_price.with_label_values(&symbol).set(float(price.value()));
+        }
+        self.realized_pnl.with_label_values(&symbol).set(float(status.realized_pnl.value()));
+        self.position_values.lock().unwrap().insert(status.symbol.clone(), value);
+        self.update_equity();
+    }
+
+    fn update_equity(&self) {
+        let positions: f64 = self.position_values.lock().unwrap().values().sum();
+        self.equity.set(self.balance.get() + positions);
+    }
+
+    pub fn cycle(&self, symbol: &str, ok: bool) {
+        self.cycles.with_label_values(&[symbol, if ok { "ok" } else { "error" }]).inc();
+    }
+
+    pub fn signal(&self, symbol: &str, signal: &str) {
+        self.signals.with_label_values(&[symbol, signal]).inc();
+    }
+
+    pub fn order_placed(&self, symbol: &str, side: Side, order_type: &str, ok: bool) {
+        let counter = if ok { &self.orders_placed } else { &self.orders_failed };
+        counter.with_label_values(&[symbol, side_label(side), order_type]).inc();
+    }
+
+    pub fn order_closed(&self, symbol: &str, status: &str) {
+        self.orders_closed.with_label_values(&[symbol, status]).inc();
+    }
+
+    pub fn fill(&self, symbol: &str, side: Side) {
+        self.fills.with_label_values(&[symbol, side_label(side)]).inc();
+    }
+
+    pub fn market_call(&self, symbol: &str, call: &str, elapsed: Duration, ok: bool) {
+        let outcome = if ok { "ok" } else { "error" };
+        self.market_calls.with_label_values(&[symbol, call, outcome]).observe(elapsed.as_secs_f64());
+    }
+
+    // the Prometheus text format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer).expect("text encoding does not fail");
+        String::from_utf8(buffer).expect("the text format is UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Metrics::new()
+    }
+}
+
+// serves GET /metrics until the listener fails
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>) -> Result<(), BoxError> {
+    let app = Router::new().route("/metrics", get(scrape)).with_state(metrics);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn scrape(State(metrics): State<Arc<Metrics>>) -> ([(axum::http::HeaderName, &'static str); 1], String) {
+    ([(CONTENT_TYPE, prometheus::TEXT_FORMAT)], metrics.render())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::State;
+
+    #[test]
+    fn adds_positions_to_the_balance() {
+        let metrics = Metrics::new();
+        metrics.set_balance("600".parse().unwrap());
+        metrics.set_status(&SymbolStatus {
+            symbol: "BTCUSD".to_string(),
+            last_price: Some("40000".parse().unwrap()),
+            next_operation: State::Sell,
+            position: "0.01".parse().unwrap(),
+            realized_pnl: "-2.5".parse().unwrap(),
+            trades: 1,
+        });
+        metrics.fill("BTCUSD", Side::Buy);
+        metrics.market_call("BTCUSD", "place_buy_order", Duration::from_millis(30), true);
+
+        let text = metrics.render();
+        assert!(text.contains("trading_bot_equity 1000\n"), "{}", text);
+        assert!(text.contains("trading_bot_position_value{symbol=\"BTCUSD\"} 400\n"), "{}", text);
+        assert!(text.contains("trading_bot_realized_pnl{symbol=\"BTCUSD\"} -2.5\n"), "{}", text);
+        assert!(text.contains("trading_bot_fills_total{side=\"buy\",symbol=\"BTCUSD\"} 1\n"), "{}", text);
+        assert!(
+            text.contains("trading_bot_market_call_duration_seconds_bucket{call=\"place_buy_order\",outcome=\"ok\",symbol=\"BTCUSD\",le=\"0.05\"} 1\n"),
+            "{}",
+            text
+        );
+    }
+
+    #[tokio::test]
+    async fn serves_the_text_format() {
+        let metrics = Arc::new(Metrics::new());
+        metrics.cycle("ETHUSD", true);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/metrics", listener.local_addr().unwrap());
+        tokio::spawn(serve(listener, metrics));
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.headers()[CONTENT_TYPE.as_str()], prometheus::TEXT_FORMAT);
+        let text = response.text().await.unwrap();
+        assert!(text.contains("trading_bot_cycles_total{outcome=\"ok\",symbol=\"ETHUSD\"} 1\n"), "{}", text);
+    }
+}
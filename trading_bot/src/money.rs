@@ -0,0 +1,272 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+// prices, balances and amounts are fixed-point decimals, never floats.
+// each newtype only exposes the arithmetic that makes sense for it and every operation is checked:
+// None means overflow (or division by zero) instead of a silently wrong number.
+macro_rules! decimal_newtype {
+    ($name:ident) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Serialize, Deserialize)]
+        #[serde(transparent)]
+        pub struct $name(Decimal);
+
+        impl $name {
+            pub const ZERO: $name = $name(Decimal::ZERO);
+
+            pub fn new(value: Decimal) -> Self {
+                $name(value)
+            }
+
+            pub fn value(self) -> Decimal {
+                self.0
+            }
+
+            pub fn is_zero(self) -> bool {
+                self.0.is_zero()
+            }
+
+            pub fn is_positive(self) -> bool {
+                self.0 > Decimal::ZERO
+            }
+
+            pub fn checked_add(self, other: $name) -> Option<$name> {
+                self.0.checked_add(other.0).map($name)
+            }
+
+            pub fn checked_sub(self, other: $name) -> Option<$name> {
+                self.0.checked_sub(other.0).map($name)
+            }
+
+            // scale by a plain factor, e.g. 0.25 of a balance
+            pub fn checked_scale(self, factor: Decimal) -> Option<$name> {
+                self.0.checked_mul(factor).map($name)
+            }
+
+            // change from `reference` to this value in percent, e.g. 2.5 for +2.5%
+            pub fn percentage_change_from(self, reference: $name) -> Option<Decimal> {
+                self.0
+                    .checked_sub(reference.0)?
+                    .checked_div(reference.0)?
+                    .checked_mul(Decimal::ONE_HUNDRED)
+            }
+        }
+
+        impl From<Decimal> for $name {
+            fn from(value: Decimal) -> Self {
+                $name(value)
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = rust_decimal::Error;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                Decimal::from_str(s).map($name)
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0.normalize())
+            }
+        }
+    };
+}
+
+// quote currency per unit of the base asset, e.g. USD per BTC
+decimal_newtype!(Price);
+// amount of the base asset, e.g. BTC
+decimal_newtype!(Quantity);
+// amount of the quote currency, e.g. USD
+decimal_newtype!(Money);
+
+impl Price {
+    // value of `quantity` units at this price
+    pub fn checked_notional(self, quantity: Quantity) -> Option<Money> {
+        self.0.checked_mul(quantity.0).map(Money)
+    }
+}
+
+impl Money {
+    // how many units this amount buys at `price`, before any lot-size rounding
+    pub fn checked_div_price(self, price: Price) -> Option<Quantity> {
+        self.0.checked_div(price.0).map(Quantity)
+    }
+}
+
+// round `value` down to a multiple of `step`; a zero step means no constraint
+fn floor_to_step(value: Decimal, step: Decimal) -> Option<Decimal> {
+    if step.is_zero() {
+        return Some(value);
+    }
+    value.checked_div(step)?.floor().checked_mul(step)
+}
+
+fn ceil_to_step(value: Decimal, step: Decimal) -> Option<Decimal> {
+    if step.is_zero() {
+        return Some(value);
+    }
+    value.checked_div(step)?.ceil().checked_mul(step)
+}
+
+// exchange filters for one symbol: orders that break them get rejected
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SymbolRules {
+    pub tick_size: Price,
+    pub lot_size: Quantity,
+    pub min_quantity: Quantity,
+    pub min_notional: Money,
+}
+
+impl Default for SymbolRules {
+    // BTC/USD style filters
+    fn default() -> Self {
+        SymbolRules {
+            tick_size: Price::new(Decimal::new(1, 2)),
+            lot_size: Quantity::new(Decimal::new(1, 5)),
+            min_quantity: Quantity::new(Decimal::new(1, 5)),
+            min_notional: Money::new(Decimal::TEN),
+        }
+    }
+}
+
+impl SymbolRules {
+    pub fn floor_price(&self, price: Price) -> Option<Price> {
+        floor_to_step(price.0, self.tick_size.0).map(Price)
+    }
+
+    pub fn ceil_price(&self, price: Price) -> Option<Price> {
+        ceil_to_step(price.0, self.tick_size.0).map(Price)
+    }
+
+    pub fn floor_quantity(&self, quantity: Quantity) -> Option<Quantity> {
+        floor_to_step(quantity.0, self.lot_size.0).map(Quantity)
+    }
+
+    // the largest lot-aligned quantity `funds` can pay for at `price`.
+    // the price is rounded up to the next tick so the order never costs more than the funds.
+    pub fn quantity_for(&self, funds: Money, price: Price) -> Result<Quantity, OrderRuleError> {
+        if !price.is_positive() {
+            return Err(OrderRuleError::InvalidPrice(price));
+        }
+        let price = self.ceil_price(price).ok_or(OrderRuleError::Overflow)?;
+        let quantity = funds
+            .checked_div_price(price)
+            .and_then(|quantity| self.floor_quantity(quantity))
+            .ok_or(OrderRuleError::Overflow)?;
+        self.check_order(quantity, price)?;
+        Ok(quantity)
+    }
+
+    // validate an order the same way the exchange would
+    pub fn check_order(&self, quantity: Quantity, price: Price) -> Result<(), OrderRuleError> {
+        if !price.is_positive() || self.floor_price(price) != Some(price) {
+            return Err(OrderRuleError::InvalidPrice(price));
+        }
+        if self.floor_quantity(quantity) != Some(quantity) {
+            return Err(OrderRuleError::NotLotAligned { quantity, lot_size: self.lot_size });
+        }
+        if quantity < self.min_quantity || !quantity.is_positive() {
+            return Err(OrderRuleError::BelowMinQuantity { quantity, min_quantity: self.min_quantity });
+        }
+        let notional = price.checked_notional(quantity).ok_or(OrderRuleError::Overflow)?;
+        if notional < self.min_notional {
+            return Err(OrderRuleError::BelowMinNotional { notional, min_notional: self.min_notional });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OrderRuleError {
+    Overflow,
+    InvalidPrice(Price),
+    NotLotAligned { quantity: Quantity, lot_size: Quantity },
+    BelowMinQuantity { quantity: Quantity, min_quantity: Quantity },
+    BelowMinNotional { notional: Money, min_notional: Money },
+}
+
+impl fmt::Display for OrderRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderRuleError::Overflow => write!(f, "order amount overflowed"),
+            OrderRuleError::InvalidPrice(price) => write!(f, "price {} is not a positive multiple of the tick size", price),
+            OrderRuleError::NotLotAligned { quantity, lot_size } => {
+                write!(f, "quantity {} is not a multiple of the lot size {}", quantity, lot_size)
+            }
+            OrderRuleError::BelowMinQuantity { quantity, min_quantity } => {
+                write!(f, "quantity {} is below the minimum {}", quantity, min_quantity)
+            }
+            OrderRuleError::BelowMinNotional { notional, min_notional } => {
+                write!(f, "order value {} is below the minimum {}", notional, min_notional)
+            }
+        }
+    }
+}
+
+impl Error for OrderRuleError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn arithmetic_is_exact() {
+        let price: Price = "0.1".parse().unwrap();
+        let notional = price.checked_notional(Quantity::new(d("3"))).unwrap();
+        assert_eq!(notional, Money::new(d("0.3")));
+        assert_eq!(Money::new(d("0.1")).checked_add(Money::new(d("0.2"))), Some(Money::new(d("0.3"))));
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        let max = Money::new(Decimal::MAX);
+        assert_eq!(max.checked_add(Money::new(Decimal::ONE)), None);
+        assert_eq!(Money::new(Decimal::ONE).checked_div_price(Price::ZERO), None);
+    }
+
+    #[test]
+    fn percentage_change() {
+        let change = Price::new(d("102")).percentage_change_from(Price::new(d("100")));
+        assert_eq!(change, Some(d("2")));
+        assert_eq!(Price::new(d("1")).percentage_change_from(Price::ZERO), None);
+    }
+
+    #[test]
+    fn quantity_respects_lot_size() {
+        let rules = SymbolRules::default();
+        let quantity = rules.quantity_for(Money::new(d("1000")), Price::new(d("43210.123"))).unwrap();
+        // the price is rounded up to 43210.13 before sizing
+        assert_eq!(quantity, Quantity::new(d("0.02314")));
+        assert!(rules.check_order(quantity, Price::new(d("43210.13"))).is_ok());
+    }
+
+    #[test]
+    fn rejects_orders_breaking_rules() {
+        let rules = SymbolRules::default();
+        let price = Price::new(d("40000"));
+        assert_eq!(
+            rules.quantity_for(Money::new(d("0.2")), price),
+            Err(OrderRuleError::BelowMinQuantity { quantity: Quantity::ZERO, min_quantity: rules.min_quantity })
+        );
+        assert!(matches!(
+            rules.check_order(Quantity::new(d("0.000015")), price),
+            Err(OrderRuleError::NotLotAligned { .. })
+        ));
+        assert!(matches!(
+            rules.check_order(Quantity::new(d("0.0002")), price),
+            Err(OrderRuleError::BelowMinNotional { .. })
+        ));
+        assert!(matches!(
+            rules.check_order(Quantity::new(d("1")), Price::new(d("40000.005"))),
+            Err(OrderRuleError::InvalidPrice(_))
+        ));
+    }
+}
@@ -0,0 +1,141 @@
+use super::{Alert, Notifier};
+use crate::BoxError;
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::transport::smtp::client::{Tls, TlsParameters};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Deserialize;
+use std::time::Duration;
+
+// how the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    // plain text, only for a relay on the same host
+    None,
+    // upgrade with STARTTLS, usually on port 587
+    #[default]
+    Starttls,
+    // TLS from the first byte, usually on port 465
+    Tls,
+}
+
+// mails every alert, the alert being the subject and the body
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailNotifier {
+    pub fn new(
+        host: &str,
+        port: u16,
+        security: SmtpSecurity,
+        credentials: Option<(String, String)>,
+        from: &str,
+        to: &[String],
+    ) -> Result<Self, BoxError> {
+        let tls = match security {
+            SmtpSecurity::None => Tls::None,
+            SmtpSecurity::Starttls => Tls::Required(TlsParameters::new(host.to_string())?),
+            SmtpSecurity::Tls => Tls::Wrapper(TlsParameters::new(host.to_string())?),
+        };
+        let mut transport =
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host).port(port).tls(tls).timeout(Some(Duration::from_secs(10)));
+        if let Some((username, password)) = credentials {
+            transport = transport.credentials(Credentials::new(username, password));
+        }
+        let from = from.parse().map_err(|e| format!("invalid sender {}: {}", from, e))?;
+        let to = to
+            .iter()
+            .map(|to| to.parse().map_err(|e| format!("invalid recipient {}: {}", to, e)))
+            .collect::<Result<Vec<Mailbox>, String>>()?;
+        if to.is_empty() {
+            return Err("an email notifier needs at least one recipient".into());
+        }
+        Ok(EmailNotifier { transport: transport.build(), from, to })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<(), BoxError> {
+        let text = alert.to_string();
+        let mut message = Message::builder().from(self.from.clone()).subject(format!("trading_bot: {}", text));
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message.body(format!("{}\n", text))?;
+        self.transport.send(message).await.map_err(|e| format!("cannot mail the alert: {}", e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    // just enough of an SMTP server to accept one message, returns the commands and the data
+    async fn accept_one(listener: TcpListener) -> (Vec<String>, String) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut commands = Vec::new();
+        let mut data = String::new();
+        write.write_all(b"220 localhost ESMTP\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let reply: &[u8] = match line.split(' ').next().unwrap_or("").to_uppercase().as_str() {
+                "EHLO" => b"250 localhost\r\n",
+                "DATA" => {
+                    write.write_all(b"354 end with a dot\r\n").await.unwrap();
+                    while let Some(line) = lines.next_line().await.unwrap() {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push('\n');
+                    }
+                    b"250 queued\r\n"
+                }
+                "QUIT" => {
+                    write.write_all(b"221 bye\r\n").await.unwrap();
+                    commands.push(line);
+                    break;
+                }
+                _ => b"250 ok\r\n",
+            };
+            commands.push(line);
+            write.write_all(reply).await.unwrap();
+        }
+        (commands, data)
+    }
+
+    #[tokio::test]
+    async fn mails_alerts_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(accept_one(listener));
+
+        let to = ["ops@example.com".to_string(), "me@example.com".to_string()];
+        let notifier = EmailNotifier::new("127.0.0.1", port, SmtpSecurity::None, None, "bot@example.com", &to).unwrap();
+        let alert = Alert::RepeatedErrors { symbol: "ETHUSD".to_string(), count: 3, error: "connection refused".to_string() };
+        notifier.notify(&alert).await.unwrap();
+
+        let (commands, data) = server.await.unwrap();
+        assert!(commands.contains(&"MAIL FROM:<bot@example.com>".to_string()), "{:?}", commands);
+        assert!(commands.contains(&"RCPT TO:<ops@example.com>".to_string()), "{:?}", commands);
+        assert!(commands.contains(&"RCPT TO:<me@example.com>".to_string()), "{:?}", commands);
+        assert!(data.contains("Subject: trading_bot: ETHUSD failed 3 cycles in a row: connection refused\n"), "{}", data);
+        assert!(data.contains("\n\nETHUSD failed 3 cycles in a row: connection refused\n"), "{}", data);
+    }
+
+    #[test]
+    fn needs_a_recipient() {
+        let error = EmailNotifier::new("localhost", 25, SmtpSecurity::None, None, "bot@example.com", &[]).err().unwrap();
+        assert_eq!(error.to_string(), "an email notifier needs at least one recipient");
+    }
+}
@@ -0,0 +1,100 @@
+use crate::market::Side;
+use crate::money::{Money, Price, Quantity};
+use crate::risk::ExitReason;
+use crate::BoxError;
+use async_trait::async_trait;
+use serde::Serialize;
+use std::fmt;
+
+pub mod email;
+pub mod webhook;
+
+pub use email::{EmailNotifier, SmtpSecurity};
+pub use webhook::WebhookNotifier;
+
+// something a person watching the bot should hear about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Alert {
+    // an order filled, whole or in part
+    Fill { symbol: String, side: Side, quantity: Quantity, price: Price },
+    // a stop loss or take profit sells the position
+    RiskExit { symbol: String, reason: ExitReason, price: Price },
+    // buys stop until the next UTC day
+    DailyLossLimit { symbol: String, loss: Money },
+    // cycles keep failing, usually because the market cannot be reached
+    RepeatedErrors { symbol: String, count: u32, error: String },
+    // the bot gave up, the whole portfolio stops with it
+    Halted { symbol: String, error: String },
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Alert::Fill { symbol, side: Side::Buy, quantity, price } => write!(f, "{} bought {} at {}", symbol, quantity, price),
+            Alert::Fill { symbol, side: Side::Sell, quantity, price } => write!(f, "{} sold {} at {}", symbol, quantity, price),
+            Alert::RiskExit { symbol, reason: ExitReason::StopLoss, price } => {
+                write!(f, "{} stop loss triggered at {}", symbol, price)
+            }
+            Alert::RiskExit { symbol, reason: ExitReason::TakeProfit, price } => {
+                write!(f, "{} take profit triggered at {}", symbol, price)
+            }
+            Alert::DailyLossLimit { symbol, loss } => {
+                write!(f, "{} hit the daily loss limit with {} lost today, no more buys until tomorrow", symbol, loss)
+            }
+            Alert::RepeatedErrors { symbol, count, error } => write!(f, "{} failed {} cycles in a row: {}", symbol, count, error),
+            Alert::Halted { symbol, error } => write!(f, "{} stopped trading: {}", symbol, error),
+        }
+    }
+}
+
+// delivers alerts somewhere. a failed delivery is logged by the caller and never stops trading.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, alert: &Alert) -> Result<(), BoxError>;
+}
+
+// prints alerts to stdout, apart from the log lines on stderr
+pub struct StdoutNotifier;
+
+#[async_trait]
+impl Notifier for StdoutNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<(), BoxError> {
+        println!("[ALERT] {}", alert);
+        Ok(())
+    }
+}
+
+// hands every alert to all of them, one failing does not keep the others from delivering it
+pub struct Notifiers(pub Vec<Box<dyn Notifier>>);
+
+#[async_trait]
+impl Notifier for Notifiers {
+    async fn notify(&self, alert: &Alert) -> Result<(), BoxError> {
+        let mut errors = Vec::new();
+        for notifier in &self.0 {
+            if let Err(e) = notifier.notify(alert).await {
+                errors.push(e.to_string());
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; ").into())
+        }
+    }
+}
+
+// keeps the alerts for tests to look at
+#[cfg(test)]
+#[derive(Default)]
+pub struct RecordingNotifier(pub std::sync::Mutex<Vec<Alert>>);
+
+#[cfg(test)]
+#[async_trait]
+impl Notifier for RecordingNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<(), BoxError> {
+        self.0.lock().unwrap().push(alert.clone());
+        Ok(())
+    }
+}
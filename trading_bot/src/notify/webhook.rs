@@ -0,0 +1,109 @@
+use super::{Alert, Notifier};
+use crate::BoxError;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Serialize;
+use std::time::Duration;
+
+// POSTs every alert as JSON, e.g.
+//
+//   {"text": "BTCUSD bought 0.01 at 40000", "event": "fill", "symbol": "BTCUSD", "side": "buy",
+//    "quantity": "0.01", "price": "40000"}
+//
+// chat services that take a "text" field show the message as is, anything else can read the
+// fields of the alert.
+pub struct WebhookNotifier {
+    client: Client,
+    url: String,
+}
+
+#[derive(Serialize)]
+struct Payload<'a> {
+    text: String,
+    #[serde(flatten)]
+    alert: &'a Alert,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: &str) -> Self {
+        // a hanging webhook must not hold up the cycle that raised the alert for long
+        let client = Client::builder().timeout(Duration::from_secs(10)).build().unwrap_or_default();
+        WebhookNotifier { client, url: url.to_string() }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, alert: &Alert) -> Result<(), BoxError> {
+        let payload = Payload { text: alert.to_string(), alert };
+        self.client
+            .post(&self.url)
+            .json(&payload)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("webhook {}: {}", self.url, e))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market::Side;
+    use axum::extract::State;
+    use axum::http::StatusCode;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use tokio::net::TcpListener;
+
+    type Received = Arc<Mutex<Vec<Value>>>;
+
+    async fn hook(State(received): State<Received>, Json(body): Json<Value>) -> StatusCode {
+        let mut received = received.lock().unwrap();
+        received.push(body);
+        // the second delivery fails
+        if received.len() == 2 {
+            StatusCode::INTERNAL_SERVER_ERROR
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    #[tokio::test]
+    async fn posts_alerts_as_json() {
+        let received = Received::default();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let app = Router::new().route("/hook", post(hook)).with_state(received.clone());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let notifier = WebhookNotifier::new(&url);
+        let fill = Alert::Fill {
+            symbol: "BTCUSD".to_string(),
+            side: Side::Buy,
+            quantity: "0.01".parse().unwrap(),
+            price: "40000".parse().unwrap(),
+        };
+        notifier.notify(&fill).await.unwrap();
+        let halted = Alert::Halted { symbol: "BTCUSD".to_string(), error: "price feed closed".to_string() };
+        let error = notifier.notify(&halted).await.unwrap_err();
+        assert!(error.to_string().contains("500"), "{}", error);
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            received[0],
+            json!({
+                "text": "BTCUSD bought 0.01 at 40000",
+                "event": "fill",
+                "symbol": "BTCUSD",
+                "side": "buy",
+                "quantity": "0.01",
+                "price": "40000"
+            })
+        );
+        assert_eq!(received[1]["event"], "halted");
+    }
+}
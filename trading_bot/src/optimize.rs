@@ -0,0 +1,461 @@
+use crate::backtest::{run_backtest, PricePoint};
+use crate::bot::TradingConfig;
+use crate::money::Money;
+use crate::BoxError;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::Range as Span;
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::task::JoinSet;
+
+// a sweep that would take this many backtests per window is most likely a typo
+const MAX_PARAMETER_SETS: usize = 100_000;
+
+// the strategy thresholds a sweep can vary, in percent like in TradingConfig
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Parameter {
+    UpwardTrendThreshold,
+    DipThreshold,
+    ProfitThreshold,
+    StopLossThreshold,
+}
+
+impl Parameter {
+    pub fn name(self) -> &'static str {
+        match self {
+            Parameter::UpwardTrendThreshold => "upward_trend_threshold",
+            Parameter::DipThreshold => "dip_threshold",
+            Parameter::ProfitThreshold => "profit_threshold",
+            Parameter::StopLossThreshold => "stop_loss_threshold",
+        }
+    }
+
+    fn apply(self, config: &mut TradingConfig, value: Decimal) {
+        match self {
+            Parameter::UpwardTrendThreshold => config.upward_trend_threshold = value,
+            Parameter::DipThreshold => config.dip_threshold = value,
+            Parameter::ProfitThreshold => config.profit_threshold = value,
+            Parameter::StopLossThreshold => config.stop_loss_threshold = value,
+        }
+    }
+}
+
+// `from` to `to` inclusive in steps of `step`, written `from:to:step` or just `value`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range {
+    pub from: Decimal,
+    pub to: Decimal,
+    pub step: Decimal,
+}
+
+impl Range {
+    pub fn values(&self) -> Vec<Decimal> {
+        let mut values = Vec::new();
+        let mut value = self.from;
+        while value <= self.to {
+            values.push(value.normalize());
+            value = match value.checked_add(self.step) {
+                Some(next) => next,
+                None => break,
+            };
+        }
+        values
+    }
+}
+
+impl FromStr for Range {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse = |part: &str| part.trim().parse::<Decimal>().map_err(|e| format!("bad number {:?}: {}", part, e));
+        let parts: Vec<&str> = s.split(':').collect();
+        let range = match parts.as_slice() {
+            [value] => {
+                let value = parse(value)?;
+                Range { from: value, to: value, step: Decimal::ONE }
+            }
+            [from, to, step] => Range { from: parse(from)?, to: parse(to)?, step: parse(step)? },
+            _ => return Err(format!("expected `from:to:step` or a single value, got {:?}", s)),
+        };
+        if range.from > range.to {
+            return Err(format!("{} is above {}", range.from, range.to));
+        }
+        if range.step <= Decimal::ZERO {
+            return Err(format!("step must be positive, got {}", range.step));
+        }
+        Ok(range)
+    }
+}
+
+// one point of the parameter space
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ParameterSet(pub Vec<(Parameter, Decimal)>);
+
+impl ParameterSet {
+    pub fn apply(&self, config: &TradingConfig) -> TradingConfig {
+        let mut config = config.clone();
+        for (parameter, value) in &self.0 {
+            parameter.apply(&mut config, *value);
+        }
+        config
+    }
+}
+
+impl fmt::Display for ParameterSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let values: Vec<String> = self.0.iter().map(|(parameter, value)| format!("{}={}", parameter.name(), value)).collect();
+        write!(f, "{}", values.join(" "))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ParameterSpace {
+    ranges: Vec<(Parameter, Vec<Decimal>)>,
+}
+
+impl ParameterSpace {
+    pub fn new() -> Self {
+        ParameterSpace::default()
+    }
+
+    pub fn with(mut self, parameter: Parameter, range: Range) -> Self {
+        self.ranges.retain(|(p, _)| *p != parameter);
+        self.ranges.push((parameter, range.values()));
+        self
+    }
+
+    pub fn size(&self) -> usize {
+        self.ranges.iter().fold(1usize, |size, (_, values)| size.saturating_mul(values.len()))
+    }
+
+    fn set(&self, indices: &[usize]) -> ParameterSet {
+        ParameterSet(self.ranges.iter().zip(indices).map(|((parameter, values), &idx)| (*parameter, values[idx])).collect())
+    }
+
+    // every combination of the ranges
+    pub fn grid(&self) -> Result<Vec<ParameterSet>, BoxError> {
+        if self.size() > MAX_PARAMETER_SETS {
+            return Err(format!("the grid has {} parameter sets, narrow it or use a random search", self.size()).into());
+        }
+        let mut sets = Vec::with_capacity(self.size());
+        let mut indices = vec![0; self.ranges.len()];
+        loop {
+            sets.push(self.set(&indices));
+            // count up like an odometer, the last parameter turning fastest
+            let mut position = self.ranges.len();
+            loop {
+                if position == 0 {
+                    return Ok(sets);
+                }
+                position -= 1;
+                indices[position] += 1;
+                if indices[position] < self.ranges[position].1.len() {
+                    break;
+                }
+                indices[position] = 0;
+            }
+        }
+    }
+
+    // `samples` distinct combinations drawn at random, the whole grid if it is not bigger
+    pub fn random(&self, samples: usize, seed: u64) -> Result<Vec<ParameterSet>, BoxError> {
+        if samples > MAX_PARAMETER_SETS {
+            return Err(format!("{} samples is more than the {} allowed", samples, MAX_PARAMETER_SETS).into());
+        }
+        if samples >= self.size() {
+            return self.grid();
+        }
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut seen = HashSet::new();
+        let mut sets = Vec::with_capacity(samples);
+        while sets.len() < samples {
+            let indices: Vec<usize> = self.ranges.iter().map(|(_, values)| rng.random_range(0..values.len())).collect();
+            if seen.insert(indices.clone()) {
+                sets.push(self.set(&indices));
+            }
+        }
+        Ok(sets)
+    }
+}
+
+// indices of the prices a fold tunes on and of those it is validated on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fold {
+    pub tune: Span<usize>,
+    pub validate: Span<usize>,
+}
+
+// splits `len` prices into `folds` + 1 equal windows. fold i tunes on window i and is validated
+// on window i + 1, which the parameters have never seen.
+pub fn walk_forward_windows(len: usize, folds: usize) -> Result<Vec<Fold>, BoxError> {
+    if folds == 0 {
+        return Err("walk-forward validation needs at least one fold".into());
+    }
+    let size = len / (folds + 1);
+    // a window needs a reference price and one to trade on
+    if size < 2 {
+        return Err(format!("{} prices are too few for {} folds", len, folds).into());
+    }
+    Ok((0..folds).map(|fold| Fold { tune: fold * size..(fold + 1) * size, validate: (fold + 1) * size..(fold + 2) * size }).collect())
+}
+
+// how one parameter set did over all folds, returns in percent
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Score {
+    pub parameters: ParameterSet,
+    // average return on the tuning windows
+    pub in_sample: Decimal,
+    // average return on the validation windows, what the ranking goes by
+    pub out_of_sample: Decimal,
+    // worst drawdown on a validation window
+    pub max_drawdown: Decimal,
+    pub trades: usize,
+    // per fold, in and out of sample
+    folds: Vec<(Decimal, Decimal)>,
+}
+
+// the parameter set that did best on a fold's tuning window and how it did on the next window
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WalkForwardStep {
+    pub parameters: ParameterSet,
+    pub in_sample: Decimal,
+    pub out_of_sample: Decimal,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OptimizationReport {
+    pub window: usize,
+    // best first
+    pub ranked: Vec<Score>,
+    pub steps: Vec<WalkForwardStep>,
+    // how many of the ranked sets Display prints
+    pub top: usize,
+}
+
+impl OptimizationReport {
+    // average validation return of always trading the latest in-sample winner, None on overflow
+    pub fn walk_forward_return(&self) -> Option<Decimal> {
+        average(self.steps.iter().map(|step| step.out_of_sample))
+    }
+}
+
+impl fmt::Display for OptimizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} parameter sets, {} folds of {} prices, ranked by out-of-sample return",
+            self.ranked.len(),
+            self.steps.len(),
+            self.window
+        )?;
+        writeln!(f)?;
+        writeln!(f, "rank  in-sample %  out-of-sample %  max drawdown %  trades  parameters")?;
+        for (idx, score) in self.ranked.iter().take(self.top).enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:>11}  {:>15}  {:>14}  {:>6}  {}",
+                idx + 1,
+                score.in_sample.round_dp(2),
+                score.out_of_sample.round_dp(2),
+                score.max_drawdown.round_dp(2),
+                score.trades,
+                score.parameters
+            )?;
+        }
+        writeln!(f)?;
+        writeln!(f, "fold  in-sample %  out-of-sample %  in-sample winner")?;
+        for (idx, step) in self.steps.iter().enumerate() {
+            writeln!(
+                f,
+                "{:>4}  {:>11}  {:>15}  {}",
+                idx + 1,
+                step.in_sample.round_dp(2),
+                step.out_of_sample.round_dp(2),
+                step.parameters
+            )?;
+        }
+        match self.walk_forward_return() {
+            Some(walk_forward) => write!(f, "walk-forward return: {} %", walk_forward.round_dp(2)),
+            None => write!(f, "walk-forward return: overflowed"),
+        }
+    }
+}
+
+// zero for no values, None if the sum overflows
+fn average(values: impl Iterator<Item = Decimal>) -> Option<Decimal> {
+    let (mut sum, mut count) = (Decimal::ZERO, 0u32);
+    for value in values {
+        sum = sum.checked_add(value)?;
+        count += 1;
+    }
+    if count == 0 {
+        return Some(Decimal::ZERO);
+    }
+    sum.checked_div(Decimal::from(count))
+}
+
+async fn score(
+    parameters: ParameterSet,
+    base: Arc<TradingConfig>,
+    balance: Money,
+    prices: Arc<Vec<PricePoint>>,
+    windows: Arc<Vec<Fold>>,
+) -> Result<Score, BoxError> {
+    let config = parameters.apply(&base);
+    let mut folds = Vec::with_capacity(windows.len());
+    let mut max_drawdown = Decimal::ZERO;
+    let mut trades = 0;
+    for fold in windows.iter() {
+        let tuned = run_backtest(config.clone(), balance, &prices[fold.tune.clone()]).await;
+        let validated = run_backtest(config.clone(), balance, &prices[fold.validate.clone()]).await;
+        let (tuned, validated) = match (tuned, validated) {
+            (Ok(tuned), Ok(validated)) => (tuned, validated),
+            (Err(e), _) | (_, Err(e)) => return Err(format!("{}: {}", parameters, e).into()),
+        };
+        max_drawdown = max_drawdown.max(validated.max_drawdown);
+        trades += validated.trades;
+        folds.push((tuned.total_return, validated.total_return));
+    }
+    let overflowed = || format!("{}: average return overflowed", parameters);
+    Ok(Score {
+        in_sample: average(folds.iter().map(|fold| fold.0)).ok_or_else(overflowed)?,
+        out_of_sample: average(folds.iter().map(|fold| fold.1)).ok_or_else(overflowed)?,
+        parameters,
+        max_drawdown,
+        trades,
+        folds,
+    })
+}
+
+// backtests every parameter set on every walk-forward window, spread over the runtime's worker
+// threads, and ranks the sets by how they did on data they were not tuned on
+pub async fn optimize(
+    base: TradingConfig,
+    balance: Money,
+    prices: Vec<PricePoint>,
+    sets: Vec<ParameterSet>,
+    folds: usize,
+) -> Result<OptimizationReport, BoxError> {
+    if sets.is_empty() {
+        return Err("nothing to optimize, no parameter sets".into());
+    }
+    let windows = walk_forward_windows(prices.len(), folds)?;
+    let window = windows[0].tune.len();
+    let base = Arc::new(base);
+    let prices = Arc::new(prices);
+    let windows = Arc::new(windows);
+
+    let mut tasks = JoinSet::new();
+    for (idx, parameters) in sets.into_iter().enumerate() {
+        let task = score(parameters, base.clone(), balance, prices.clone(), windows.clone());
+        tasks.spawn(async move { task.await.map(|score| (idx, score)) });
+    }
+    let mut scores = Vec::with_capacity(tasks.len());
+    while let Some(finished) = tasks.join_next().await {
+        scores.push(finished??);
+    }
+    // finishing order depends on the threads, keep ties in the order the sets came in
+    scores.sort_by_key(|(idx, _)| *idx);
+    let mut ranked: Vec<Score> = scores.into_iter().map(|(_, score)| score).collect();
+
+    let steps = (0..folds)
+        .map(|fold| {
+            // the first of equally good sets wins
+            let mut best = &ranked[0];
+            for score in &ranked[1..] {
+                if score.folds[fold].0 > best.folds[fold].0 {
+                    best = score;
+                }
+            }
+            WalkForwardStep {
+                parameters: best.parameters.clone(),
+                in_sample: best.folds[fold].0,
+                out_of_sample: best.folds[fold].1,
+            }
+        })
+        .collect();
+    // stable, so equal scores keep their order
+    ranked.sort_by(|a, b| b.out_of_sample.cmp(&a.out_of_sample).then(b.in_sample.cmp(&a.in_sample)));
+
+    Ok(OptimizationReport { window, ranked, steps, top: 10 })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::parse_prices;
+    use crate::risk::RiskConfig;
+    use std::time::Duration;
+
+    fn d(value: &str) -> Decimal {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn averages_without_overflowing() {
+        assert_eq!(average(std::iter::empty()), Some(Decimal::ZERO));
+        assert_eq!(average(vec![Decimal::ONE, Decimal::TWO].into_iter()), Some(Decimal::new(15, 1)));
+        assert_eq!(average(vec![Decimal::MAX, Decimal::MAX].into_iter()), None);
+    }
+
+    #[test]
+    fn builds_grids_and_random_samples() {
+        assert_eq!("1:2:0.5".parse::<Range>().unwrap().values(), vec![d("1"), d("1.5"), d("2")]);
+        assert_eq!("-2".parse::<Range>().unwrap().values(), vec![d("-2")]);
+        assert!("2:1:0.5".parse::<Range>().unwrap_err().contains("above"));
+        assert!("1:2:0".parse::<Range>().unwrap_err().contains("step must be positive"));
+
+        let space = ParameterSpace::new()
+            .with(Parameter::DipThreshold, "-3:-1:1".parse().unwrap())
+            .with(Parameter::ProfitThreshold, "1:2:1".parse().unwrap());
+        let grid = space.grid().unwrap();
+        assert_eq!(grid.len(), 6);
+        assert_eq!(grid[1].to_string(), "dip_threshold=-3 profit_threshold=2");
+        assert_eq!(grid.iter().collect::<HashSet<_>>().len(), 6);
+
+        let sample = space.random(4, 7).unwrap();
+        assert_eq!(sample.len(), 4);
+        assert_eq!(sample.iter().collect::<HashSet<_>>().len(), 4);
+        assert!(sample.iter().all(|set| grid.contains(set)));
+        assert_eq!(space.random(4, 7).unwrap(), sample);
+        assert_eq!(space.random(10, 7).unwrap(), grid);
+
+        let folds = walk_forward_windows(10, 2).unwrap();
+        assert_eq!(folds, vec![Fold { tune: 0..3, validate: 3..6 }, Fold { tune: 3..6, validate: 6..9 }]);
+        assert!(walk_forward_windows(5, 4).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn ranks_by_out_of_sample_return() {
+        // dips and recoveries in every window
+        let prices = parse_prices(
+            "0,100\n60,97\n120,99\n180,100\n\
+             240,100\n300,97\n360,99\n420,100\n\
+             480,100\n540,97\n600,99\n660,100\n",
+        )
+        .unwrap();
+        let risk = RiskConfig { position_fraction: Decimal::ONE, cooldown: Duration::ZERO, ..RiskConfig::default() };
+        let base = TradingConfig { risk, ..TradingConfig::default() };
+        let space = ParameterSpace::new()
+            .with(Parameter::DipThreshold, "-4:-2:1".parse().unwrap())
+            .with(Parameter::ProfitThreshold, "1:3:1".parse().unwrap());
+        let report = optimize(base, "1000".parse().unwrap(), prices, space.grid().unwrap(), 2).await.unwrap();
+
+        assert_eq!(report.ranked.len(), 9);
+        assert_eq!(report.steps.len(), 2);
+        assert_eq!(report.window, 4);
+        // a -4% dip never comes and holding on until 100 beats selling at 99
+        let best = &report.ranked[0];
+        assert_eq!(best.parameters.to_string(), "dip_threshold=-3 profit_threshold=3");
+        assert!(best.out_of_sample > Decimal::ZERO);
+        assert_eq!(best.trades, 4);
+        assert!(report.ranked.windows(2).all(|pair| pair[0].out_of_sample >= pair[1].out_of_sample));
+        assert_eq!(report.steps[0].parameters, best.parameters);
+        assert_eq!(report.walk_forward_return(), Some(best.out_of_sample));
+        assert!(report.to_string().contains("   1  "));
+    }
+}
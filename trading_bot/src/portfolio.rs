@@ -0,0 +1,400 @@
+use crate::bot::{State, TradingBot};
+use crate::money::{Money, Price, Quantity};
+use crate::feed::PriceFeed;
+use crate::notify::Alert;
+use crate::BoxError;
+use futures_util::StreamExt;
+use log::{info, warn};
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+// splits one quote currency balance between the bots of a portfolio.
+// a symbol may hold at most `max_fraction` of the portfolio, counted as free balance plus what
+// every symbol has spent on its open position.
+#[derive(Debug)]
+pub struct Allocator {
+    max_fraction: Decimal,
+    books: Mutex<Books>,
+}
+
+#[derive(Debug, Default)]
+struct Books {
+    // handed out to buys still in flight
+    reserved: BTreeMap<String, Money>,
+    // spent on open positions
+    allocated: BTreeMap<String, Money>,
+}
+
+fn total(amounts: &BTreeMap<String, Money>) -> Money {
+    amounts.values().fold(Money::ZERO, |sum, amount| sum.checked_add(*amount).unwrap_or(sum))
+}
+
+fn of(amounts: &BTreeMap<String, Money>, symbol: &str) -> Money {
+    amounts.get(symbol).copied().unwrap_or(Money::ZERO)
+}
+
+impl Allocator {
+    pub fn new(max_fraction: Decimal) -> Self {
+        Allocator { max_fraction, books: Mutex::new(Books::default()) }
+    }
+
+    // how much of `requested` a buy of `symbol` may spend, given the free `balance`.
+    // the amount stays reserved until `settle` is called with what the buy actually spent.
+    pub fn reserve(&self, symbol: &str, requested: Money, balance: Money) -> Money {
+        let mut books = self.books.lock().unwrap();
+        let reserved = total(&books.reserved);
+        let portfolio = balance.checked_add(total(&books.allocated)).unwrap_or(balance);
+        let cap = portfolio
+            .checked_scale(self.max_fraction)
+            .and_then(|cap| cap.checked_sub(of(&books.allocated, symbol)))
+            .and_then(|cap| cap.checked_sub(of(&books.reserved, symbol)))
+            .unwrap_or(Money::ZERO);
+        let free = balance.checked_sub(reserved).unwrap_or(Money::ZERO);
+        let granted = requested.min(cap).min(free).max(Money::ZERO);
+        let entry = books.reserved.entry(symbol.to_string()).or_insert(Money::ZERO);
+        *entry = entry.checked_add(granted).unwrap_or(*entry);
+        granted
+    }
+
+    // turn a reservation into an allocation of `spent`, zero if the buy did not go through
+    pub fn settle(&self, symbol: &str, reserved: Money, spent: Money) {
+        let mut books = self.books.lock().unwrap();
+        if let Some(entry) = books.reserved.get_mut(symbol) {
+            *entry = entry.checked_sub(reserved).unwrap_or(Money::ZERO).max(Money::ZERO);
+        }
+        let entry = books.allocated.entry(symbol.to_string()).or_insert(Money::ZERO);
+        *entry = entry.checked_add(spent).unwrap_or(*entry);
+    }
+
+    // the position of `symbol` was sold
+    pub fn release(&self, symbol: &str) {
+        self.books.lock().unwrap().allocated.remove(symbol);
+    }
+
+    pub fn allocated(&self, symbol: &str) -> Money {
+        of(&self.books.lock().unwrap().allocated, symbol)
+    }
+}
+
+// what one bot of the portfolio is up to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SymbolStatus {
+    pub symbol: String,
+    pub last_price: Option<Price>,
+    pub next_operation: State,
+    pub position: Quantity,
+    pub realized_pnl: Money,
+    pub trades: usize,
+}
+
+impl SymbolStatus {
+    // the open position valued at the last price seen
+    pub fn position_value(&self) -> Money {
+        self.last_price.and_then(|price| price.checked_notional(self.position)).unwrap_or(Money::ZERO)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortfolioReport {
+    pub symbols: Vec<SymbolStatus>,
+}
+
+impl PortfolioReport {
+    pub fn realized_pnl(&self) -> Money {
+        self.symbols.iter().fold(Money::ZERO, |sum, s| sum.checked_add(s.realized_pnl).unwrap_or(sum))
+    }
+
+    pub fn position_value(&self) -> Money {
+        self.symbols.iter().fold(Money::ZERO, |sum, s| sum.checked_add(s.position_value()).unwrap_or(sum))
+    }
+}
+
+impl fmt::Display for PortfolioReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for status in &self.symbols {
+            let price = status.last_price.map_or_else(|| "-".to_string(), |price| price.to_string());
+            writeln!(
+                f,
+                "{:<10} price {:>12}  next {:?}  position {} ({})  realized {}  trades {}",
+                status.symbol,
+                price,
+                status.next_operation,
+                status.position,
+                status.position_value(),
+                status.realized_pnl,
+                status.trades
+            )?;
+        }
+        write!(f, "total      positions {}  realized {}", self.position_value(), self.realized_pnl())
+    }
+}
+
+// how many failed cycles in a row a bot sits through. the `alert_after`th raises an alert, the
+// `halt_after`th stops the portfolio, so the alert only goes out if it comes first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorLimits {
+    pub alert_after: u32,
+    pub halt_after: u32,
+}
+
+impl Default for ErrorLimits {
+    fn default() -> Self {
+        ErrorLimits { alert_after: 3, halt_after: 5 }
+    }
+}
+
+// several bots, one per symbol, trading concurrently against a shared allocator.
+// with a feed the bots trade on its ticks, otherwise they poll their markets.
+pub struct Portfolio {
+    allocator: Arc<Allocator>,
+    feed: Option<Arc<dyn PriceFeed>>,
+    bots: Vec<TradingBot>,
+    error_limits: ErrorLimits,
+}
+
+impl Portfolio {
+    pub fn new(allocator: Arc<Allocator>) -> Self {
+        Portfolio { allocator, feed: None, bots: Vec::new(), error_limits: ErrorLimits::default() }
+    }
+
+    pub fn with_feed(mut self, feed: Arc<dyn PriceFeed>) -> Self {
+        self.feed = Some(feed);
+        self
+    }
+
+    // keep trading through failed cycles instead of stopping at the first one
+    pub fn with_error_limits(mut self, error_limits: ErrorLimits) -> Self {
+        self.error_limits = error_limits;
+        self
+    }
+
+    // a position the bot restored from its journal counts against its allocation right away
+    pub fn add(&mut self, bot: TradingBot) {
+        let config = &bot.trading_config;
+        if config.position.is_positive() {
+            let cost = config.last_operation_price.checked_notional(config.position).unwrap_or(Money::ZERO);
+            self.allocator.settle(&config.symbol, Money::ZERO, cost);
+        }
+        self.bots.push(bot.with_allocator(self.allocator.clone()));
+    }
+
+    pub fn report(&self) -> PortfolioReport {
+        PortfolioReport { symbols: self.bots.iter().map(TradingBot::status).collect() }
+    }
+
+    // runs every bot on its own task and logs a report every `report_interval`.
+    // the first bot to run out of its error limits stops the whole portfolio.
+    pub async fn start(self, report_interval: Duration) -> Result<(), BoxError> {
+        let statuses = Arc::new(Mutex::new(self.report()));
+        let mut tasks = JoinSet::new();
+        for (idx, bot) in self.bots.into_iter().enumerate() {
+            let failures = Failures { limits: self.error_limits, count: 0 };
+            match &self.feed {
+                Some(feed) => tasks.spawn(stream_bot(bot, feed.clone(), statuses.clone(), idx, failures)),
+                None => tasks.spawn(poll_bot(bot, statuses.clone(), idx, failures)),
+            };
+        }
+
+        let mut interval = tokio::time::interval(report_interval);
+        // the first tick completes right away
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                finished = tasks.join_next() => match finished {
+                    Some(result) => result??,
+                    None => return Ok(()),
+                },
+                _ = interval.tick() => {
+                    info!("[PORTFOLIO]\n{}", statuses.lock().unwrap());
+                }
+            }
+        }
+    }
+}
+
+// failed cycles of one bot in a row
+struct Failures {
+    limits: ErrorLimits,
+    count: u32,
+}
+
+impl Failures {
+    // whether the bot carries on after a cycle, the error it stops with otherwise
+    async fn check(&mut self, bot: &TradingBot, result: Result<(), BoxError>) -> Result<(), BoxError> {
+        let error = match result {
+            Ok(()) => {
+                self.count = 0;
+                return Ok(());
+            }
+            Err(e) => e,
+        };
+        self.count += 1;
+        if self.count >= self.limits.halt_after {
+            return Err(halt(bot, error).await);
+        }
+        let symbol = &bot.trading_config.symbol;
+        warn!("[ERROR] {} cycle failed, {} in a row: {}", symbol, self.count, error);
+        if self.count == self.limits.alert_after {
+            bot.notify(Alert::RepeatedErrors { symbol: symbol.clone(), count: self.count, error: error.to_string() }).await;
+        }
+        Ok(())
+    }
+}
+
+// the bot stops for good
+async fn halt(bot: &TradingBot, error: BoxError) -> BoxError {
+    let symbol = bot.trading_config.symbol.clone();
+    bot.notify(Alert::Halted { symbol: symbol.clone(), error: error.to_string() }).await;
+    format!("{}: {}", symbol, error).into()
+}
+
+async fn poll_bot(
+    mut bot: TradingBot,
+    statuses: Arc<Mutex<PortfolioReport>>,
+    idx: usize,
+    mut failures: Failures,
+) -> Result<(), BoxError> {
+    loop {
+        let result = bot.run_cycle().await;
+        statuses.lock().unwrap().symbols[idx] = bot.status();
+        failures.check(&bot, result).await?;
+        tokio::time::sleep(bot.trading_config.polling_interval).await;
+    }
+}
+
+async fn stream_bot(
+    mut bot: TradingBot,
+    feed: Arc<dyn PriceFeed>,
+    statuses: Arc<Mutex<PortfolioReport>>,
+    idx: usize,
+    mut failures: Failures,
+) -> Result<(), BoxError> {
+    let mut ticks = match feed.subscribe(&bot.trading_config.symbol).await {
+        Ok(ticks) => ticks,
+        Err(e) => return Err(halt(&bot, e).await),
+    };
+    while let Some(tick) = ticks.next().await {
+        let result = match tick {
+            Ok(tick) => bot.on_tick(&tick).await,
+            Err(e) => Err(e),
+        };
+        statuses.lock().unwrap().symbols[idx] = bot.status();
+        failures.check(&bot, result).await?;
+    }
+    Err(halt(&bot, "price feed closed".into()).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot::TradingConfig;
+    use crate::market::{SimulatedMarket, Wallet};
+    use crate::money::SymbolRules;
+    use crate::notify::RecordingNotifier;
+    use crate::risk::RiskConfig;
+
+    fn money(value: &str) -> Money {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn caps_each_symbol_and_never_hands_out_more_than_the_balance() {
+        let allocator = Allocator::new(Decimal::new(4, 1));
+
+        let btc = allocator.reserve("BTCUSD", money("1000"), money("1000"));
+        assert_eq!(btc, money("400"));
+        // the reservation is still in flight, only 600 are free
+        assert_eq!(allocator.reserve("ETHUSD", money("1000"), money("1000")), money("400"));
+        allocator.settle("ETHUSD", money("400"), Money::ZERO);
+
+        allocator.settle("BTCUSD", btc, money("399.5"));
+        assert_eq!(allocator.allocated("BTCUSD"), money("399.5"));
+        // the portfolio is still worth 1000, BTCUSD has room for 0.5 more
+        assert_eq!(allocator.reserve("BTCUSD", money("100"), money("600.5")), money("0.5"));
+        allocator.settle("BTCUSD", money("0.5"), Money::ZERO);
+
+        allocator.release("BTCUSD");
+        assert_eq!(allocator.allocated("BTCUSD"), Money::ZERO);
+        // after a loss the caps shrink with the portfolio
+        assert_eq!(allocator.reserve("ETHUSD", money("1000"), money("50")), money("20"));
+    }
+
+    #[tokio::test]
+    async fn bots_share_the_wallet_within_their_allocation() {
+        let wallet = Arc::new(Wallet::new(money("1000")));
+        let mut portfolio = Portfolio::new(Arc::new(Allocator::new(Decimal::new(5, 1))));
+        let mut markets = Vec::new();
+        for (symbol, prices) in [("BTCUSD", ["40000", "38000"]), ("ETHUSD", ["2000", "1900"])] {
+            let prices = prices.iter().map(|p| p.parse().unwrap()).collect();
+            let market = Arc::new(SimulatedMarket::with_wallet(prices, wallet.clone(), SymbolRules::default()));
+            let risk = RiskConfig { position_fraction: Decimal::ONE, ..RiskConfig::default() };
+            let config = TradingConfig { symbol: symbol.to_string(), risk, polling_interval: Duration::ZERO, ..TradingConfig::default() };
+            portfolio.add(TradingBot::new(config, Box::new(market.clone())));
+            markets.push(market);
+        }
+
+        for bot in &mut portfolio.bots {
+            bot.run_cycle().await.unwrap();
+            bot.run_cycle().await.unwrap();
+        }
+        // both dipped, both bought with half of the portfolio at most
+        for market in &markets {
+            let spent = market.fills()[0].price.checked_notional(market.holdings()).unwrap();
+            assert!(spent <= money("500") && spent > money("499"), "{}", spent);
+        }
+        assert!(wallet.balance() < money("2"));
+    }
+
+    #[tokio::test]
+    async fn stops_when_a_bot_fails() {
+        let mut portfolio = Portfolio::new(Arc::new(Allocator::new(Decimal::ONE)));
+        let market = SimulatedMarket::new(Vec::new(), money("1000"), SymbolRules::default());
+        // it sits through a few failed cycles first
+        let config = TradingConfig { polling_interval: Duration::ZERO, ..TradingConfig::default() };
+        portfolio.add(TradingBot::new(config, Box::new(market)));
+        let error = portfolio.start(Duration::from_secs(60)).await.unwrap_err();
+        assert_eq!(error.to_string(), "BTCUSD: price feed exhausted");
+    }
+
+    #[tokio::test]
+    async fn alerts_on_repeated_errors_before_halting() {
+        let notifier = Arc::new(RecordingNotifier::default());
+        let limits = ErrorLimits { alert_after: 2, halt_after: 4 };
+        let mut portfolio = Portfolio::new(Arc::new(Allocator::new(Decimal::ONE))).with_error_limits(limits);
+        let market = SimulatedMarket::new(Vec::new(), money("1000"), SymbolRules::default());
+        let config = TradingConfig { polling_interval: Duration::ZERO, ..TradingConfig::default() };
+        portfolio.add(TradingBot::new(config, Box::new(market)).with_notifier(notifier.clone()));
+        let error = portfolio.start(Duration::from_secs(60)).await.unwrap_err();
+        assert_eq!(error.to_string(), "BTCUSD: price feed exhausted");
+
+        let error = "price feed exhausted".to_string();
+        assert_eq!(
+            *notifier.0.lock().unwrap(),
+            vec![
+                Alert::RepeatedErrors { symbol: "BTCUSD".to_string(), count: 2, error: error.clone() },
+                Alert::Halted { symbol: "BTCUSD".to_string(), error },
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_every_symbol_and_the_totals() {
+        let status = |symbol: &str, price: &str, position: &str, pnl: &str| SymbolStatus {
+            symbol: symbol.to_string(),
+            last_price: Some(price.parse().unwrap()),
+            next_operation: State::Sell,
+            position: position.parse().unwrap(),
+            realized_pnl: money(pnl),
+            trades: 3,
+        };
+        let report =
+            PortfolioReport { symbols: vec![status("BTCUSD", "40000", "0.01", "12.5"), status("ETHUSD", "2000", "0.5", "-2")] };
+        assert_eq!(report.position_value(), money("1400"));
+        assert_eq!(report.realized_pnl(), money("10.5"));
+        assert!(report.to_string().ends_with("total      positions 1400  realized 10.5"), "{}", report);
+    }
+}
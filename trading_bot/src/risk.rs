@@ -0,0 +1,234 @@
+use crate::money::{Money, Price, Quantity};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+// limits checked by the bot before any order reaches the market.
+// stop_loss and take_profit are percentages away from the entry price.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskConfig {
+    pub position_fraction: Decimal,
+    pub stop_loss: Option<Decimal>,
+    pub take_profit: Option<Decimal>,
+    pub max_daily_loss: Option<Money>,
+    pub cooldown: Duration,
+}
+
+impl Default for RiskConfig {
+    fn default() -> Self {
+        RiskConfig {
+            position_fraction: Decimal::new(25, 2),
+            stop_loss: Some(Decimal::new(5, 0)),
+            take_profit: None,
+            max_daily_loss: Some(Money::new(Decimal::new(100, 0))),
+            cooldown: Duration::from_secs(60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitReason {
+    StopLoss,
+    TakeProfit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskRejection {
+    Halted { loss: Money },
+    CoolingDown { remaining: Duration },
+}
+
+impl fmt::Display for RiskRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RiskRejection::Halted { loss } => write!(f, "daily loss limit hit ({} lost today)", loss),
+            RiskRejection::CoolingDown { remaining } => write!(f, "cooling down for another {:?}", remaining),
+        }
+    }
+}
+
+impl Error for RiskRejection {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Entry {
+    price: Price,
+    stop_loss: Option<Price>,
+    take_profit: Option<Price>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RiskManager {
+    pub config: RiskConfig,
+    entry: Option<Entry>,
+    last_operation_at: Option<SystemTime>,
+    // realized profit and loss of the current UTC day and overall
+    day: u64,
+    realized_today: Money,
+    realized_total: Money,
+}
+
+fn day_of(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() / SECONDS_PER_DAY
+}
+
+// entry price moved by `percent`, e.g. -5 for a 5% stop loss
+fn offset_price(price: Price, percent: Decimal) -> Option<Price> {
+    let factor = Decimal::ONE.checked_add(percent.checked_div(Decimal::ONE_HUNDRED)?)?;
+    price.checked_scale(factor)
+}
+
+impl RiskManager {
+    pub fn new(config: RiskConfig) -> Self {
+        RiskManager {
+            config,
+            entry: None,
+            last_operation_at: None,
+            day: 0,
+            realized_today: Money::ZERO,
+            realized_total: Money::ZERO,
+        }
+    }
+
+    // quote currency the next buy may spend: a fraction of equity, capped by the free balance
+    pub fn position_budget(&self, balance: Money, position: Quantity, price: Price) -> Option<Money> {
+        let equity = balance.checked_add(price.checked_notional(position)?)?;
+        let budget = equity.checked_scale(self.config.position_fraction)?;
+        Some(budget.min(balance))
+    }
+
+    pub fn check_buy(&self, now: SystemTime) -> Result<(), RiskRejection> {
+        if let Some(limit) = self.config.max_daily_loss {
+            let loss = self.loss_today(now);
+            if loss >= limit && loss.is_positive() {
+                return Err(RiskRejection::Halted { loss });
+            }
+        }
+        self.check_cooldown(now)
+    }
+
+    pub fn check_sell(&self, now: SystemTime) -> Result<(), RiskRejection> {
+        self.check_cooldown(now)
+    }
+
+    fn check_cooldown(&self, now: SystemTime) -> Result<(), RiskRejection> {
+        let elapsed = match self.last_operation_at {
+            Some(at) => now.duration_since(at).unwrap_or_default(),
+            None => return Ok(()),
+        };
+        match self.config.cooldown.checked_sub(elapsed) {
+            Some(remaining) if !remaining.is_zero() => Err(RiskRejection::CoolingDown { remaining }),
+            _ => Ok(()),
+        }
+    }
+
+    // stop loss and take profit exits bypass both the strategy and the cooldown
+    pub fn exit_signal(&self, price: Price) -> Option<ExitReason> {
+        let entry = self.entry?;
+        if entry.stop_loss.is_some_and(|stop| price <= stop) {
+            Some(ExitReason::StopLoss)
+        } else if entry.take_profit.is_some_and(|take| price >= take) {
+            Some(ExitReason::TakeProfit)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_halted(&self, now: SystemTime) -> bool {
+        matches!(self.check_buy(now), Err(RiskRejection::Halted { .. }))
+    }
+
+    pub fn realized_pnl(&self) -> Money {
+        self.realized_total
+    }
+
+    pub fn loss_today(&self, now: SystemTime) -> Money {
+        if day_of(now) != self.day {
+            return Money::ZERO;
+        }
+        Money::ZERO.checked_sub(self.realized_today).unwrap_or(Money::ZERO)
+    }
+
+    pub fn record_buy(&mut self, price: Price, now: SystemTime) {
+        self.entry = Some(Entry {
+            price,
+            stop_loss: self.config.stop_loss.and_then(|percent| offset_price(price, -percent)),
+            take_profit: self.config.take_profit.and_then(|percent| offset_price(price, percent)),
+        });
+        self.last_operation_at = Some(now);
+    }
+
+    pub fn record_sell(&mut self, price: Price, quantity: Quantity, now: SystemTime) {
+        if day_of(now) != self.day {
+            self.day = day_of(now);
+            self.realized_today = Money::ZERO;
+        }
+        if let Some(entry) = self.entry.take() {
+            let pnl = price
+                .checked_notional(quantity)
+                .zip(entry.price.checked_notional(quantity))
+                .and_then(|(proceeds, cost)| proceeds.checked_sub(cost));
+            if let Some(pnl) = pnl {
+                self.realized_today = self.realized_today.checked_add(pnl).unwrap_or(self.realized_today);
+                self.realized_total = self.realized_total.checked_add(pnl).unwrap_or(self.realized_total);
+            }
+        }
+        self.last_operation_at = Some(now);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn p(s: &str) -> Price {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn sizes_positions_from_equity() {
+        let risk = RiskManager::new(RiskConfig::default());
+        // 1000 free + 0.01 * 40000 held = 1400 equity, a quarter of it is 350
+        let budget = risk.position_budget("1000".parse().unwrap(), "0.01".parse().unwrap(), p("40000"));
+        assert_eq!(budget, Some("350".parse().unwrap()));
+        let budget = risk.position_budget("100".parse().unwrap(), "0.1".parse().unwrap(), p("40000"));
+        assert_eq!(budget, Some("100".parse().unwrap()));
+    }
+
+    #[test]
+    fn stop_loss_and_take_profit_levels() {
+        let config = RiskConfig { take_profit: Some(Decimal::new(10, 0)), ..RiskConfig::default() };
+        let mut risk = RiskManager::new(config);
+        risk.record_buy(p("40000"), UNIX_EPOCH);
+        assert_eq!(risk.exit_signal(p("38500")), None);
+        assert_eq!(risk.exit_signal(p("38000")), Some(ExitReason::StopLoss));
+        assert_eq!(risk.exit_signal(p("44000")), Some(ExitReason::TakeProfit));
+    }
+
+    #[test]
+    fn cooldown_between_operations() {
+        let mut risk = RiskManager::new(RiskConfig::default());
+        let start = UNIX_EPOCH + Duration::from_secs(1000);
+        risk.record_buy(p("40000"), start);
+        assert_eq!(
+            risk.check_sell(start + Duration::from_secs(20)),
+            Err(RiskRejection::CoolingDown { remaining: Duration::from_secs(40) })
+        );
+        assert_eq!(risk.check_sell(start + Duration::from_secs(60)), Ok(()));
+    }
+
+    #[test]
+    fn daily_loss_halts_buying_until_the_next_day() {
+        let mut risk = RiskManager::new(RiskConfig { cooldown: Duration::ZERO, ..RiskConfig::default() });
+        let now = UNIX_EPOCH + Duration::from_secs(3 * SECONDS_PER_DAY + 100);
+        risk.record_buy(p("40000"), now);
+        risk.record_sell(p("38000"), "0.06".parse().unwrap(), now);
+        assert!(risk.is_halted(now));
+        assert_eq!(risk.check_buy(now), Err(RiskRejection::Halted { loss: "120".parse().unwrap() }));
+        assert_eq!(risk.check_buy(now + Duration::from_secs(SECONDS_PER_DAY)), Ok(()));
+    }
+}
@@ -0,0 +1,386 @@
+// a local stand-in for the REST exchange behind RestMarket, so the adapter is tested offline.
+// it speaks the API documented in src/market/rest.rs and checks signatures on its own, and
+// streams trades as documented in src/feed/websocket.rs.
+#![allow(dead_code)]
+
+use axum::body::Bytes;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::extract::{Query, State};
+use axum::http::{HeaderMap, Method, StatusCode, Uri};
+use axum::response::Response;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use trading_bot::market::rest::{API_KEY_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+
+pub const SYMBOL: &str = "BTCUSD";
+pub const API_KEY: &str = "test-key";
+pub const API_SECRET: &str = "test-secret";
+
+#[derive(Debug)]
+pub struct Exchange {
+    pub price: Decimal,
+    pub balances: HashMap<String, Decimal>,
+    pub orders: Vec<MockOrder>,
+    // every trade published, as sent to the WebSocket subscribers
+    pub trades: broadcast::Sender<String>,
+    // drops every feed connection without closing it, as a network failure would
+    pub drops: broadcast::Sender<()>,
+}
+
+pub struct MockExchange {
+    pub base_url: String,
+    pub exchange: Arc<Mutex<Exchange>>,
+    address: SocketAddr,
+    server: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl MockExchange {
+    pub async fn start(price: &str, usd: &str) -> MockExchange {
+        let mut balances = HashMap::new();
+        balances.insert("USD".to_string(), usd.parse().unwrap());
+        balances.insert("BTC".to_string(), Decimal::ZERO);
+        let (trades, _) = broadcast::channel(64);
+        let (drops, _) = broadcast::channel(1);
+        let exchange = Exchange { price: price.parse().unwrap(), balances, orders: Vec::new(), trades, drops };
+        let exchange = Arc::new(Mutex::new(exchange));
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = serve(listener, exchange.clone());
+        MockExchange { base_url: format!("http://{}", address), exchange, address, server: Mutex::new(Some(server)) }
+    }
+
+    // goes down as a crashed server would, taking every connection with it
+    pub fn stop(&self) {
+        if let Some(server) = self.server.lock().unwrap().take() {
+            server.abort();
+        }
+        let _ = self.exchange.lock().unwrap().drops.send(());
+    }
+
+    // back up on the same port, with the same state
+    pub async fn start_again(&self) {
+        let listener = tokio::net::TcpListener::bind(self.address).await.unwrap();
+        *self.server.lock().unwrap() = Some(serve(listener, self.exchange.clone()));
+    }
+
+    pub fn set_price(&self, price: &str) {
+        self.exchange.lock().unwrap().price = price.parse().unwrap();
+    }
+
+    pub fn feed_url(&self) -> String {
+        format!("{}/ws", self.base_url.replace("http://", "ws://"))
+    }
+
+    // a trade moves the ticker price, fills the resting orders it reaches and goes out to every
+    // feed subscriber
+    pub fn publish(&self, price: &str, quantity: &str) {
+        let mut exchange = self.exchange.lock().unwrap();
+        exchange.price = price.parse().unwrap();
+        let (price, quantity) = (exchange.price, quantity.parse().unwrap());
+        exchange.trade(price, quantity);
+        let _ = exchange.trades.send(trade(price, quantity));
+    }
+
+    // a message to every feed subscriber that is not a trade, such as a heartbeat
+    pub fn broadcast(&self, message: Value) {
+        let _ = self.exchange.lock().unwrap().trades.send(message.to_string());
+    }
+
+    pub fn balance(&self, asset: &str) -> Decimal {
+        self.exchange.lock().unwrap().balances[asset]
+    }
+}
+
+fn serve(listener: tokio::net::TcpListener, exchange: Arc<Mutex<Exchange>>) -> JoinHandle<()> {
+    let app = Router::new()
+        .route("/api/v1/ticker", get(ticker))
+        .route("/api/v1/balance", get(balance))
+        .route("/api/v1/order", post(order).get(order_status).delete(order_status))
+        .route("/ws", get(feed))
+        .with_state(exchange);
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() })
+}
+
+type Reply = (StatusCode, Json<Value>);
+
+fn error(status: StatusCode, code: &str, message: &str) -> Reply {
+    (status, Json(json!({ "code": code, "message": message })))
+}
+
+fn verify(method: &Method, uri: &Uri, headers: &HeaderMap, body: &[u8]) -> Result<(), Reply> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).unwrap_or("").to_string();
+    if header(API_KEY_HEADER) != API_KEY {
+        return Err(error(StatusCode::UNAUTHORIZED, "invalid_key", "unknown API key"));
+    }
+    let timestamp: u64 = header(TIMESTAMP_HEADER).parse().unwrap_or(0);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    if now.abs_diff(timestamp) > 30_000 {
+        return Err(error(StatusCode::UNAUTHORIZED, "stale_request", "timestamp outside the allowed window"));
+    }
+    let mut mac = Hmac::<Sha256>::new_from_slice(API_SECRET.as_bytes()).unwrap();
+    let path_and_query = uri.path_and_query().map(|p| p.as_str()).unwrap_or("");
+    mac.update(format!("{}{}{}", timestamp, method, path_and_query).as_bytes());
+    mac.update(body);
+    let signature = hex::decode(header(SIGNATURE_HEADER)).unwrap_or_default();
+    mac.verify_slice(&signature).map_err(|_| error(StatusCode::UNAUTHORIZED, "invalid_signature", "signature mismatch"))
+}
+
+#[derive(Deserialize)]
+struct TickerQuery {
+    symbol: String,
+}
+
+async fn ticker(State(exchange): State<Arc<Mutex<Exchange>>>, Query(query): Query<TickerQuery>) -> Reply {
+    if query.symbol != SYMBOL {
+        return error(StatusCode::NOT_FOUND, "unknown_symbol", "no such symbol");
+    }
+    let price = exchange.lock().unwrap().price;
+    (StatusCode::OK, Json(json!({ "symbol": SYMBOL, "price": price.to_string() })))
+}
+
+#[derive(Deserialize)]
+struct BalanceQuery {
+    asset: String,
+}
+
+async fn balance(
+    State(exchange): State<Arc<Mutex<Exchange>>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<BalanceQuery>,
+) -> Reply {
+    if let Err(reply) = verify(&method, &uri, &headers, b"") {
+        return reply;
+    }
+    match exchange.lock().unwrap().balances.get(&query.asset) {
+        Some(free) => (StatusCode::OK, Json(json!({ "asset": query.asset, "free": free.to_string() }))),
+        None => error(StatusCode::NOT_FOUND, "unknown_asset", "no such asset"),
+    }
+}
+
+#[derive(Deserialize)]
+struct OrderBody {
+    symbol: String,
+    side: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    quantity: Decimal,
+    price: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MockOrder {
+    pub id: String,
+    pub side: String,
+    pub quantity: Decimal,
+    pub filled: Decimal,
+    // total paid or received for the fills
+    pub value: Decimal,
+    pub limit: Option<Decimal>,
+    pub status: String,
+}
+
+impl MockOrder {
+    fn reply(&self) -> Value {
+        let price = if self.filled.is_zero() { Value::Null } else { json!((self.value / self.filled).normalize().to_string()) };
+        json!({
+            "order_id": self.id,
+            "side": self.side,
+            "status": self.status,
+            "quantity": self.quantity.to_string(),
+            "filled_quantity": self.filled.to_string(),
+            "price": price,
+            "limit_price": self.limit.map(|limit| limit.to_string()),
+        })
+    }
+
+    fn is_open(&self) -> bool {
+        self.status == "open" || self.status == "partially_filled"
+    }
+}
+
+impl Exchange {
+    // fills up to `quantity` of order `idx` at `price`. the funds of a limit buy were locked at
+    // its limit, a better price gives some back.
+    fn fill(&mut self, idx: usize, price: Decimal, quantity: Decimal) {
+        let order = &mut self.orders[idx];
+        let quantity = quantity.min(order.quantity - order.filled);
+        let locked_price = order.limit.unwrap_or(price);
+        match order.side.as_str() {
+            "buy" => {
+                *self.balances.get_mut("USD").unwrap() += (locked_price - price) * quantity;
+                *self.balances.get_mut("BTC").unwrap() += quantity;
+            }
+            _ => *self.balances.get_mut("USD").unwrap() += price * quantity,
+        }
+        order.filled += quantity;
+        order.value += price * quantity;
+        order.status = if order.filled == order.quantity { "filled" } else { "partially_filled" }.to_string();
+    }
+
+    // a trade of `quantity` at `price` fills the resting orders it reaches
+    fn trade(&mut self, price: Decimal, mut quantity: Decimal) {
+        for idx in 0..self.orders.len() {
+            let order = &self.orders[idx];
+            let reached = match (order.side.as_str(), order.limit) {
+                ("buy", Some(limit)) => price <= limit,
+                ("sell", Some(limit)) => price >= limit,
+                _ => false,
+            };
+            if order.is_open() && reached && quantity > Decimal::ZERO {
+                let before = order.filled;
+                let limit = order.limit.unwrap();
+                self.fill(idx, limit, quantity);
+                quantity -= self.orders[idx].filled - before;
+            }
+        }
+    }
+}
+
+async fn order(
+    State(exchange): State<Arc<Mutex<Exchange>>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Reply {
+    if let Err(reply) = verify(&method, &uri, &headers, &body) {
+        return reply;
+    }
+    let order: OrderBody = match serde_json::from_slice(&body) {
+        Ok(order) => order,
+        Err(e) => return error(StatusCode::BAD_REQUEST, "bad_request", &e.to_string()),
+    };
+    let limit = match (order.order_type.as_str(), order.price) {
+        ("market", None) => None,
+        ("limit", Some(price)) => Some(price),
+        _ => return error(StatusCode::BAD_REQUEST, "bad_request", "market orders take no price, limit orders need one"),
+    };
+    if order.symbol != SYMBOL {
+        return error(StatusCode::BAD_REQUEST, "bad_request", "only BTCUSD is traded");
+    }
+
+    let mut exchange = exchange.lock().unwrap();
+    let price = exchange.price;
+    // the whole order is paid for up front, a limit order at its limit
+    let (pay, pay_amount) = match order.side.as_str() {
+        "buy" => ("USD", limit.unwrap_or(price) * order.quantity),
+        "sell" => ("BTC", order.quantity),
+        _ => return error(StatusCode::BAD_REQUEST, "bad_request", "side must be buy or sell"),
+    };
+    if exchange.balances[pay] < pay_amount {
+        return error(StatusCode::BAD_REQUEST, "insufficient_balance", &format!("not enough {}", pay));
+    }
+    *exchange.balances.get_mut(pay).unwrap() -= pay_amount;
+
+    let idx = exchange.orders.len();
+    exchange.orders.push(MockOrder {
+        id: (idx + 1).to_string(),
+        side: order.side.clone(),
+        quantity: order.quantity,
+        filled: Decimal::ZERO,
+        value: Decimal::ZERO,
+        limit,
+        status: "open".to_string(),
+    });
+    let crosses = match (order.side.as_str(), limit) {
+        (_, None) => true,
+        ("buy", Some(limit)) => price <= limit,
+        (_, Some(limit)) => price >= limit,
+    };
+    if crosses {
+        exchange.fill(idx, price, order.quantity);
+    }
+    (StatusCode::OK, Json(exchange.orders[idx].reply()))
+}
+
+#[derive(Deserialize)]
+struct OrderQuery {
+    symbol: String,
+    order_id: String,
+}
+
+async fn order_status(
+    State(exchange): State<Arc<Mutex<Exchange>>>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    Query(query): Query<OrderQuery>,
+) -> Reply {
+    if let Err(reply) = verify(&method, &uri, &headers, b"") {
+        return reply;
+    }
+    let mut exchange = exchange.lock().unwrap();
+    let idx = match exchange.orders.iter().position(|order| order.id == query.order_id && query.symbol == SYMBOL) {
+        Some(idx) => idx,
+        None => return error(StatusCode::NOT_FOUND, "unknown_order", "no such order"),
+    };
+    if method == Method::DELETE && exchange.orders[idx].is_open() {
+        let order = &mut exchange.orders[idx];
+        order.status = "cancelled".to_string();
+        let refund = match order.side.as_str() {
+            "buy" => ("USD", order.limit.unwrap_or_default() * (order.quantity - order.filled)),
+            _ => ("BTC", order.quantity - order.filled),
+        };
+        *exchange.balances.get_mut(refund.0).unwrap() += refund.1;
+    }
+    (StatusCode::OK, Json(exchange.orders[idx].reply()))
+}
+
+fn trade(price: Decimal, quantity: Decimal) -> String {
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_millis() as u64;
+    json!({ "symbol": SYMBOL, "price": price.to_string(), "quantity": quantity.to_string(), "time": time }).to_string()
+}
+
+async fn feed(State(exchange): State<Arc<Mutex<Exchange>>>, upgrade: WebSocketUpgrade) -> Response {
+    upgrade.on_upgrade(move |socket| stream_trades(exchange, socket))
+}
+
+// waits for the subscription, acknowledges it, then sends the last price followed by every
+// published trade
+async fn stream_trades(exchange: Arc<Mutex<Exchange>>, mut socket: WebSocket) {
+    let request: Value = match socket.recv().await {
+        Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap_or_default(),
+        _ => return,
+    };
+    if request["op"] != "subscribe" || request["symbol"] != SYMBOL {
+        let _ = socket.send(Message::Close(None)).await;
+        return;
+    }
+    let (snapshot, mut trades, mut drops) = {
+        let exchange = exchange.lock().unwrap();
+        (trade(exchange.price, Decimal::ZERO), exchange.trades.subscribe(), exchange.drops.subscribe())
+    };
+    let ack = json!({ "op": "subscribed", "symbol": SYMBOL }).to_string();
+    for message in [ack, snapshot] {
+        if socket.send(Message::Text(message.into())).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        let trade = tokio::select! {
+            trade = trades.recv() => match trade {
+                Ok(trade) => trade,
+                Err(_) => return,
+            },
+            // the socket goes without a close frame
+            _ = drops.recv() => return,
+        };
+        if socket.send(Message::Text(trade.into())).await.is_err() {
+            return;
+        }
+    }
+}
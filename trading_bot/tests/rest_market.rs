@@ -0,0 +1,179 @@
+mod common;
+
+use common::{MockExchange, API_KEY, API_SECRET, SYMBOL};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use std::time::Duration;
+use trading_bot::bot::OrderKind;
+use trading_bot::journal::{self, Journal, JournalEvent};
+use trading_bot::market::{OrderStatus, PaperMarket, RestMarket, Side, Wallet};
+use trading_bot::{Market, Quantity, RiskConfig, State, SymbolRules, TradingBot, TradingConfig};
+
+fn market(exchange: &MockExchange, secret: &str) -> RestMarket {
+    RestMarket::new(&exchange.base_url, SYMBOL, "USD", API_KEY, secret)
+}
+
+#[tokio::test]
+async fn reads_price_and_balance() {
+    let exchange = MockExchange::start("42000.5", "1000").await;
+    let market = market(&exchange, API_SECRET);
+    assert_eq!(market.get_market_price().await.unwrap(), "42000.5".parse().unwrap());
+    assert_eq!(market.get_balance().await.unwrap(), "1000".parse().unwrap());
+}
+
+#[tokio::test]
+async fn encodes_the_query_it_signs() {
+    let exchange = MockExchange::start("42000.5", "1000").await;
+    // signed as sent and read back whole, an asset the exchange does not know
+    let market = RestMarket::new(&exchange.base_url, SYMBOL, "US D&x=1", API_KEY, API_SECRET);
+    let error = market.get_balance().await.unwrap_err();
+    assert_eq!(error.to_string(), "exchange returned 404 Not Found: no such asset (unknown_asset)");
+}
+
+#[tokio::test]
+async fn market_orders_fill_at_the_ticker_price() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let market = market(&exchange, API_SECRET);
+
+    let price = market.place_buy_order("0.02".parse().unwrap()).await.unwrap();
+    assert_eq!(price, "40000".parse().unwrap());
+    assert_eq!(exchange.balance("USD"), "200".parse::<Decimal>().unwrap());
+    assert_eq!(exchange.balance("BTC"), "0.02".parse::<Decimal>().unwrap());
+
+    exchange.set_price("41000.25");
+    let price = market.place_sell_order("0.02".parse().unwrap()).await.unwrap();
+    assert_eq!(price, "41000.25".parse().unwrap());
+    assert_eq!(exchange.balance("USD"), "1020.005".parse::<Decimal>().unwrap());
+}
+
+#[tokio::test]
+async fn rejects_requests_signed_with_the_wrong_secret() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let error = market(&exchange, "wrong").get_balance().await.unwrap_err();
+    assert_eq!(error.to_string(), "exchange returned 401 Unauthorized: signature mismatch (invalid_signature)");
+    // the ticker is public
+    assert!(market(&exchange, "wrong").get_market_price().await.is_ok());
+}
+
+#[tokio::test]
+async fn gives_up_on_a_hung_exchange() {
+    // takes connections and never answers
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let market = RestMarket::new(&base_url, SYMBOL, "USD", API_KEY, API_SECRET).with_timeout(Duration::from_millis(200));
+    let error = tokio::time::timeout(Duration::from_secs(5), market.get_market_price()).await.unwrap().unwrap_err();
+    assert!(error.to_string().starts_with("GET /api/v1/ticker?symbol=BTCUSD failed"), "{}", error);
+}
+
+#[tokio::test]
+async fn surfaces_exchange_errors() {
+    let exchange = MockExchange::start("40000", "100").await;
+    let error = market(&exchange, API_SECRET).place_buy_order("1".parse().unwrap()).await.unwrap_err();
+    assert_eq!(error.to_string(), "exchange returned 400 Bad Request: not enough USD (insufficient_balance)");
+    assert!(exchange.exchange.lock().unwrap().orders.is_empty());
+}
+
+#[tokio::test]
+async fn bot_trades_through_the_adapter() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let risk = RiskConfig { position_fraction: Decimal::ONE, ..RiskConfig::default() };
+    let config = TradingConfig { risk, ..TradingConfig::default() };
+    let mut bot = TradingBot::new(config, Box::new(market(&exchange, API_SECRET)));
+
+    bot.run_cycle().await.unwrap();
+    exchange.set_price("38000");
+    bot.run_cycle().await.unwrap();
+
+    assert_eq!(bot.trading_config.next_operation, State::Sell);
+    assert_eq!(bot.trading_config.position, "0.02631".parse().unwrap());
+    assert_eq!(exchange.balance("BTC"), "0.02631".parse::<Decimal>().unwrap());
+    assert_eq!(exchange.exchange.lock().unwrap().orders.len(), 1);
+}
+
+#[tokio::test]
+async fn follows_limit_orders_until_cancelled() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let market = market(&exchange, API_SECRET);
+
+    let order = market.place_limit_order(Side::Buy, "0.02".parse().unwrap(), "39000".parse().unwrap()).await.unwrap();
+    assert_eq!((order.status, order.filled_quantity), (OrderStatus::Open, Quantity::ZERO));
+    assert_eq!(exchange.balance("USD"), "220".parse::<Decimal>().unwrap());
+
+    exchange.publish("38900", "0.005");
+    let order = market.get_order(&order.id).await.unwrap();
+    assert_eq!(order.status, OrderStatus::PartiallyFilled);
+    assert_eq!(order.filled_quantity, "0.005".parse().unwrap());
+    assert_eq!(order.average_price, Some("39000".parse().unwrap()));
+
+    let order = market.cancel_order(&order.id).await.unwrap();
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert_eq!(exchange.balance("USD"), "805".parse::<Decimal>().unwrap());
+    assert_eq!(exchange.balance("BTC"), "0.005".parse::<Decimal>().unwrap());
+
+    let error = market.get_order("42").await.unwrap_err();
+    assert_eq!(error.to_string(), "exchange returned 404 Not Found: no such order (unknown_order)");
+}
+
+#[tokio::test]
+async fn paper_trades_live_prices_without_placing_orders() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let dir = tempfile::tempdir().unwrap();
+    let journal = dir.path().join("paper.jsonl");
+    let wallet = Arc::new(Wallet::new("500".parse().unwrap()));
+    let paper = PaperMarket::new(Box::new(market(&exchange, API_SECRET)), wallet.clone(), SymbolRules::default());
+    let orders = OrderKind::Limit { offset: Decimal::new(1, 1), timeout: Duration::from_secs(600) };
+    let risk = RiskConfig { position_fraction: Decimal::ONE, ..RiskConfig::default() };
+    let config = TradingConfig { orders, risk, ..TradingConfig::default() };
+    let mut bot = TradingBot::new(config, Box::new(paper)).with_journal(&journal).unwrap();
+
+    bot.run_cycle().await.unwrap();
+    exchange.set_price("38000");
+    bot.run_cycle().await.unwrap();
+    // the dip buy rests at 37962 until the live price comes down to it
+    assert!(bot.open_order.is_some());
+    assert_eq!(wallet.balance(), "0.04046".parse().unwrap());
+    exchange.set_price("37950");
+    bot.run_cycle().await.unwrap();
+
+    assert_eq!(bot.trading_config.next_operation, State::Sell);
+    assert_eq!(bot.trading_config.position, "0.01317".parse().unwrap());
+    assert!(exchange.exchange.lock().unwrap().orders.is_empty());
+    assert_eq!(exchange.balance("USD"), "1000".parse::<Decimal>().unwrap());
+
+    // the journal is the one a live bot keeps
+    let records = Journal::read(&journal).unwrap();
+    assert!(records.iter().any(|record| matches!(record.event, JournalEvent::OrderPlaced { .. })));
+    assert!(records.iter().any(|record| matches!(record.event, JournalEvent::Order { side: Side::Buy, .. })));
+}
+
+#[tokio::test]
+async fn paper_trading_picks_up_after_a_restart() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let dir = tempfile::tempdir().unwrap();
+    let journal = dir.path().join("paper.jsonl");
+    let risk = RiskConfig { position_fraction: Decimal::ONE, cooldown: Duration::ZERO, ..RiskConfig::default() };
+    let config = TradingConfig { risk, ..TradingConfig::default() };
+    let wallet = Arc::new(Wallet::new("500".parse().unwrap()));
+    let paper = PaperMarket::new(Box::new(market(&exchange, API_SECRET)), wallet, SymbolRules::default());
+    let mut bot = TradingBot::new(config.clone(), Box::new(paper)).with_journal(&journal).unwrap();
+    bot.run_cycle().await.unwrap();
+    exchange.set_price("38000");
+    bot.run_cycle().await.unwrap();
+    assert_eq!(bot.trading_config.position, "0.01315".parse().unwrap());
+    drop(bot);
+
+    // a fresh wallet and ledger, as the paper command builds them, from the journal
+    let account = journal::replay_account(&Journal::read(&journal).unwrap(), "500".parse().unwrap()).unwrap();
+    let wallet = Arc::new(Wallet::new(account.balance));
+    let paper = PaperMarket::new(Box::new(market(&exchange, API_SECRET)), wallet.clone(), SymbolRules::default())
+        .with_holdings(account.holdings(SYMBOL));
+    let mut bot = TradingBot::new(config, Box::new(paper)).with_journal(&journal).unwrap();
+    assert_eq!(bot.trading_config.next_operation, State::Sell);
+    exchange.set_price("39000");
+    bot.run_cycle().await.unwrap();
+
+    assert_eq!(bot.trading_config.next_operation, State::Buy);
+    // 500 - 0.01315 * 38000 + 0.01315 * 39000
+    assert_eq!(wallet.balance(), "513.15".parse().unwrap());
+    assert_eq!(exchange.balance("USD"), "1000".parse::<Decimal>().unwrap());
+}
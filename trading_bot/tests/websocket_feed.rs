@@ -0,0 +1,97 @@
+mod common;
+
+use common::{MockExchange, API_KEY, API_SECRET, SYMBOL};
+use futures_util::StreamExt;
+use rust_decimal::Decimal;
+use serde_json::json;
+use std::time::Duration;
+use trading_bot::feed::{candles, WebSocketFeed};
+use trading_bot::market::RestMarket;
+use trading_bot::{PriceFeed, RiskConfig, State, TradingBot, TradingConfig};
+
+#[tokio::test]
+async fn streams_trades_after_the_last_price() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let mut ticks = WebSocketFeed::new(&exchange.feed_url()).subscribe(SYMBOL).await.unwrap();
+
+    let snapshot = ticks.next().await.unwrap().unwrap();
+    assert_eq!(snapshot.price, "40000".parse().unwrap());
+    exchange.publish("40100.5", "0.25");
+    let tick = ticks.next().await.unwrap().unwrap();
+    assert_eq!((tick.symbol.as_str(), tick.price, tick.quantity), (SYMBOL, "40100.5".parse().unwrap(), "0.25".parse().unwrap()));
+}
+
+#[tokio::test]
+async fn skips_messages_that_are_not_trades() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    // the subscription is acknowledged before the last price comes
+    let mut ticks = WebSocketFeed::new(&exchange.feed_url()).subscribe(SYMBOL).await.unwrap();
+    assert_eq!(ticks.next().await.unwrap().unwrap().price, "40000".parse().unwrap());
+
+    exchange.broadcast(json!({ "op": "heartbeat" }));
+    exchange.broadcast(json!({ "op": "error", "message": "slow down" }));
+    exchange.publish("40100", "1");
+    assert_eq!(ticks.next().await.unwrap().unwrap().price, "40100".parse().unwrap());
+}
+
+#[tokio::test]
+async fn ends_when_the_exchange_closes_the_stream() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let mut ticks = WebSocketFeed::new(&exchange.feed_url()).subscribe("DOGEUSD").await.unwrap();
+    assert!(ticks.next().await.is_none());
+
+    let error = WebSocketFeed::new("ws://127.0.0.1:1/ws").subscribe(SYMBOL).await.err().unwrap();
+    assert!(error.to_string().starts_with("cannot connect to ws://127.0.0.1:1/ws"), "{}", error);
+}
+
+#[tokio::test]
+async fn reconnects_after_the_exchange_restarts() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let feed = WebSocketFeed::new(&exchange.feed_url());
+    let feed = feed.with_backoff(Duration::from_millis(10), Duration::from_millis(50));
+    let mut ticks = feed.subscribe(SYMBOL).await.unwrap();
+    ticks.next().await.unwrap().unwrap();
+
+    exchange.stop();
+    exchange.set_price("40500");
+    // down for long enough that the feed fails to connect a few times
+    let restart = async {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        exchange.start_again().await;
+    };
+    let (snapshot, ()) = tokio::join!(tokio::time::timeout(Duration::from_secs(5), ticks.next()), restart);
+    assert_eq!(snapshot.unwrap().unwrap().unwrap().price, "40500".parse().unwrap());
+    exchange.publish("40600", "1");
+    assert_eq!(ticks.next().await.unwrap().unwrap().price, "40600".parse().unwrap());
+}
+
+#[tokio::test]
+async fn aggregates_live_trades_into_candles() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let mut ticks = WebSocketFeed::new(&exchange.feed_url()).subscribe(SYMBOL).await.unwrap();
+    ticks.next().await.unwrap().unwrap();
+    for price in ["40100", "39900", "40050"] {
+        exchange.publish(price, "1");
+    }
+    // every trade lands in the same hour, the candle comes out once the feed is dropped
+    let mut candles = candles(Box::pin(ticks.take(3)), Duration::from_secs(3600));
+    let candle = candles.next().await.unwrap().unwrap();
+    assert_eq!((candle.high, candle.low, candle.close), ("40100".parse().unwrap(), "39900".parse().unwrap(), "40050".parse().unwrap()));
+    assert_eq!(candle.volume, "3".parse().unwrap());
+}
+
+#[tokio::test]
+async fn bot_trades_on_streamed_prices() {
+    let exchange = MockExchange::start("40000", "1000").await;
+    let risk = RiskConfig { position_fraction: Decimal::ONE, ..RiskConfig::default() };
+    let market = RestMarket::new(&exchange.base_url, SYMBOL, "USD", API_KEY, API_SECRET);
+    let mut bot = TradingBot::new(TradingConfig { risk, ..TradingConfig::default() }, Box::new(market));
+
+    let mut ticks = WebSocketFeed::new(&exchange.feed_url()).subscribe(SYMBOL).await.unwrap();
+    bot.on_tick(&ticks.next().await.unwrap().unwrap()).await.unwrap();
+    exchange.publish("38000", "0.5");
+    bot.on_tick(&ticks.next().await.unwrap().unwrap()).await.unwrap();
+
+    assert_eq!(bot.trading_config.next_operation, State::Sell);
+    assert_eq!(exchange.balance("BTC"), "0.02631".parse::<Decimal>().unwrap());
+}